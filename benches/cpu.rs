@@ -0,0 +1,61 @@
+//! Instructions per second of the CPU, running a loop of common
+//! instructions from RAM, on the NES bus and on flat memory.
+//! Run with `cargo bench --bench cpu`.
+
+use std::time::{Duration, Instant};
+
+use lib::{
+    assembler::assemble,
+    cpu::{FlatMemory, Memory},
+    Cpu, Nes, Reset,
+};
+
+/// A mix of loads, stores, arithmetic, read-modify-writes and branches
+const PROGRAM: &str = "
+    .org $0200
+    loop:   lda $10
+            clc
+            adc #3
+            sta $10
+            ldx #8
+    inner:  rol $20,x
+            inc $0300,x
+            lda ($30),y
+            dex
+            bne inner
+            jmp loop
+";
+
+const INSTRUCTIONS: u64 = 5_000_000;
+const RUNS: usize = 5;
+
+fn main() {
+    let program = assemble(PROGRAM).expect("valid benchmark program");
+
+    let nes = Nes::default();
+    program.write_to(|address, data| nes.bus.borrow_mut().write_cpu(address, data));
+    measure("NES bus", &mut nes.cpu_mut(), program.origin);
+
+    let memory = FlatMemory::with_program(program.origin, &program.bytes);
+    measure("Flat memory", &mut Cpu::with_memory(memory), program.origin);
+}
+
+fn measure<M: Memory>(name: &str, cpu: &mut Cpu<M>, start: u16) {
+    cpu.reset();
+    cpu.program_counter = start;
+
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        for _ in 0..INSTRUCTIONS {
+            cpu.step();
+        }
+        best = best.min(start.elapsed());
+    }
+
+    let per_second = INSTRUCTIONS as f64 / best.as_secs_f64();
+    println!(
+        "{name}: {INSTRUCTIONS} instructions in {best:?}, {:.2} million instructions per second",
+        per_second / 1_000_000.0
+    );
+}
@@ -0,0 +1,194 @@
+//! Generate `src/lib/database/database.xml`, the ROM database bundled with
+//! the emulator.
+//!
+//! The games come from `game_database.txt` of
+//! [TetaNES](https://github.com/lukexor/tetanes) (MIT or Apache-2.0), as
+//! shipped in the tetanes-core 0.10.0 crate. It lists the No-Intro NES set
+//! with the CRC32 of each ROM without its header, its region, mapper,
+//! mirroring and battery. An export of [NesCartDB](https://nescartdb.com)
+//! can be given as well to fill in publishers and board names.
+//! Run with
+//!
+//! ```text
+//! cargo run --example import_database -- game_database.txt [nescartdb.xml] \
+//!     > src/lib/database/database.xml
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use lib::database::RomDatabase;
+
+/// Mappers whose boards wire the mirroring, rather than switch it
+const FIXED_MIRRORING_MAPPERS: [u16; 5] = [0, 2, 3, 34, 66];
+
+struct Game {
+    name: String,
+    publisher: Option<String>,
+    board: Option<String>,
+    system: &'static str,
+    mapper: u16,
+    /// `H`, `V` or `4`
+    mirroring: Option<&'static str>,
+    battery: bool,
+}
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let (tetanes, nes_cart_db) = match arguments.as_slice() {
+        [tetanes] => (tetanes, None),
+        [tetanes, nes_cart_db] => (tetanes, Some(nes_cart_db)),
+        _ => {
+            eprintln!("Usage: import_database <game_database.txt> [nescartdb.xml]");
+            std::process::exit(2);
+        }
+    };
+
+    let text = std::fs::read_to_string(tetanes).expect("TetaNES database should be readable");
+    let mut games = BTreeMap::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (crc32, game) = parse_line(line)
+            .unwrap_or_else(|| panic!("Line {} is not a game: {}", line_number + 1, line));
+        // The first entry of a CRC wins, as it does in TetaNES
+        games.entry(crc32).or_insert(game);
+    }
+
+    if let Some(path) = nes_cart_db {
+        let xml = std::fs::read_to_string(path).expect("NesCartDB export should be readable");
+        let database = RomDatabase::from_xml(&xml).expect("NesCartDB export should be valid");
+        for (&crc32, game) in games.iter_mut() {
+            if let Some(known) = database.find_crc32(crc32) {
+                game.publisher = known.publisher.clone();
+                game.board = known.board.clone();
+            }
+        }
+    }
+
+    print!("{}", to_xml(&games, nes_cart_db.is_some()));
+}
+
+/// `  D26EFD78, NTSC, Mapper 066 - GxROM/MxROM/0, 2, 0, 0, false, Vertical, "Title.nes"`
+///
+/// The columns are the CRC32, region, mapper, three ROM and RAM sizes, battery,
+/// mirroring and file name. The sizes are not needed.
+fn parse_line(line: &str) -> Option<(u32, Game)> {
+    let columns: Vec<&str> = line.splitn(9, ", ").collect();
+    let [crc32, region, mapper, _, _, _, battery, mirroring, file_name] = columns[..] else {
+        return None;
+    };
+
+    let crc32 = u32::from_str_radix(crc32, 16).ok()?;
+    let mapper = mapper.strip_prefix("Mapper ")?.get(..3)?.parse().ok()?;
+    let name = file_name
+        .strip_prefix('"')?
+        .strip_suffix('"')?
+        .strip_suffix(".nes")?
+        .to_string();
+    let system = match region {
+        // Famicom and NES-NTSC only differ in name, but the name is shown
+        "NTSC" if name.contains("(Japan") => "Famicom",
+        "NTSC" => "NES-NTSC",
+        "PAL" => "NES-PAL",
+        _ => return None,
+    };
+    let mirroring = match mirroring {
+        "FourScreen" => Some("4"),
+        _ if !FIXED_MIRRORING_MAPPERS.contains(&mapper) => None,
+        "Horizontal" => Some("H"),
+        "Vertical" => Some("V"),
+        _ => None,
+    };
+    let battery = match battery {
+        "true" => true,
+        "false" => false,
+        _ => return None,
+    };
+
+    Some((
+        crc32,
+        Game {
+            name,
+            publisher: None,
+            board: None,
+            system,
+            mapper,
+            mirroring,
+            battery,
+        },
+    ))
+}
+
+fn to_xml(games: &BTreeMap<u32, Game>, with_nes_cart_db: bool) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!--
+  ROM database bundled with the emulator, see `RomDatabase` for the format.
+  Entries are keyed by the CRC32 of the ROM without its header, the same
+  value shown as CRC32 in the Cartridge Info panel.
+
+  Generated by examples/import_database.rs from game_database.txt of
+  TetaNES (https://github.com/lukexor/tetanes, MIT or Apache-2.0), as
+  shipped in tetanes-core 0.10.0, which lists the No-Intro NES set.
+"#,
+    );
+    if with_nes_cart_db {
+        xml.push_str("  Publishers and boards are from NesCartDB (https://nescartdb.com).\n");
+    }
+    xml.push_str(
+        r#"
+  Japanese releases are marked Famicom. Mirroring is only kept for four-screen
+  boards and for mappers 0, 2, 3, 34 and 66, whose boards wire it; other
+  mappers switch it themselves.
+-->
+<database version="1">
+"#,
+    );
+
+    let mut sorted: Vec<_> = games.iter().collect();
+    sorted.sort_by_key(|(crc32, game)| (game.name.to_lowercase(), **crc32));
+    for (crc32, game) in sorted {
+        write!(xml, "  <game name={}", quote(&game.name)).unwrap();
+        if let Some(publisher) = &game.publisher {
+            write!(xml, " publisher={}", quote(publisher)).unwrap();
+        }
+        xml.push_str(">\n");
+        writeln!(
+            xml,
+            "    <cartridge system=\"{}\" crc=\"{:08X}\">",
+            game.system, crc32
+        )
+        .unwrap();
+        xml.push_str("      <board");
+        if let Some(board) = &game.board {
+            write!(xml, " type={}", quote(board)).unwrap();
+        }
+        write!(xml, " mapper=\"{}\"", game.mapper).unwrap();
+        if let Some(mirroring) = game.mirroring {
+            write!(xml, " mirroring=\"{}\"", mirroring).unwrap();
+        }
+        writeln!(xml, " battery=\"{}\"/>", game.battery as u8).unwrap();
+        xml.push_str("    </cartridge>\n  </game>\n");
+    }
+    xml.push_str("</database>\n");
+    xml
+}
+
+/// `value` as a double-quoted XML attribute
+fn quote(value: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '&' => quoted.push_str("&amp;"),
+            '<' => quoted.push_str("&lt;"),
+            '>' => quoted.push_str("&gt;"),
+            '"' => quoted.push_str("&quot;"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Any input must parse or fail with a `CartridgeParseError`, never panic.
+// Run with `cargo +nightly fuzz run cartridge` from the repository root.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut cartridge) = lib::Cartridge::try_from(data) {
+        for address in [0x4020, 0x6000, 0x7FFF, 0x8000, 0xFFFF] {
+            cartridge.cpu_read(address);
+        }
+        cartridge.ppu_read(0x1FFF);
+    }
+});
@@ -0,0 +1,479 @@
+use std::{collections::HashMap, fmt::Display};
+
+use super::expression::{evaluate, is_identifier};
+use crate::{
+    cpu::AddressingMode,
+    opcodes::{OpCode, OpCodeType},
+};
+
+/// # Assembler
+/// A small 6502 assembler, for building test programs and for patching
+/// instructions into memory.
+///
+/// Each line holds an optional `label:`, then an instruction or a directive,
+/// then an optional `; comment`. Mnemonics and registers are case
+/// insensitive, labels aren't.
+///
+/// Operands use the usual syntax for each addressing mode:
+///
+/// | Syntax          | Mode                        |
+/// |-----------------|-----------------------------|
+/// |                 | Implied                     |
+/// | `A`             | Accumulator (implied)       |
+/// | `#value`        | Immediate                   |
+/// | `value`         | Zero page, absolute, branch |
+/// | `value,X`       | Zero page or absolute, X    |
+/// | `value,Y`       | Zero page or absolute, Y    |
+/// | `(value)`       | Indirect                    |
+/// | `(value,X)`     | Indexed indirect            |
+/// | `(value),Y`     | Indirect indexed            |
+///
+/// Values are described in `expression.rs`. Zero page is used when the value
+/// fits in a byte and is known by the time the line is reached, so labels
+/// defined further down are addressed as absolute.
+///
+/// Directives:
+/// - `.org address` continues assembling at `address`, filling any gap with
+///   zeros. It can't go backwards.
+/// - `.byte value, ...` and `.word value, ...` emit bytes and little endian
+///   words.
+///
+/// The unofficial mnemonics are accepted, with `ISB` as another name for
+/// `ISC`. Where several opcodes do the same thing, such as the unofficial
+/// `SBC #`, the official one is used.
+///
+/// <https://www.nesdev.org/wiki/CPU_unofficial_opcodes>
+pub fn assemble(source: &str) -> Result<Assembly, AssemblyError> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(index, text)| {
+            parse_line(text).map_err(|kind| AssemblyError {
+                line: index + 1,
+                kind,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut assembler = Assembler::default();
+    // Find each line's address and size, then emit them once every label is known
+    for pass in [Pass::Layout, Pass::Emit] {
+        assembler.start(pass);
+        for (index, line) in lines.iter().enumerate() {
+            assembler.line(index, line).map_err(|kind| AssemblyError {
+                line: index + 1,
+                kind,
+            })?;
+        }
+    }
+
+    Ok(Assembly {
+        origin: assembler.origin.unwrap_or(0),
+        bytes: assembler.bytes,
+        labels: assembler.labels,
+    })
+}
+
+/// Output of [`assemble`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    /// Address of the first byte
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+    /// Address of every label
+    pub labels: HashMap<String, u16>,
+}
+
+impl Assembly {
+    /// Write the bytes to memory at their addresses, e.g. through
+    /// [`crate::Bus::write_cpu`]
+    pub fn write_to(&self, mut write: impl FnMut(u16, u8)) {
+        for (offset, &data) in self.bytes.iter().enumerate() {
+            write(self.origin.wrapping_add(offset as u16), data);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblyError {
+    /// Line number, from 1
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblyErrorKind {
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    /// The operand or a value in it can't be parsed
+    BadOperand(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    /// The instruction has no opcode for the operand's addressing mode
+    UnsupportedMode(String),
+    /// A value doesn't fit in the byte or word it is assembled into
+    ValueOutOfRange(i64),
+    /// Branch offset, from the end of the branch, outside `-128..=127`
+    BranchOutOfRange(i64),
+    /// `.org` to an address before the current one
+    OrgBackwards(u16),
+    /// The program runs past `$FFFF`
+    PastEndOfMemory,
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.kind)
+    }
+}
+
+impl Display for AssemblyErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssemblyErrorKind::UnknownMnemonic(name) => write!(f, "Unknown mnemonic {name}"),
+            AssemblyErrorKind::UnknownDirective(name) => write!(f, "Unknown directive {name}"),
+            AssemblyErrorKind::BadOperand(operand) => write!(f, "Can't parse operand {operand}"),
+            AssemblyErrorKind::UndefinedLabel(label) => write!(f, "Label {label} isn't defined"),
+            AssemblyErrorKind::DuplicateLabel(label) => {
+                write!(f, "Label {label} is defined more than once")
+            }
+            AssemblyErrorKind::UnsupportedMode(mnemonic) => {
+                write!(f, "{mnemonic} can't use this addressing mode")
+            }
+            AssemblyErrorKind::ValueOutOfRange(value) => write!(f, "{value} is out of range"),
+            AssemblyErrorKind::BranchOutOfRange(offset) => {
+                write!(f, "Branch is {offset} bytes away, the limit is 128")
+            }
+            AssemblyErrorKind::OrgBackwards(address) => {
+                write!(f, ".org ${address:04X} is before the current address")
+            }
+            AssemblyErrorKind::PastEndOfMemory => write!(f, "Program runs past $FFFF"),
+        }
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+struct Line {
+    label: Option<String>,
+    statement: Statement,
+}
+
+enum Statement {
+    Empty,
+    Instruction(OpCodeType, Operand),
+    Byte(Vec<String>),
+    Word(Vec<String>),
+    Org(String),
+}
+
+enum Operand {
+    Implied,
+    Immediate(String),
+    /// Zero page, absolute or relative, with an optional index register
+    Address(String, Option<Index>),
+    Indirect(String),
+    IndirectX(String),
+    IndirectY(String),
+}
+
+#[derive(Clone, Copy)]
+enum Index {
+    X,
+    Y,
+}
+
+fn parse_line(text: &str) -> Result<Line, AssemblyErrorKind> {
+    let text = match text.split_once(';') {
+        Some((code, _comment)) => code,
+        None => text,
+    }
+    .trim();
+
+    let (label, text) = match text.split_once(':') {
+        Some((label, rest)) if is_identifier(label.trim()) => {
+            (Some(label.trim().to_string()), rest.trim())
+        }
+        _ => (None, text),
+    };
+
+    let (word, rest) = match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest),
+        None => (text, ""),
+    };
+    // Spaces in operands don't matter, e.g. `( $10 ), Y`
+    let operand: String = rest.chars().filter(|char| !char.is_whitespace()).collect();
+    let list = || operand.split(',').map(str::to_string).collect();
+
+    let statement = match word.to_ascii_lowercase().as_str() {
+        "" => Statement::Empty,
+        ".byte" => Statement::Byte(list()),
+        ".word" => Statement::Word(list()),
+        ".org" => Statement::Org(operand),
+        directive if directive.starts_with('.') => {
+            return Err(AssemblyErrorKind::UnknownDirective(word.to_string()))
+        }
+        _ => match parse_mnemonic(word) {
+            Some(code_type) => Statement::Instruction(code_type, parse_operand(&operand)?),
+            None => return Err(AssemblyErrorKind::UnknownMnemonic(word.to_string())),
+        },
+    };
+
+    Ok(Line { label, statement })
+}
+
+fn parse_mnemonic(word: &str) -> Option<OpCodeType> {
+    let word = match word.to_ascii_uppercase().as_str() {
+        "ISB" => "ISC".to_string(),
+        word => word.to_string(),
+    };
+    (0..=0xFF)
+        .map(|raw: u8| OpCode::from(raw).code_type)
+        .find(|code_type| format!("{code_type:?}") == word)
+}
+
+fn parse_operand(operand: &str) -> Result<Operand, AssemblyErrorKind> {
+    let upper = operand.to_ascii_uppercase();
+    let without = |suffix: &str| operand[..operand.len() - suffix.len()].to_string();
+
+    let parsed = if operand.is_empty() || upper == "A" {
+        Operand::Implied
+    } else if let Some(value) = operand.strip_prefix('#') {
+        Operand::Immediate(value.to_string())
+    } else if operand.starts_with('(') && upper.ends_with(",X)") {
+        Operand::IndirectX(without(",X)")[1..].to_string())
+    } else if operand.starts_with('(') && upper.ends_with("),Y") {
+        Operand::IndirectY(without("),Y")[1..].to_string())
+    } else if operand.starts_with('(') && operand.ends_with(')') {
+        Operand::Indirect(without(")")[1..].to_string())
+    } else if upper.ends_with(",X") {
+        Operand::Address(without(",X"), Some(Index::X))
+    } else if upper.ends_with(",Y") {
+        Operand::Address(without(",Y"), Some(Index::Y))
+    } else {
+        Operand::Address(operand.to_string(), None)
+    };
+
+    match &parsed {
+        Operand::Implied => Ok(parsed),
+        Operand::Immediate(value)
+        | Operand::Address(value, _)
+        | Operand::Indirect(value)
+        | Operand::IndirectX(value)
+        | Operand::IndirectY(value) => match value.is_empty() || value.contains(['(', ')', ',']) {
+            true => Err(AssemblyErrorKind::BadOperand(operand.to_string())),
+            false => Ok(parsed),
+        },
+    }
+}
+
+/// The opcode for an instruction in an addressing mode, preferring the
+/// official one when there are several
+fn encode(code_type: OpCodeType, mode: AddressingMode) -> Option<u8> {
+    let mut matching = (0..=0xFF).filter(|&raw: &u8| {
+        let opcode = OpCode::from(raw);
+        opcode.code_type == code_type && opcode.addressing_mode == mode
+    });
+    let first = matching.next()?;
+    Some(
+        std::iter::once(first)
+            .chain(matching)
+            .find(|&raw| OpCode::is_official(raw))
+            .unwrap_or(first),
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pass {
+    Layout,
+    Emit,
+}
+
+#[derive(Default)]
+struct Assembler {
+    emitting: bool,
+    /// Address of the next byte, which can be `$10000` at the very end
+    address: u32,
+    origin: Option<u16>,
+    bytes: Vec<u8>,
+    labels: HashMap<String, u16>,
+    /// Opcode chosen for each instruction line on the layout pass, so both
+    /// passes agree on the sizes
+    opcodes: HashMap<usize, u8>,
+}
+
+impl Assembler {
+    fn start(&mut self, pass: Pass) {
+        self.emitting = pass == Pass::Emit;
+        self.address = 0;
+        self.origin = None;
+        self.bytes.clear();
+    }
+
+    /// Value of an expression. Undefined labels are only an error once
+    /// every label has been seen.
+    fn value(&self, expression: &str) -> Result<Option<i64>, AssemblyErrorKind> {
+        match evaluate(expression, &self.labels, self.address as u16)? {
+            None if self.emitting => {
+                let label = expression
+                    .split(['+', '-', '<', '>'])
+                    .find(|term| is_identifier(term) && !self.labels.contains_key(*term))
+                    .unwrap_or(expression);
+                Err(AssemblyErrorKind::UndefinedLabel(label.to_string()))
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn byte(&self, expression: &str) -> Result<u8, AssemblyErrorKind> {
+        match self.value(expression)? {
+            Some(value @ 0..=0xFF) => Ok(value as u8),
+            Some(value) => Err(AssemblyErrorKind::ValueOutOfRange(value)),
+            None => Ok(0),
+        }
+    }
+
+    fn word(&self, expression: &str) -> Result<u16, AssemblyErrorKind> {
+        match self.value(expression)? {
+            Some(value @ 0..=0xFFFF) => Ok(value as u16),
+            Some(value) => Err(AssemblyErrorKind::ValueOutOfRange(value)),
+            None => Ok(0),
+        }
+    }
+
+    fn emit(&mut self, data: &[u8]) -> Result<(), AssemblyErrorKind> {
+        if self.address + data.len() as u32 > 0x10000 {
+            return Err(AssemblyErrorKind::PastEndOfMemory);
+        }
+        if self.origin.is_none() {
+            self.origin = Some(self.address as u16);
+        }
+        self.address += data.len() as u32;
+        self.bytes.extend(data);
+        Ok(())
+    }
+
+    fn line(&mut self, index: usize, line: &Line) -> Result<(), AssemblyErrorKind> {
+        if let (Some(label), false) = (&line.label, self.emitting) {
+            if self.labels.contains_key(label) {
+                return Err(AssemblyErrorKind::DuplicateLabel(label.clone()));
+            }
+            self.labels.insert(label.clone(), self.address as u16);
+        }
+
+        match &line.statement {
+            Statement::Empty => Ok(()),
+            Statement::Byte(values) => {
+                let bytes = values
+                    .iter()
+                    .map(|value| self.byte(value))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.emit(&bytes)
+            }
+            Statement::Word(values) => {
+                let bytes = values
+                    .iter()
+                    .map(|value| self.word(value).map(u16::to_le_bytes))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.emit(&bytes.concat())
+            }
+            Statement::Org(expression) => {
+                let address = match evaluate(expression, &self.labels, self.address as u16)? {
+                    Some(address) => address,
+                    None => return Err(AssemblyErrorKind::UndefinedLabel(expression.clone())),
+                };
+                let address = match u16::try_from(address) {
+                    Ok(address) => address,
+                    Err(_) => return Err(AssemblyErrorKind::ValueOutOfRange(address)),
+                };
+                match (self.origin, address as u32) {
+                    (None, _) => self.address = address as u32,
+                    (Some(_), new) if new >= self.address => {
+                        self.bytes
+                            .resize(self.bytes.len() + (new - self.address) as usize, 0);
+                        self.address = new;
+                    }
+                    (Some(_), _) => return Err(AssemblyErrorKind::OrgBackwards(address)),
+                }
+                Ok(())
+            }
+            Statement::Instruction(code_type, operand) => {
+                let raw = match self.opcodes.get(&index) {
+                    Some(&raw) => raw,
+                    None => {
+                        let raw = self.choose_opcode(*code_type, operand)?;
+                        self.opcodes.insert(index, raw);
+                        raw
+                    }
+                };
+                let bytes = self.instruction(raw, operand)?;
+                self.emit(&bytes)
+            }
+        }
+    }
+
+    /// Pick the addressing mode for an operand, on the layout pass
+    fn choose_opcode(
+        &self,
+        code_type: OpCodeType,
+        operand: &Operand,
+    ) -> Result<u8, AssemblyErrorKind> {
+        use AddressingMode::*;
+
+        let modes: &[AddressingMode] = match operand {
+            Operand::Implied => &[IMP],
+            Operand::Immediate(_) => &[IMM],
+            Operand::Indirect(_) => &[IND],
+            Operand::IndirectX(_) => &[IZX],
+            Operand::IndirectY(_) => &[IZY],
+            Operand::Address(expression, index) => {
+                let zero_page = matches!(self.value(expression)?, Some(0..=0xFF));
+                match (index, zero_page) {
+                    (None, true) => &[REL, ZP0, ABS],
+                    (None, false) => &[REL, ABS, ZP0],
+                    (Some(Index::X), true) => &[ZPX, ABX],
+                    (Some(Index::X), false) => &[ABX, ZPX],
+                    (Some(Index::Y), true) => &[ZPY, ABY],
+                    (Some(Index::Y), false) => &[ABY, ZPY],
+                }
+            }
+        };
+
+        modes
+            .iter()
+            .find_map(|&mode| encode(code_type, mode))
+            .ok_or_else(|| AssemblyErrorKind::UnsupportedMode(format!("{code_type:?}")))
+    }
+
+    /// Opcode and operand bytes
+    fn instruction(&self, raw: u8, operand: &Operand) -> Result<Vec<u8>, AssemblyErrorKind> {
+        let expression = match operand {
+            Operand::Implied => return Ok(vec![raw]),
+            Operand::Immediate(expression)
+            | Operand::Address(expression, _)
+            | Operand::Indirect(expression)
+            | Operand::IndirectX(expression)
+            | Operand::IndirectY(expression) => expression,
+        };
+
+        let mode = OpCode::from(raw).addressing_mode;
+        let operand = match mode {
+            AddressingMode::REL => {
+                let offset = match self.value(expression)? {
+                    Some(target) => target - (self.address as i64 + 2),
+                    None => 0,
+                };
+                match i8::try_from(offset) {
+                    Ok(offset) => vec![offset as u8],
+                    Err(_) => return Err(AssemblyErrorKind::BranchOutOfRange(offset)),
+                }
+            }
+            _ => match mode.operand_len() {
+                1 => vec![self.byte(expression)?],
+                _ => self.word(expression)?.to_le_bytes().to_vec(),
+            },
+        };
+
+        Ok([vec![raw], operand].concat())
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use super::AssemblyErrorKind;
+
+/// Evaluate an operand expression: numbers and labels, added and subtracted,
+/// optionally prefixed by `<` or `>` to take the low or high byte.
+///
+/// Numbers are `$` hexadecimal, `%` binary or decimal. `*` is the address of
+/// the current line. Returns `None` while a label isn't defined yet.
+pub(super) fn evaluate(
+    expression: &str,
+    labels: &HashMap<String, u16>,
+    address: u16,
+) -> Result<Option<i64>, AssemblyErrorKind> {
+    let (byte, expression) = match expression.as_bytes().first() {
+        Some(b'<') => (Some(false), &expression[1..]),
+        Some(b'>') => (Some(true), &expression[1..]),
+        _ => (None, expression),
+    };
+    if expression.is_empty() {
+        return Err(AssemblyErrorKind::BadOperand(expression.to_string()));
+    }
+
+    let mut total = Some(0);
+    let mut negative = false;
+    let mut start = 0;
+    for (index, char) in expression.char_indices().chain([(expression.len(), '+')]) {
+        if index == 0 || !matches!(char, '+' | '-') {
+            continue;
+        }
+        let term = evaluate_term(&expression[start..index], labels, address)?;
+        total = match (total, term) {
+            (Some(total), Some(term)) if negative => Some(total - term),
+            (Some(total), Some(term)) => Some(total + term),
+            _ => None,
+        };
+        negative = char == '-';
+        start = index + 1;
+    }
+
+    Ok(total.map(|value| match byte {
+        Some(false) => value & 0xFF,
+        Some(true) => (value >> 8) & 0xFF,
+        None => value,
+    }))
+}
+
+fn evaluate_term(
+    term: &str,
+    labels: &HashMap<String, u16>,
+    address: u16,
+) -> Result<Option<i64>, AssemblyErrorKind> {
+    let bad_operand = || AssemblyErrorKind::BadOperand(term.to_string());
+    let value = match term.as_bytes().first() {
+        Some(b'$') => i64::from_str_radix(&term[1..], 16).map_err(|_| bad_operand())?,
+        Some(b'%') => i64::from_str_radix(&term[1..], 2).map_err(|_| bad_operand())?,
+        Some(b'0'..=b'9') => term.parse().map_err(|_| bad_operand())?,
+        Some(b'*') if term.len() == 1 => address as i64,
+        Some(_) if is_identifier(term) => match labels.get(term) {
+            Some(&value) => value as i64,
+            None => return Ok(None),
+        },
+        _ => return Err(bad_operand()),
+    };
+    Ok(Some(value))
+}
+
+/// Label names: a letter, `_` or `.`, then letters, digits and `_`
+pub(super) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '.' => {
+            chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,5 @@
+#![allow(clippy::module_inception)]
+mod assembler;
+mod expression;
+
+pub use assembler::{assemble, Assembly, AssemblyError, AssemblyErrorKind};
@@ -1,6 +1,11 @@
 use std::{cell::Ref, ops::RangeInclusive};
 
-use crate::{Cartridge, Clock, Cpu, Ppu, RcCell, Reset, WeakCell};
+use crate::{
+    controllers::{select_port_device, ControllerInput, PortDevice, PortDeviceKind},
+    cpu::Memory,
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+    Cartridge, Clock, Cpu, Ppu, RcCell, Reset, WeakCell,
+};
 
 /// # Bus For NES
 ///
@@ -29,10 +34,12 @@ use crate::{Cartridge, Clock, Cpu, Ppu, RcCell, Reset, WeakCell};
 pub struct Bus {
     pub cpu: WeakCell<Cpu>,
     pub ppu: WeakCell<Ppu>,
-    /// See Memory Layout - CPU
-    pub ram: [u8; 64 * 1024],
+    /// Internal CPU RAM, mirrored across 0x0000 - 0x1FFF. See Memory Layout - CPU
+    pub ram: [u8; Self::CPU_RAM_SIZE],
     pub cartridge: Option<RcCell<Cartridge>>,
     pub clock: Clock,
+    /// Devices plugged into controller ports 1 and 2
+    pub ports: [Option<Box<dyn PortDevice>>; 2],
 }
 
 impl Bus {
@@ -40,19 +47,31 @@ impl Bus {
     const CPU_RAM_END: u16 = 0x1FFF;
     pub const CPU_RAM_RANGE: RangeInclusive<u16> = Self::CPU_RAM_START..=Self::CPU_RAM_END;
     pub const CPU_RAM_MIRROR_MASK: u16 = 0x07FF;
+    pub const CPU_RAM_SIZE: usize = 2 * 1024;
 
     const PPU_START: u16 = 0x2000;
     const PPU_END: u16 = 0x3FFF;
     pub const PPU_RANGE: RangeInclusive<u16> = Self::PPU_START..=Self::PPU_END;
     const PPU_MEMORY_MASK: u16 = 0b111;
 
+    /// Write: Strobe for both ports. Read: Serial data from port 1
+    const PORT_1: u16 = 0x4016;
+    /// Read: Serial data from port 2
+    const PORT_2: u16 = 0x4017;
+
+    /// Cartridge space: expansion area, PRG-RAM and PRG-ROM
+    const CARTRIDGE_START: u16 = 0x4020;
+    const CARTRIDGE_END: u16 = 0xFFFF;
+    pub const CARTRIDGE_RANGE: RangeInclusive<u16> = Self::CARTRIDGE_START..=Self::CARTRIDGE_END;
+
     pub fn new(cpu: WeakCell<Cpu>, ppu: WeakCell<Ppu>) -> Self {
         Self {
             cpu,
-            ram: [0; 64 * 1024],
+            ram: [0; Self::CPU_RAM_SIZE],
             ppu,
             cartridge: None,
             clock: Clock::default(),
+            ports: [None, None],
         }
     }
 
@@ -88,6 +107,15 @@ impl Bus {
             self.unwrap_ppu()
                 .borrow_mut()
                 .write_cpu(address & Self::PPU_MEMORY_MASK, data);
+        } else if address == Self::PORT_1 {
+            self.ports
+                .iter_mut()
+                .flatten()
+                .for_each(|device| device.write_strobe(data));
+        } else if Self::CARTRIDGE_RANGE.contains(&address) {
+            if let Some(cartridge) = &self.cartridge {
+                cartridge.borrow_mut().cpu_write(address, data);
+            }
         } else {
             // panic!("Unimplemented write to address: {:04X}", address);
             // 0
@@ -95,19 +123,50 @@ impl Bus {
     }
 
     /// Connects PPU to CPU BUS
-    pub fn read_cpu(&self, address: u16) -> u8 {
+    pub fn read_cpu(&mut self, address: u16) -> u8 {
         if Self::CPU_RAM_RANGE.contains(&address) {
             self.ram[(address & Self::CPU_RAM_MIRROR_MASK) as usize]
         } else if Self::PPU_RANGE.contains(&address) {
             self.unwrap_ppu()
                 .borrow_mut()
                 .read_cpu(address & Self::PPU_MEMORY_MASK)
+        } else if address == Self::PORT_1 || address == Self::PORT_2 {
+            let port = (address - Self::PORT_1) as usize;
+            match &mut self.ports[port] {
+                Some(device) => device.read(),
+                None => 0,
+            }
+        } else if Self::CARTRIDGE_RANGE.contains(&address) {
+            match &self.cartridge {
+                Some(cartridge) => cartridge.borrow_mut().cpu_read(address),
+                None => 0,
+            }
         } else {
             // panic!("Unimplemented read of address: {:04X}", address);
             0
         }
     }
 
+    /// Read without any side effects, for debuggers, tracing and tests.
+    /// Controller ports read as `0`, since reading them shifts their data.
+    pub fn peek_cpu(&self, address: u16) -> u8 {
+        if Self::CPU_RAM_RANGE.contains(&address) {
+            self.ram[(address & Self::CPU_RAM_MIRROR_MASK) as usize]
+        } else if Self::PPU_RANGE.contains(&address) {
+            match self.get_ppu() {
+                Some(ppu) => ppu.borrow().peek_cpu(address & Self::PPU_MEMORY_MASK),
+                None => 0,
+            }
+        } else if Self::CARTRIDGE_RANGE.contains(&address) {
+            match &self.cartridge {
+                Some(cartridge) => cartridge.borrow().cpu_peek(address),
+                None => 0,
+            }
+        } else {
+            0
+        }
+    }
+
     pub fn tick(&mut self) {
         self.clock.tick();
     }
@@ -116,9 +175,48 @@ impl Bus {
         self.cartridge = cartridge;
     }
 
-    pub fn cartridge_ref(&self) -> Option<Ref<Cartridge>> {
+    pub fn cartridge_ref(&self) -> Option<Ref<'_, Cartridge>> {
         self.cartridge.as_ref().map(|cartridge| cartridge.borrow())
     }
+
+    /// Plug a device into a controller port, replacing whatever was there.
+    /// `port` is `0` for port 1 and `1` for port 2.
+    pub fn connect_port_device(&mut self, port: usize, kind: PortDeviceKind) {
+        self.ports[port] = select_port_device(kind);
+    }
+
+    pub fn port_device_kind(&self, port: usize) -> PortDeviceKind {
+        match &self.ports[port] {
+            Some(device) => device.kind(),
+            None => PortDeviceKind::Disconnected,
+        }
+    }
+
+    /// Forward the frontend's input to every connected device
+    pub fn update_port_input(&mut self, input: &ControllerInput) {
+        self.ports
+            .iter_mut()
+            .flatten()
+            .for_each(|device| device.update_input(input));
+    }
+}
+
+/// The CPU's view of the bus
+impl Memory for Bus {
+    #[inline(always)]
+    fn read(&mut self, address: u16) -> u8 {
+        self.read_cpu(address)
+    }
+
+    #[inline(always)]
+    fn write(&mut self, address: u16, data: u8) {
+        self.write_cpu(address, data)
+    }
+
+    #[inline(always)]
+    fn peek(&self, address: u16) -> u8 {
+        self.peek_cpu(address)
+    }
 }
 
 impl Default for Bus {
@@ -126,9 +224,10 @@ impl Default for Bus {
         Self {
             cpu: WeakCell::new(),
             ppu: WeakCell::new(),
-            ram: [0; 64 * 1024],
+            ram: [0; Self::CPU_RAM_SIZE],
             clock: Clock::default(),
             cartridge: None,
+            ports: [None, None],
         }
     }
 }
@@ -138,3 +237,38 @@ impl Reset for Bus {
         self.clock.reset();
     }
 }
+
+impl SaveState for Bus {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        self.clock.save_state(state);
+        for port in self.ports.iter() {
+            match port {
+                Some(device) => {
+                    state.write_u8(device.kind() as u8);
+                    device.save_state(state);
+                }
+                None => state.write_u8(PortDeviceKind::Disconnected as u8),
+            }
+        }
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.clock.load_state(state)?;
+        for port in 0..self.ports.len() {
+            let kind = *PortDeviceKind::ALL
+                .get(state.read_u8()? as usize)
+                .ok_or(SaveStateError::InvalidValue("port device"))?;
+            // Keep the device if it is already connected, so loading a
+            // state every frame doesn't reallocate it
+            if self.port_device_kind(port) != kind {
+                self.connect_port_device(port, kind);
+            }
+            if let Some(device) = &mut self.ports[port] {
+                device.load_state(state)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::Cartridge;
+
+/// # Battery Save
+/// Keeps the battery-backed PRG-RAM of a cartridge in a `.sav` file next to
+/// the ROM, so in-game saves survive the emulator being closed.
+///
+/// The file is a raw dump of PRG-RAM, the same layout used by most other
+/// emulators, so saves can be moved between them.
+pub struct BatterySave {
+    path: PathBuf,
+    /// Contents of PRG-RAM when last loaded or written, to skip writing the
+    /// file when nothing has changed
+    flushed: Vec<u8>,
+}
+
+impl BatterySave {
+    /// `.sav` file for the ROM at `rom_path`, e.g. `zelda.nes` is saved to
+    /// `zelda.sav`
+    pub fn for_rom(rom_path: &Path) -> Self {
+        Self {
+            path: rom_path.with_extension("sav"),
+            flushed: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Fill the cartridge's PRG-RAM from the `.sav` file. Not having a file
+    /// yet is not an error; PRG-RAM is left as it is.
+    pub fn load(&mut self, cartridge: &mut Cartridge) -> io::Result<()> {
+        match std::fs::read(&self.path) {
+            Ok(data) => cartridge.load_program_ram(&data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.flushed = cartridge.program_ram.clone();
+        Ok(())
+    }
+
+    /// Write the cartridge's PRG-RAM to the `.sav` file, if it has changed
+    /// since it was last loaded or written.
+    pub fn flush(&mut self, cartridge: &Cartridge) -> io::Result<()> {
+        if cartridge.program_ram == self.flushed {
+            return Ok(());
+        }
+        std::fs::write(&self.path, &cartridge.program_ram)?;
+        self.flushed = cartridge.program_ram.clone();
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
+use std::fmt::Display;
 use std::vec::Vec;
 
 use log::debug;
 
 use crate::{
+    database::RomDatabase,
+    fds::{DiskImage, DiskSystem},
     mappers::{select_mapper, Mapper000},
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
     Mapper,
 };
 
-use super::{header::HeaderParseError, Header};
+use super::{header::HeaderParseError, unif, CartridgeMetadata, FileFormat, Header, Mirroring};
 
 /// Program is in 16Kb Chunks
 // const PRG_CHUNK_SIZE: usize = 0x4000;
@@ -15,48 +19,337 @@ const PRG_CHUNK_SIZE: usize = 16384;
 /// Characters chunks are 8Kb
 // const CHR_CHUNK_SIZE: usize = 0x2000;
 const CHR_CHUNK_SIZE: usize = 8192;
+/// Optional block loaded into PRG-RAM, before the PRG-ROM in the file
+const TRAINER_SIZE: usize = 512;
+/// The trainer is copied to 0x7000 - 0x71FF on power-on
+const TRAINER_START: u16 = 0x7000;
+/// Work RAM on the cartridge, battery-backed on some boards
+const PRG_RAM_START: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+/// The Disk System's RAM runs up to its BIOS at 0xE000
+const DISK_SYSTEM_RAM_END: u16 = 0xDFFF;
+/// Registers of the Disk System's RAM adapter
+const DISK_SYSTEM_REGISTERS_START: u16 = 0x4020;
+const DISK_SYSTEM_REGISTERS_END: u16 = 0x40FF;
 
 /// # Cartridge
 ///
 /// ## Description
-/// Created from a `iNes` file, this struct contains all the information
+/// Created from a `iNes` or `UNIF` file, this struct contains all the information
 /// needed to emulate the cartridge including metadata, game rom and, mappers.
+///
+/// Famicom Disk System games are loaded from a `.fds` image with
+/// [`Cartridge::from_disk_image`], giving a cartridge that stands in for the
+/// RAM adapter: its PRG-ROM is the BIOS and the disks are in `disk_system`.
 pub struct Cartridge {
     pub header: Header,
+    pub metadata: CartridgeMetadata,
     pub virtual_program_memory: Vec<u8>,
+    /// CHR-ROM, or CHR-RAM if `has_character_ram` is set
     pub virtual_character_memory: Vec<u8>,
-    pub mapper_id: u8,
-    pub program_banks_count: u8,
-    pub character_banks_count: u8,
+    /// The board has writable CHR-RAM in place of CHR-ROM
+    pub has_character_ram: bool,
+    /// Mapped at 0x6000 - 0x7FFF. Kept across power cycles in a `.sav` file
+    /// if the cartridge has a battery.
+    pub program_ram: Vec<u8>,
+    /// 512 bytes from the file, present on some hacked dumps and
+    /// translations. Copied into PRG-RAM at 0x7000 on power-on.
+    pub trainer: Option<Vec<u8>>,
+    pub mapper_id: u16,
+    pub program_banks_count: u16,
+    pub character_banks_count: u16,
     pub mapper: Mapper000,
+    /// RAM adapter and disk drive, for Disk System games
+    pub disk_system: Option<DiskSystem>,
 }
 
 impl Cartridge {
-    pub fn ppu_read() {}
-    pub fn ppu_write() {}
+    pub fn ppu_read(&mut self, address: u16) -> u8 {
+        let mut new_address: u16 = 0;
+        match self.mapper.map_ppu_read(address, &mut new_address) {
+            true => self
+                .virtual_character_memory
+                .get(new_address as usize)
+                .copied()
+                .unwrap_or(0),
+            false => 0,
+        }
+    }
+
+    /// Writes only take effect on boards with CHR-RAM
+    pub fn ppu_write(&mut self, address: u16, data: u8) {
+        let mut new_address: u16 = 0;
+        if self.mapper.map_ppu_write(address, &mut new_address) {
+            if let Some(byte) = self.virtual_character_memory.get_mut(new_address as usize) {
+                *byte = data;
+            }
+        }
+    }
 
     pub fn cpu_read(&mut self, address: u16) -> u8 {
+        if let Some(disk_system) = &mut self.disk_system {
+            if (DISK_SYSTEM_REGISTERS_START..=DISK_SYSTEM_REGISTERS_END).contains(&address) {
+                return disk_system.read_register(address).unwrap_or(0);
+            }
+        }
+        if let Some(offset) = self.program_ram_offset(address) {
+            return self.program_ram[offset];
+        }
+
         let mut new_address: u16 = 0;
         if self.mapper.map_cpu_read(address, &mut new_address) {
-            self.virtual_program_memory[new_address as usize]
+            // PRG-ROM smaller than the mapper's window is mirrored
+            self.virtual_program_memory[new_address as usize % self.virtual_program_memory.len()]
+        } else {
+            0
+        }
+    }
+
+    /// Same as [`Cartridge::cpu_read`], without the side effects a read can
+    /// have on the mapper or the disk system
+    pub fn cpu_peek(&self, address: u16) -> u8 {
+        if let Some(disk_system) = &self.disk_system {
+            if (DISK_SYSTEM_REGISTERS_START..=DISK_SYSTEM_REGISTERS_END).contains(&address) {
+                return disk_system.peek_register(address).unwrap_or(0);
+            }
+        }
+        if let Some(offset) = self.program_ram_offset(address) {
+            return self.program_ram[offset];
+        }
+
+        let mut new_address: u16 = 0;
+        if self.mapper.map_cpu_peek(address, &mut new_address) {
+            self.virtual_program_memory[new_address as usize % self.virtual_program_memory.len()]
         } else {
             0
         }
     }
 
-    pub fn cpu_write() {}
+    pub fn cpu_write(&mut self, address: u16, data: u8) {
+        if let Some(disk_system) = &mut self.disk_system {
+            if (DISK_SYSTEM_REGISTERS_START..=DISK_SYSTEM_REGISTERS_END).contains(&address) {
+                disk_system.write_register(address, data);
+                return;
+            }
+        }
+        if let Some(offset) = self.program_ram_offset(address) {
+            self.program_ram[offset] = data;
+        }
+        // TODO: Pass writes to the mapper's registers once a mapper has any
+    }
+
+    /// Index into PRG-RAM for a CPU address, mirrored if the RAM is
+    /// smaller than its window
+    fn program_ram_offset(&self, address: u16) -> Option<usize> {
+        let end = match self.disk_system {
+            Some(_) => DISK_SYSTEM_RAM_END,
+            None => PRG_RAM_END,
+        };
+        match (PRG_RAM_START..=end).contains(&address) && !self.program_ram.is_empty() {
+            true => Some((address - PRG_RAM_START) as usize % self.program_ram.len()),
+            false => None,
+        }
+    }
+
+    /// Put the cartridge in its power-on state. PRG-RAM is kept, apart
+    /// from the trainer, if there is one, which is copied back to 0x7000.
+    pub fn power_on(&mut self) {
+        if let Some(disk_system) = &mut self.disk_system {
+            disk_system.power_on();
+        }
+        let trainer = match &self.trainer {
+            Some(trainer) => trainer,
+            None => return,
+        };
+        let start = (TRAINER_START - PRG_RAM_START) as usize;
+        self.program_ram[start..start + TRAINER_SIZE].copy_from_slice(trainer);
+    }
+
+    /// Advance hardware on the cartridge that runs off the CPU clock by
+    /// one cycle
+    pub fn cpu_tick(&mut self) {
+        if let Some(disk_system) = &mut self.disk_system {
+            disk_system.tick();
+        }
+    }
+
+    /// The cartridge is asserting the CPU's IRQ line
+    pub fn irq_pending(&self) -> bool {
+        match &self.disk_system {
+            Some(disk_system) => disk_system.irq_pending(),
+            None => false,
+        }
+    }
+
+    /// Nametable mirroring, as set by the board or its mapper
+    pub fn mirroring(&self) -> Mirroring {
+        match &self.disk_system {
+            Some(disk_system) => disk_system.mirroring(),
+            None => self.header.mirroring(),
+        }
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.header.has_battery()
+    }
+
+    /// Replace the contents of PRG-RAM with data from a `.sav` file.
+    /// Data of the wrong size is truncated or zero-padded.
+    pub fn load_program_ram(&mut self, data: &[u8]) {
+        let length = data.len().min(self.program_ram.len());
+        self.program_ram.fill(0);
+        self.program_ram[..length].copy_from_slice(&data[..length]);
+    }
+
+    /// CRC-32 of the PRG and CHR ROM, excluding the header. Used to check
+    /// that a save state belongs to this cartridge.
+    pub fn crc32(&self) -> u32 {
+        self.metadata.crc32
+    }
+}
+
+/// Only the parts of the cartridge that can change while running are saved.
+/// The ROM itself comes from the loaded file.
+impl SaveState for Cartridge {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_vec(&self.program_ram);
+        if self.has_character_ram {
+            state.write_vec(&self.virtual_character_memory);
+        }
+        self.mapper.save_state(state);
+        if let Some(disk_system) = &self.disk_system {
+            disk_system.save_state(state);
+        }
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        state.read_vec_into(&mut self.program_ram, "PRG-RAM size")?;
+        if self.has_character_ram {
+            state.read_vec_into(&mut self.virtual_character_memory, "CHR-RAM size")?;
+        }
+        self.mapper.load_state(state)?;
+        if let Some(disk_system) = &mut self.disk_system {
+            disk_system.load_state(state)?;
+        }
+        Ok(())
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 
+/// Reasons a ROM file could not be loaded. Offsets are from the start of
+/// the file.
 #[derive(Debug)]
 pub enum CartridgeParseError {
+    /// File is too short to hold a header. Holds the file length
+    TooShort(usize),
     InvalidHeader(HeaderParseError),
-    ProgramRomCutsOff,
-    CharacterRomCutsOff,
+    /// Flag 6 declares a trainer that runs past the end of the file
+    TrainerCutsOff(SectionCutOff),
+    /// The header declares no PRG-ROM
+    NoProgramRom,
+    ProgramRomCutsOff(SectionCutOff),
+    CharacterRomCutsOff(SectionCutOff),
+    UnsupportedMapper(u16),
+    /// A UNIF chunk runs past the end of the file
+    UnifChunkCutsOff(SectionCutOff),
+    /// The UNIF file has no `MAPR` chunk naming its board
+    UnifNoBoard,
+    /// The UNIF board name is not in the board table
+    UnifUnknownBoard(String),
+    /// `.fds` images can only be run with the Disk System BIOS, see
+    /// [`Cartridge::from_disk_image`]
+    DiskSystemNeedsBios,
+    /// The BIOS is not 8KB. Holds its length
+    InvalidBios(usize),
+    /// The fwNES header declares no sides
+    NoDiskSides,
+    DiskSideCutsOff(SectionCutOff),
+    /// The side with this index doesn't start with a disk info block
+    NotADiskSide(usize),
     FileError(std::io::Error),
 }
 
+/// Where a section of the file starts, how long the header says it is, and
+/// how many bytes were left in the file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SectionCutOff {
+    pub offset: usize,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl Display for SectionCutOff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected {} bytes at offset {:#X}, but the file ends after {} bytes",
+            self.expected, self.offset, self.found
+        )
+    }
+}
+
+impl Display for CartridgeParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use CartridgeParseError::*;
+        match self {
+            TooShort(length) => {
+                write!(f, "File is {} bytes long, too short for its header", length)
+            }
+            InvalidHeader(e) => write!(f, "Invalid header: {}", e),
+            TrainerCutsOff(cut_off) => write!(f, "Trainer is cut off: {}", cut_off),
+            NoProgramRom => write!(f, "File declares no PRG-ROM"),
+            ProgramRomCutsOff(cut_off) => write!(f, "PRG-ROM is cut off: {}", cut_off),
+            CharacterRomCutsOff(cut_off) => write!(f, "CHR-ROM is cut off: {}", cut_off),
+            UnsupportedMapper(mapper) => write!(f, "Mapper {} is not supported", mapper),
+            UnifChunkCutsOff(cut_off) => write!(f, "UNIF chunk is cut off: {}", cut_off),
+            UnifNoBoard => write!(f, "UNIF file has no MAPR chunk"),
+            UnifUnknownBoard(board) => write!(f, "UNIF board {:?} is not known", board),
+            DiskSystemNeedsBios => write!(
+                f,
+                "Famicom Disk System images need the Disk System BIOS (disksys.rom)"
+            ),
+            InvalidBios(length) => write!(
+                f,
+                "Disk System BIOS is {} bytes long, it should be 8192",
+                length
+            ),
+            NoDiskSides => write!(f, "FDS image has no disk sides"),
+            DiskSideCutsOff(cut_off) => write!(f, "Disk side is cut off: {}", cut_off),
+            NotADiskSide(side) => write!(
+                f,
+                "Side {} of the FDS image doesn't start with *NINTENDO-HVC*",
+                side + 1
+            ),
+            FileError(e) => write!(f, "Could not read file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeParseError::InvalidHeader(e) => Some(e),
+            CartridgeParseError::FileError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CartridgeParseError {
+    fn from(e: std::io::Error) -> Self {
+        CartridgeParseError::FileError(e)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Cartridge {
+    type Error = CartridgeParseError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_slice())
+    }
+}
+
 impl TryFrom<&Vec<u8>> for Cartridge {
     type Error = CartridgeParseError;
 
@@ -68,6 +361,20 @@ impl TryFrom<&Vec<u8>> for Cartridge {
 impl TryFrom<&[u8]> for Cartridge {
     type Error = CartridgeParseError;
 
+    /// Parse an iNES/NES 2.0 or UNIF file, telling them apart by the
+    /// signature at the start of the file.
+    fn try_from(bytestream: &[u8]) -> Result<Self, Self::Error> {
+        if DiskImage::is_disk_image(bytestream) {
+            return Err(CartridgeParseError::DiskSystemNeedsBios);
+        }
+        match bytestream.starts_with(&unif::SIGNATURE) {
+            true => unif::parse(bytestream),
+            false => Self::from_ines(bytestream),
+        }
+    }
+}
+
+impl Cartridge {
     /// An iNES file consists of the following sections, in order:
     ///
     /// 1. Header (16 bytes)
@@ -77,79 +384,223 @@ impl TryFrom<&[u8]> for Cartridge {
     /// 5. PlayChoice INST-ROM, if present (0 or 8192 bytes)
     /// 6. PlayChoice PROM, if present (16 bytes Data, 16 bytes CounterOut) (this is often missing, see PC10 ROM-Images for details)
     /// 7. Some ROM-Images additionally contain a 128-byte (or sometimes 127-byte) title at the end of the file.
-    fn try_from(bytestream: &[u8]) -> Result<Self, Self::Error> {
-        use CartridgeParseError::*;
-
-        let cartridge_size = bytestream.len();
-        let header: Header = match Header::try_from(&bytestream[..16]) {
-            Ok(header) => header,
-            Err(e) => return Err(InvalidHeader(e)),
-        };
+    fn from_ines(bytestream: &[u8]) -> Result<Self, CartridgeParseError> {
+        let sections = split_ines(bytestream)?;
+        Self::from_parts(
+            sections.header,
+            sections.trainer.map(|trainer| trainer.to_vec()),
+            sections.program_rom.to_vec(),
+            sections.character_rom.to_vec(),
+            FileFormat::INes,
+        )
+    }
 
-        debug!("Header: {:?}", header);
+    /// The cartridge as an iNES or NES 2.0 file, whichever its header is.
+    /// CHR-RAM, PRG-RAM and the contents of Disk System disks are not
+    /// included; disks are saved with [`DiskImage::to_bytes`].
+    pub fn to_nes_file(&self) -> Vec<u8> {
+        let mut file = self.header.to_bytes().to_vec();
+        if let Some(trainer) = &self.trainer {
+            file.extend(trainer);
+        }
+        file.extend(&self.virtual_program_memory);
+        if !self.has_character_ram {
+            file.extend(&self.virtual_character_memory);
+        }
+        file
+    }
 
-        let bytestream = &bytestream[16..];
-        log_read_progress("Debug", bytestream, cartridge_size);
+    /// Load a Famicom Disk System game from a `.fds` image, with or without
+    /// its fwNES header, running on `bios` (`disksys.rom`).
+    pub fn from_disk_image(bytestream: &[u8], bios: &[u8]) -> Result<Self, CartridgeParseError> {
+        let image = DiskImage::try_from(bytestream)?;
+        if bios.len() != DiskSystem::BIOS_SIZE {
+            return Err(CartridgeParseError::InvalidBios(bios.len()));
+        }
 
-        // TODO: Do something other than just ignore the training data?
-        let has_trainer = header.has_trainer();
-        debug!("Has Trainer: {}", has_trainer);
+        let header = DiskSystem::header();
+        // The disks identify the game, not the BIOS
+        let metadata = CartridgeMetadata::new(&image.side_data(), &[], FileFormat::Fds);
+        debug!("Metadata: {:?}", metadata);
 
-        // Trainer
-        let bytestream = match has_trainer {
-            true => &bytestream[512..],
-            false => &bytestream[..],
+        let mut cartridge = Self {
+            header,
+            metadata,
+            virtual_program_memory: bios.to_vec(),
+            virtual_character_memory: vec![0; header.chr_ram_bytes()],
+            has_character_ram: true,
+            program_ram: vec![0; header.prg_ram_bytes()],
+            trainer: None,
+            mapper_id: header.mapper_id(),
+            program_banks_count: 0,
+            character_banks_count: 0,
+            // The BIOS is mirrored through 0x8000 - 0xFFFF like a small
+            // NROM, with PRG-RAM covering all of it below 0xE000
+            mapper: Mapper000::new(&header),
+            disk_system: Some(DiskSystem::new(&image)),
         };
-        log_read_progress("Trainer", bytestream, cartridge_size);
+        cartridge.power_on();
+
+        Ok(cartridge)
+    }
+
+    /// Build a cartridge from ROM data that has already been split out of a
+    /// file. `header` describes the board, whichever format the file was in,
+    /// and is corrected from the [`RomDatabase`] if the ROM is in it.
+    pub(super) fn from_parts(
+        mut header: Header,
+        trainer: Option<Vec<u8>>,
+        program_rom: Vec<u8>,
+        character_rom: Vec<u8>,
+        file_format: FileFormat,
+    ) -> Result<Self, CartridgeParseError> {
+        use CartridgeParseError::*;
+
+        if program_rom.is_empty() {
+            return Err(NoProgramRom);
+        }
+        let program_banks_count = (program_rom.len() / PRG_CHUNK_SIZE) as u16;
+        let character_banks_count = (character_rom.len() / CHR_CHUNK_SIZE) as u16;
 
-        // PROGRAM_MEMORY
-        let program_banks_count = header.prg_rom_size;
-        let prg_size = program_banks_count as usize * PRG_CHUNK_SIZE;
-        debug!("PRG Size: {}", prg_size);
+        let mut metadata = CartridgeMetadata::new(&program_rom, &character_rom, file_format);
+        debug!("Metadata: {:?}", metadata);
+        if let Some(game) = RomDatabase::bundled().find(&metadata) {
+            debug!("Database entry: {:?}", game);
+            header = game.correct_header(&header);
+            metadata.name = Some(game.name.clone());
+            metadata.publisher = game.publisher.clone();
+            metadata.board = game.board.clone();
+        }
 
-        let virtual_program_memory = match bytestream.len() < prg_size {
-            true => return Err(ProgramRomCutsOff),
-            false => bytestream[..prg_size].to_vec(),
+        let has_character_ram = character_rom.is_empty();
+        let virtual_character_memory = match has_character_ram {
+            true => vec![0; header.chr_ram_bytes() + header.chr_nvram_bytes()],
+            false => character_rom,
         };
-        let bytestream = &bytestream[prg_size..];
-        log_read_progress("PROG Memory", bytestream, cartridge_size);
-
-        // Character Memory
-        let character_banks_count = header.prg_chr_size;
-        let chr_size = character_banks_count as usize * CHR_CHUNK_SIZE;
-        let virtual_character_memory = match bytestream.len() < chr_size {
-            true => Err(CharacterRomCutsOff),
-            false => Ok(bytestream[..chr_size].to_vec()),
-        }?;
-        let bytestream = &bytestream[chr_size..];
-        log_read_progress("Character Memory", bytestream, cartridge_size);
-
-        // let bytestream = &bytestream[chr_size..];
-        // debug!("at very end: remaining: {}", bytestream.len());
 
         let mapper_id = header.mapper_id();
         debug!("Mapper ID: {}", mapper_id);
         // Currently only supports Mapper 000
-        let mapper = select_mapper(mapper_id, &header);
+        let mapper = select_mapper(mapper_id, &header).ok_or(UnsupportedMapper(mapper_id))?;
+
+        let mut program_ram_size = header.prg_ram_bytes() + header.prg_nvram_bytes();
+        if trainer.is_some() {
+            // The trainer needs somewhere to go, whatever the header says
+            program_ram_size = program_ram_size.max(8 * 1024);
+        }
+        let program_ram = vec![0; program_ram_size];
 
         // To Dos
         // TODO: Program banks, chracter banks,
-        // TODO: Deal with the 3 diff file formats
-        //      - Archaic iNES
-        //      - iNES 0.7
-        //      - iNES
         // TODO: Read rest of Flag6 and Flag7
         //
 
-        Ok(Self {
+        let mut cartridge = Self {
             header,
-            virtual_program_memory,
+            metadata,
+            virtual_program_memory: program_rom,
             virtual_character_memory,
+            has_character_ram,
+            program_ram,
+            trainer,
             mapper_id,
             program_banks_count,
             character_banks_count,
             mapper,
-        })
+            disk_system: None,
+        };
+        cartridge.power_on();
+
+        Ok(cartridge)
+    }
+}
+
+/// Sections of an iNES file, see [`Cartridge::from_ines`]
+pub(super) struct INesSections<'a> {
+    pub header: Header,
+    pub trainer: Option<&'a [u8]>,
+    pub program_rom: &'a [u8],
+    pub character_rom: &'a [u8],
+    /// PlayChoice ROMs, titles, or anything else after the CHR-ROM
+    pub rest: &'a [u8],
+}
+
+/// Split an iNES file into its sections, checking they are all there
+pub(super) fn split_ines(bytestream: &[u8]) -> Result<INesSections<'_>, CartridgeParseError> {
+    use CartridgeParseError::*;
+
+    let cartridge_size = bytestream.len();
+    let header_bytes: &[u8; 16] = match bytestream.get(..16) {
+        Some(bytes) => bytes.try_into().expect("Checked Length"),
+        None => return Err(TooShort(cartridge_size)),
+    };
+    let mut header: Header = match Header::try_from(header_bytes) {
+        Ok(header) => header,
+        Err(e) => return Err(InvalidHeader(e)),
+    };
+    // A NES 2.0 header describing more ROM than the file holds is more
+    // likely an old header with garbage in byte 7
+    header.format = Header::detect_format(header_bytes, Some(cartridge_size));
+
+    debug!("Header: {:?}", header);
+
+    let bytestream = &bytestream[16..];
+    log_read_progress("Debug", bytestream, cartridge_size);
+
+    let has_trainer = header.has_trainer();
+    debug!("Has Trainer: {}", has_trainer);
+
+    // Trainer
+    let (trainer, bytestream) = match has_trainer {
+        true => {
+            let (trainer, bytestream) =
+                take_section(bytestream, TRAINER_SIZE, cartridge_size).map_err(TrainerCutsOff)?;
+            (Some(trainer), bytestream)
+        }
+        false => (None, bytestream),
+    };
+    log_read_progress("Trainer", bytestream, cartridge_size);
+
+    // PROGRAM_MEMORY
+    let prg_size = header.prg_rom_bytes();
+    debug!("PRG Size: {}", prg_size);
+    if prg_size == 0 {
+        return Err(NoProgramRom);
+    }
+
+    let (program_rom, bytestream) =
+        take_section(bytestream, prg_size, cartridge_size).map_err(ProgramRomCutsOff)?;
+    log_read_progress("PROG Memory", bytestream, cartridge_size);
+
+    // Character Memory
+    let chr_size = header.chr_rom_bytes();
+    let (character_rom, bytestream) =
+        take_section(bytestream, chr_size, cartridge_size).map_err(CharacterRomCutsOff)?;
+    log_read_progress("Character Memory", bytestream, cartridge_size);
+
+    Ok(INesSections {
+        header,
+        trainer,
+        program_rom,
+        character_rom,
+        rest: bytestream,
+    })
+}
+
+/// Split `size` bytes off the front of `bytestream`, or describe where the
+/// file ends too early
+pub(crate) fn take_section(
+    bytestream: &[u8],
+    size: usize,
+    initial_size: usize,
+) -> Result<(&[u8], &[u8]), SectionCutOff> {
+    match bytestream.len() < size {
+        true => Err(SectionCutOff {
+            offset: initial_size - bytestream.len(),
+            expected: size,
+            found: bytestream.len(),
+        }),
+        false => Ok(bytestream.split_at(size)),
     }
 }
 
@@ -0,0 +1,84 @@
+use log::info;
+
+use crate::database::RomDatabase;
+
+use super::cartridge::split_ines;
+use super::{
+    CartridgeMetadata, CartridgeParseError, ConsoleType, FileFormat, Header, HeaderFormat,
+};
+
+/// # iNES to NES 2.0
+/// Rewrite an iNES file with a [NES 2.0](https://www.nesdev.org/wiki/NES_2.0)
+/// header, for cleaning up old dumps. The ROM data, and anything after it,
+/// is copied as is.
+///
+/// With `use_database`, fields the [`RomDatabase`] knows better are
+/// corrected, so files that are already NES 2.0 can be fixed too.
+/// Otherwise the fields of the old header are carried over, see
+/// [`Header::to_nes_two`].
+///
+/// Any mapper can be converted, not only those the emulator runs.
+pub fn convert_to_nes_two(
+    bytestream: &[u8],
+    use_database: bool,
+) -> Result<Vec<u8>, CartridgeParseError> {
+    let sections = split_ines(bytestream)?;
+    if sections.header.format != HeaderFormat::NesTwo {
+        info!("Converting {} header", sections.header.format.name());
+    }
+    let mut header = sections.header.to_nes_two();
+
+    if use_database {
+        let metadata = CartridgeMetadata::new(
+            sections.program_rom,
+            sections.character_rom,
+            FileFormat::INes,
+        );
+        if let Some(game) = RomDatabase::bundled().find(&metadata) {
+            info!(
+                "Correcting header from the database entry for {}",
+                game.name
+            );
+            header = game.correct_header(&header);
+        }
+    }
+
+    let mut file = header.to_bytes().to_vec();
+    if let Some(trainer) = sections.trainer {
+        file.extend(trainer);
+    }
+    file.extend(sections.program_rom);
+    file.extend(sections.character_rom);
+    file.extend(sections.rest);
+    Ok(file)
+}
+
+impl Header {
+    /// The same board described in NES 2.0, so fields older dialects lack
+    /// can be set. Sizes those dialects only imply, such as 8KB of PRG-RAM,
+    /// are written out explicitly.
+    pub fn to_nes_two(&self) -> Self {
+        if self.format == HeaderFormat::NesTwo {
+            return *self;
+        }
+        let mut header = Self::nes_two();
+        // Mirroring, battery, trainer and four-screen bits mean the same
+        header.flag_6 = self.flag_6 & 0x0F;
+        header.set_mapper(self.mapper_id(), 0);
+        header.set_prg_rom_bytes(self.prg_rom_bytes());
+        header.set_chr_rom_bytes(self.chr_rom_bytes());
+        match self.has_battery() {
+            true => header.set_prg_ram_bytes(0, self.prg_ram_bytes()),
+            false => header.set_prg_ram_bytes(self.prg_ram_bytes(), 0),
+        }
+        header.set_chr_ram_bytes(self.chr_ram_bytes(), 0);
+        header.set_timing(self.timing());
+        header.flag_7 |= match self.console_type() {
+            ConsoleType::Nes => 0,
+            ConsoleType::VsSystem => 1,
+            ConsoleType::Playchoice10 => 2,
+            ConsoleType::Extended(_) => 3,
+        };
+        header
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use log::debug;
 
 /// # Header of the iNES file format
@@ -5,8 +7,12 @@ use log::debug;
 /// ## Format
 /// [Wiki Source](https://www.nesdev.org/wiki/INES#iNES_file_format>)
 ///
+/// The same 16 bytes are read differently depending on which dialect of the
+/// format wrote them, see [`HeaderFormat`]. The raw bytes are kept as they
+/// were read and the accessors decode them according to `format`.
 ///
-#[derive(Debug, Copy, Clone)]
+/// NES 2.0: <https://www.nesdev.org/wiki/NES_2.0>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Header {
     pub name: [u8; 4],
     pub prg_rom_size: u8,
@@ -16,9 +22,82 @@ pub struct Header {
     pub prg_ram_size: u8,
     pub tv_system_1: u8,
     pub tv_system_2: u8,
+    /// Bytes 11-15. Padding in iNES, which some rippers filled with their
+    /// name. Hold RAM sizes, timing, console type, misc ROMs and the default
+    /// expansion device in NES 2.0.
+    pub extended: [u8; 5],
+    pub format: HeaderFormat,
+}
+
+/// Dialect of the iNES header
+///
+/// <https://www.nesdev.org/wiki/INES#Variant_comparison>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeaderFormat {
+    /// Original format. Only bytes 4-6 can be trusted; anything after may
+    /// be garbage such as a ripper's name (`DiskDude!`).
+    ArchaicINes,
+    /// Byte 7 is valid, bytes 8-15 are not.
+    INes07,
+    /// Bytes 8-10 are valid extensions and 11-15 are zero.
+    INes,
+    NesTwo,
+}
+
+/// Nametable arrangement hard-wired on the board. Mappers with their own
+/// mirroring control override this.
+///
+/// <https://www.nesdev.org/wiki/Mirroring#Nametable_Mirroring>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mirroring {
+    /// Vertical arrangement, for games that scroll vertically
+    Horizontal,
+    /// Horizontal arrangement, for games that scroll horizontally
+    Vertical,
+    /// The board has an extra 2K of VRAM, giving four unique nametables
+    FourScreen,
+}
+
+/// CPU/PPU timing, or TV system, the game was made for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Timing {
+    /// RP2C02, North America, Japan, South Korea, Taiwan
+    Ntsc,
+    /// RP2C07, Western Europe, Australia
+    Pal,
+    /// Runs identically on either
+    MultipleRegion,
+    /// UMC 6527P, Eastern Europe, Russia, Mainland China, India, Africa
+    Dendy,
+}
+
+/// Type of console the game runs on
+///
+/// <https://www.nesdev.org/wiki/NES_2.0#Console_Type>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConsoleType {
+    Nes,
+    VsSystem,
+    Playchoice10,
+    /// Extended console type from byte 13. `0x0`-`0x2` are never used here,
+    /// as they are covered by the other variants.
+    Extended(u8),
+}
+
+/// PPU and hardware type of a Vs. System game, from byte 13 of a NES 2.0
+/// header
+///
+/// <https://www.nesdev.org/wiki/NES_2.0#Vs._System_Type>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VsSystemType {
+    pub ppu: u8,
+    pub hardware: u8,
 }
 
 impl Header {
+    const PRG_ROM_UNIT: usize = 16 * 1024;
+    const CHR_ROM_UNIT: usize = 8 * 1024;
+
     /// Returns true if the header says the Cartridge contains
     /// trainer data.
     /// This information exists in the `3` byte of the `flag_6` field
@@ -27,20 +106,504 @@ impl Header {
         self.flag_6 & 0b0000_0100 != 0
     }
 
-    /// Returns the mapper number by parsing flag 6 and 7
-    pub fn mapper_id(&self) -> u8 {
+    /// Returns true if the cartridge has battery-backed PRG-RAM, or other
+    /// persistent memory, at 0x6000 - 0x7FFF.
+    /// This information exists in the `1` bit of the `flag_6` field.
+    pub fn has_battery(&self) -> bool {
+        self.flag_6 & 0b0000_0010 != 0
+    }
+
+    /// Returns true if the board provides its own VRAM for four unique
+    /// nametables.
+    /// This information exists in the `3` bit of the `flag_6` field.
+    pub fn has_four_screen(&self) -> bool {
+        self.flag_6 & 0b0000_1000 != 0
+    }
+
+    /// Nametable mirroring, from the `0` and `3` bits of the `flag_6` field
+    pub fn mirroring(&self) -> Mirroring {
+        match (self.has_four_screen(), self.flag_6 & 0b0000_0001 != 0) {
+            (true, _) => Mirroring::FourScreen,
+            (false, true) => Mirroring::Vertical,
+            (false, false) => Mirroring::Horizontal,
+        }
+    }
+
+    /// Size of PRG-ROM in bytes.
+    /// NES 2.0 adds a high nybble to the bank count in byte 9, or, when that
+    /// nybble is `0xF`, stores the size as an exponent and multiplier.
+    pub fn prg_rom_bytes(&self) -> usize {
+        match self.format {
+            HeaderFormat::NesTwo => Self::nes_two_rom_bytes(
+                self.prg_rom_size,
+                self.tv_system_1 & 0x0F,
+                Self::PRG_ROM_UNIT,
+            ),
+            _ => self.prg_rom_size as usize * Self::PRG_ROM_UNIT,
+        }
+    }
+
+    /// Size of CHR-ROM in bytes. See [`Header::prg_rom_bytes`].
+    pub fn chr_rom_bytes(&self) -> usize {
+        match self.format {
+            HeaderFormat::NesTwo => Self::nes_two_rom_bytes(
+                self.prg_chr_size,
+                self.tv_system_1 >> 4,
+                Self::CHR_ROM_UNIT,
+            ),
+            _ => self.prg_chr_size as usize * Self::CHR_ROM_UNIT,
+        }
+    }
+
+    /// Size of volatile PRG-RAM in bytes.
+    /// iNES stores it in flag 8 in 8KB units; a value of 0 infers 8KB for
+    /// compatibility, as most dumps leave it unset even when the board has
+    /// RAM. Older dialects always get 8KB.
+    pub fn prg_ram_bytes(&self) -> usize {
+        match self.format {
+            HeaderFormat::NesTwo => Self::nes_two_ram_bytes(self.tv_system_2 & 0x0F),
+            HeaderFormat::INes => self.prg_ram_size.max(1) as usize * 8 * 1024,
+            _ => 8 * 1024,
+        }
+    }
+
+    /// Size of battery-backed PRG-RAM (PRG-NVRAM/EEPROM) in bytes.
+    /// Only NES 2.0 separates this from [`Header::prg_ram_bytes`]; earlier
+    /// formats mark all PRG-RAM as battery-backed with the battery bit.
+    pub fn prg_nvram_bytes(&self) -> usize {
+        match self.format {
+            HeaderFormat::NesTwo => Self::nes_two_ram_bytes(self.tv_system_2 >> 4),
+            _ => 0,
+        }
+    }
+
+    /// Returns true if the board uses CHR-RAM instead of CHR-ROM, which
+    /// the header signals by declaring no CHR-ROM banks.
+    pub fn has_chr_ram(&self) -> bool {
+        self.chr_rom_bytes() == 0
+    }
+
+    /// Size of volatile CHR-RAM in bytes. iNES has no field for it, so
+    /// boards with CHR-RAM are assumed to have 8KB.
+    pub fn chr_ram_bytes(&self) -> usize {
+        match self.format {
+            HeaderFormat::NesTwo => Self::nes_two_ram_bytes(self.extended[0] & 0x0F),
+            _ if self.has_chr_ram() => 8 * 1024,
+            _ => 0,
+        }
+    }
+
+    /// Size of battery-backed CHR-RAM in bytes. Only NES 2.0 has this.
+    pub fn chr_nvram_bytes(&self) -> usize {
+        match self.format {
+            HeaderFormat::NesTwo => Self::nes_two_ram_bytes(self.extended[0] >> 4),
+            _ => 0,
+        }
+    }
+
+    /// Returns the mapper number by parsing flag 6 and 7, plus byte 8 in
+    /// NES 2.0 which extends it to 12 bits
+    pub fn mapper_id(&self) -> u16 {
         // The lower nybble of the mapper number is in flag 6: bytes [4-7]
+        let low = (self.flag_6 >> 4) as u16;
         // The upper nybble of the mapper number is in flag 7: bytes [4-7]
-        (self.flag_7 & 0b1111_0000) | (self.flag_6 >> 4)
+        let middle = (self.flag_7 & 0b1111_0000) as u16;
+        match self.format {
+            HeaderFormat::NesTwo => ((self.prg_ram_size as u16 & 0x0F) << 8) | middle | low,
+            HeaderFormat::INes | HeaderFormat::INes07 => middle | low,
+            HeaderFormat::ArchaicINes => low,
+        }
+    }
+
+    /// Variant of the mapper's board, from the upper nybble of byte 8.
+    /// Always 0 before NES 2.0.
+    pub fn submapper_id(&self) -> u8 {
+        match self.format {
+            HeaderFormat::NesTwo => self.prg_ram_size >> 4,
+            _ => 0,
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        match self.format {
+            HeaderFormat::NesTwo => match self.extended[1] & 0b11 {
+                0 => Timing::Ntsc,
+                1 => Timing::Pal,
+                2 => Timing::MultipleRegion,
+                _ => Timing::Dendy,
+            },
+            HeaderFormat::INes => match self.tv_system_1 & 1 {
+                0 => Timing::Ntsc,
+                _ => Timing::Pal,
+            },
+            _ => Timing::Ntsc,
+        }
+    }
+
+    pub fn console_type(&self) -> ConsoleType {
+        match self.format {
+            HeaderFormat::NesTwo => match self.flag_7 & 0b11 {
+                0 => ConsoleType::Nes,
+                1 => ConsoleType::VsSystem,
+                2 => ConsoleType::Playchoice10,
+                _ => ConsoleType::Extended(self.extended[2] & 0x0F),
+            },
+            HeaderFormat::INes | HeaderFormat::INes07 => {
+                match (self.flag_7 & 0b01 != 0, self.flag_7 & 0b10 != 0) {
+                    (true, _) => ConsoleType::VsSystem,
+                    (false, true) => ConsoleType::Playchoice10,
+                    (false, false) => ConsoleType::Nes,
+                }
+            }
+            HeaderFormat::ArchaicINes => ConsoleType::Nes,
+        }
+    }
+
+    /// PPU and protection hardware of a Vs. System game.
+    /// Only NES 2.0 headers record this.
+    pub fn vs_system_type(&self) -> Option<VsSystemType> {
+        match (self.format, self.console_type()) {
+            (HeaderFormat::NesTwo, ConsoleType::VsSystem) => Some(VsSystemType {
+                ppu: self.extended[2] & 0x0F,
+                hardware: self.extended[2] >> 4,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Number of miscellaneous ROMs after the CHR-ROM, such as the
+    /// PlayChoice-10 INST-ROM. Always 0 before NES 2.0.
+    pub fn misc_rom_count(&self) -> u8 {
+        match self.format {
+            HeaderFormat::NesTwo => self.extended[3] & 0b11,
+            _ => 0,
+        }
+    }
+
+    /// Controller or other device plugged in by default.
+    /// Always 0 (unspecified) before NES 2.0.
+    ///
+    /// <https://www.nesdev.org/wiki/NES_2.0#Default_Expansion_Device>
+    pub fn default_expansion_device(&self) -> u8 {
+        match self.format {
+            HeaderFormat::NesTwo => self.extended[4] & 0b0011_1111,
+            _ => 0,
+        }
+    }
+
+    /// Size of everything the header says follows it in the file: trainer,
+    /// PRG-ROM and CHR-ROM
+    pub fn rom_image_bytes(&self) -> usize {
+        let trainer = match self.has_trainer() {
+            true => 512,
+            false => 0,
+        };
+        self.prg_rom_bytes()
+            .saturating_add(self.chr_rom_bytes())
+            .saturating_add(trainer)
+    }
+
+    /// Blank NES 2.0 header: no ROM, no RAM, mapper 0, horizontal mirroring,
+    /// NTSC. Used to describe boards loaded from formats without an iNES
+    /// header, filled in with the setters below.
+    pub fn nes_two() -> Self {
+        let mut bytestream = [0; 16];
+        bytestream[..4].copy_from_slice(b"NES\x1A");
+        bytestream[7] = 0b0000_1000;
+        Self::from_bytes(&bytestream, HeaderFormat::NesTwo)
+    }
+
+    /// Set the 12 bit mapper number and the submapper.
+    /// The setters write NES 2.0 fields and are only meaningful on a header
+    /// in that format.
+    pub fn set_mapper(&mut self, mapper: u16, submapper: u8) {
+        self.flag_6 = (self.flag_6 & 0x0F) | ((mapper as u8 & 0x0F) << 4);
+        self.flag_7 = (self.flag_7 & 0x0F) | (mapper as u8 & 0xF0);
+        self.prg_ram_size = ((submapper & 0x0F) << 4) | ((mapper >> 8) as u8 & 0x0F);
+    }
+
+    pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.flag_6 &= !0b0000_1001;
+        self.flag_6 |= match mirroring {
+            Mirroring::Horizontal => 0b0000_0000,
+            Mirroring::Vertical => 0b0000_0001,
+            Mirroring::FourScreen => 0b0000_1000,
+        };
+    }
+
+    pub fn set_battery(&mut self, battery: bool) {
+        self.flag_6 &= !0b0000_0010;
+        if battery {
+            self.flag_6 |= 0b0000_0010;
+        }
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.extended[1] = (self.extended[1] & !0b11)
+            | match timing {
+                Timing::Ntsc => 0,
+                Timing::Pal => 1,
+                Timing::MultipleRegion => 2,
+                Timing::Dendy => 3,
+            };
+    }
+
+    /// Set the PRG-ROM size. Sizes that are not a whole number of 16K banks
+    /// use the exponent-multiplier notation; sizes that can't be written in
+    /// either are rounded up to the next bank.
+    pub fn set_prg_rom_bytes(&mut self, bytes: usize) {
+        let (lsb, msb) = Self::encode_nes_two_rom_bytes(bytes, Self::PRG_ROM_UNIT);
+        self.prg_rom_size = lsb;
+        self.tv_system_1 = (self.tv_system_1 & 0xF0) | msb;
+    }
+
+    /// Set the CHR-ROM size. See [`Header::set_prg_rom_bytes`].
+    pub fn set_chr_rom_bytes(&mut self, bytes: usize) {
+        let (lsb, msb) = Self::encode_nes_two_rom_bytes(bytes, Self::CHR_ROM_UNIT);
+        self.prg_chr_size = lsb;
+        self.tv_system_1 = (self.tv_system_1 & 0x0F) | (msb << 4);
+    }
+
+    /// Set the volatile and battery-backed PRG-RAM sizes, rounded up to a
+    /// size NES 2.0 can express
+    pub fn set_prg_ram_bytes(&mut self, volatile: usize, non_volatile: usize) {
+        self.tv_system_2 = (Self::encode_nes_two_ram_bytes(non_volatile) << 4)
+            | Self::encode_nes_two_ram_bytes(volatile);
+    }
+
+    /// Set the volatile and battery-backed CHR-RAM sizes, rounded up to a
+    /// size NES 2.0 can express
+    pub fn set_chr_ram_bytes(&mut self, volatile: usize, non_volatile: usize) {
+        self.extended[0] = (Self::encode_nes_two_ram_bytes(non_volatile) << 4)
+            | Self::encode_nes_two_ram_bytes(volatile);
+    }
+
+    /// Work out which dialect a header is in, following the recommended
+    /// detection procedure from the wiki.
+    /// `file_size` is the size of the whole file, used to reject NES 2.0
+    /// headers whose ROM sizes can't be right.
+    ///
+    /// <https://www.nesdev.org/wiki/INES#Variant_comparison>
+    pub fn detect_format(bytestream: &[u8; 16], file_size: Option<usize>) -> HeaderFormat {
+        match bytestream[7] & 0b0000_1100 {
+            0b0000_1000 => {
+                let header = Self::from_bytes(bytestream, HeaderFormat::NesTwo);
+                match file_size {
+                    Some(size) if header.rom_image_bytes() > size.saturating_sub(16) => {
+                        HeaderFormat::INes07
+                    }
+                    _ => HeaderFormat::NesTwo,
+                }
+            }
+            0b0000_0000 if bytestream[12..16] == [0, 0, 0, 0] => HeaderFormat::INes,
+            0b0000_0000 => HeaderFormat::INes07,
+            _ => HeaderFormat::ArchaicINes,
+        }
+    }
+
+    /// Size of a NES 2.0 ROM area from its LSB byte and MSB nybble
+    fn nes_two_rom_bytes(lsb: u8, msb: u8, unit: usize) -> usize {
+        match msb {
+            // Exponent-multiplier notation: EEEEEEMM is 2^E * (MM * 2 + 1)
+            0x0F => {
+                let exponent = (lsb >> 2) as u32;
+                let multiplier = ((lsb & 0b11) as usize) * 2 + 1;
+                1usize
+                    .checked_shl(exponent)
+                    .and_then(|size| size.checked_mul(multiplier))
+                    .unwrap_or(usize::MAX)
+            }
+            _ => (((msb as usize) << 8) | lsb as usize) * unit,
+        }
+    }
+
+    /// Size of a NES 2.0 RAM area from its shift count. 0 means no RAM,
+    /// anything else is `64 << shift` bytes
+    fn nes_two_ram_bytes(shift: u8) -> usize {
+        match shift {
+            0 => 0,
+            shift => 64 << shift,
+        }
+    }
+
+    /// LSB byte and MSB nybble for a NES 2.0 ROM area of `bytes`
+    fn encode_nes_two_rom_bytes(bytes: usize, unit: usize) -> (u8, u8) {
+        let banks = bytes / unit;
+        if bytes.is_multiple_of(unit) && banks <= 0xEFF {
+            return (banks as u8, (banks >> 8) as u8);
+        }
+        let exponent = bytes.trailing_zeros();
+        match bytes >> exponent {
+            multiplier @ (1 | 3 | 5 | 7) if exponent < 64 => {
+                (((exponent as u8) << 2) | (multiplier as u8 >> 1), 0x0F)
+            }
+            _ => {
+                let banks = bytes.div_ceil(unit).min(0xEFF);
+                (banks as u8, (banks >> 8) as u8)
+            }
+        }
+    }
+
+    /// Smallest NES 2.0 shift count holding `bytes` of RAM
+    fn encode_nes_two_ram_bytes(bytes: usize) -> u8 {
+        match bytes {
+            0 => 0,
+            bytes => (1..15).find(|shift| 64 << shift >= bytes).unwrap_or(15),
+        }
+    }
+
+    /// The 16 bytes of the header, as written at the start of a `.nes`
+    /// file. Parsing them gives back the same header.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..4].copy_from_slice(&self.name);
+        bytes[4] = self.prg_rom_size;
+        bytes[5] = self.prg_chr_size;
+        bytes[6] = self.flag_6;
+        bytes[7] = self.flag_7;
+        bytes[8] = self.prg_ram_size;
+        bytes[9] = self.tv_system_1;
+        bytes[10] = self.tv_system_2;
+        bytes[11..].copy_from_slice(&self.extended);
+        bytes
+    }
+
+    fn from_bytes(bytestream: &[u8; 16], format: HeaderFormat) -> Self {
+        Self {
+            name: [bytestream[0], bytestream[1], bytestream[2], bytestream[3]],
+            prg_rom_size: bytestream[4],
+            prg_chr_size: bytestream[5],
+            flag_6: bytestream[6],
+            flag_7: bytestream[7],
+            prg_ram_size: bytestream[8],
+            tv_system_1: bytestream[9],
+            tv_system_2: bytestream[10],
+            extended: [
+                bytestream[11],
+                bytestream[12],
+                bytestream[13],
+                bytestream[14],
+                bytestream[15],
+            ],
+            format,
+        }
+    }
+}
+
+impl HeaderFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeaderFormat::ArchaicINes => "Archaic iNES",
+            HeaderFormat::INes07 => "iNES 0.7",
+            HeaderFormat::INes => "iNES",
+            HeaderFormat::NesTwo => "NES 2.0",
+        }
     }
 }
 
-#[derive(Debug)]
+impl Mirroring {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mirroring::Horizontal => "Horizontal",
+            Mirroring::Vertical => "Vertical",
+            Mirroring::FourScreen => "Four-screen",
+        }
+    }
+}
+
+impl Timing {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Timing::Ntsc => "NTSC",
+            Timing::Pal => "PAL",
+            Timing::MultipleRegion => "Multiple region",
+            Timing::Dendy => "Dendy",
+        }
+    }
+}
+
+impl ConsoleType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsoleType::Nes => "NES/Famicom",
+            ConsoleType::VsSystem => "Vs. System",
+            ConsoleType::Playchoice10 => "PlayChoice-10",
+            ConsoleType::Extended(kind) => match kind {
+                0x3 => "Famiclone with decimal mode",
+                0x4 => "NES/Famicom with EPSM",
+                0x5 => "V.R. Technology VT01",
+                0x6 => "V.R. Technology VT02",
+                0x7 => "V.R. Technology VT03",
+                0x8 => "V.R. Technology VT09",
+                0x9 => "V.R. Technology VT32",
+                0xA => "V.R. Technology VT369",
+                0xB => "UMC UM6578",
+                0xC => "Famicom Network System",
+                _ => "Unknown",
+            },
+        }
+    }
+}
+
+impl VsSystemType {
+    pub fn ppu_name(&self) -> &'static str {
+        match self.ppu {
+            0x0 => "RP2C03B",
+            0x1 => "RP2C03G",
+            0x2 => "RP2C04-0001",
+            0x3 => "RP2C04-0002",
+            0x4 => "RP2C04-0003",
+            0x5 => "RP2C04-0004",
+            0x6 => "RC2C03B",
+            0x7 => "RC2C03C",
+            0x8 => "RC2C05-01",
+            0x9 => "RC2C05-02",
+            0xA => "RC2C05-03",
+            0xB => "RC2C05-04",
+            0xC => "RC2C05-05",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn hardware_name(&self) -> &'static str {
+        match self.hardware {
+            0x0 => "Vs. Unisystem",
+            0x1 => "Vs. Unisystem (RBI Baseball protection)",
+            0x2 => "Vs. Unisystem (TKO Boxing protection)",
+            0x3 => "Vs. Unisystem (Super Xevious protection)",
+            0x4 => "Vs. Unisystem (Vs. Ice Climber Japan protection)",
+            0x5 => "Vs. Dual System",
+            0x6 => "Vs. Dual System (Raid on Bungeling Bay protection)",
+            _ => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum HeaderParseError {
-    InvalidStreamLength,
-    NoNesConstant,
+    /// Header must be exactly 16 bytes. Holds the length given
+    InvalidStreamLength(usize),
+    /// Bytes 0-3 are not `NES` followed by `0x1A`. Holds the bytes found
+    NoNesConstant([u8; 4]),
 }
 
+impl Display for HeaderParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderParseError::InvalidStreamLength(length) => {
+                write!(f, "Header is {} bytes long, expected 16", length)
+            }
+            HeaderParseError::NoNesConstant(found) => write!(
+                f,
+                "Bytes 0-3 are {:02X?}, expected \"NES\\x1A\" [4E, 45, 53, 1A]",
+                found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeaderParseError {}
+
 impl TryFrom<&[u8; 16]> for Header {
     type Error = HeaderParseError;
 
@@ -61,31 +624,40 @@ impl TryFrom<&[u8; 16]> for Header {
     /// | 9:      | Flags 9 - TV system (rarely used extension)                                                 |
     /// | 10:     | Flags 10 - TV system, PRG-RAM presence (unofficial, rarely used extension)                  |
     /// | 11-15:  | Unused padding (should be filled with zero-but some rippers put their name on bytes 7-15)   |
+    ///
+    /// In NES 2.0 headers, bytes 8-15 are laid out as:
+    ///
+    /// | bytes:  | Description                                                                                 |
+    /// |---------|---------------------------------------------------------------------------------------------|
+    /// | 8:      | Mapper MSB (bits 0-3), submapper (bits 4-7)                                                 |
+    /// | 9:      | PRG-ROM size MSB (bits 0-3), CHR-ROM size MSB (bits 4-7)                                    |
+    /// | 10:     | PRG-RAM shift count (bits 0-3), PRG-NVRAM shift count (bits 4-7)                            |
+    /// | 11:     | CHR-RAM shift count (bits 0-3), CHR-NVRAM shift count (bits 4-7)                            |
+    /// | 12:     | CPU/PPU timing                                                                              |
+    /// | 13:     | Vs. System PPU and hardware type, or extended console type                                  |
+    /// | 14:     | Number of miscellaneous ROMs                                                                |
+    /// | 15:     | Default expansion device                                                                    |
+    ///
+    /// The format is detected without knowing the size of the file. Use
+    /// [`Header::detect_format`] with the file size to double check a
+    /// NES 2.0 header.
     fn try_from(bytestream: &[u8; 16]) -> Result<Self, Self::Error> {
-        if bytestream.len() != 16 {
-            return Err(HeaderParseError::InvalidStreamLength);
-        }
-
         if bytestream[0..4] != [b'N', b'E', b'S', 0x1A] {
-            return Err(HeaderParseError::NoNesConstant);
+            return Err(HeaderParseError::NoNesConstant([
+                bytestream[0],
+                bytestream[1],
+                bytestream[2],
+                bytestream[3],
+            ]));
         }
 
-
         debug!("Header Bytes: ");
         for byte in bytestream.iter() {
             debug!("\t{}\t= 0x{:x}", byte, byte);
         }
 
-        Ok(Self {
-            name: [bytestream[0], bytestream[1], bytestream[2], bytestream[3]],
-            prg_rom_size: bytestream[4],
-            prg_chr_size: bytestream[5],
-            flag_6: bytestream[6],
-            flag_7: bytestream[7],
-            prg_ram_size: bytestream[8],
-            tv_system_1: bytestream[9],
-            tv_system_2: bytestream[10],
-        })
+        let format = Self::detect_format(bytestream, None);
+        Ok(Self::from_bytes(bytestream, format))
     }
 }
 
@@ -94,7 +666,7 @@ impl TryFrom<&[u8]> for Header {
 
     fn try_from(bytestream: &[u8]) -> Result<Self, Self::Error> {
         if bytestream.len() != 16 {
-            return Err(HeaderParseError::InvalidStreamLength);
+            return Err(HeaderParseError::InvalidStreamLength(bytestream.len()));
         }
         let header_bytestream: &[u8; 16] = bytestream.try_into().expect("Checked Length");
         Self::try_from(header_bytestream)
@@ -0,0 +1,66 @@
+use crate::checksum::{crc32, crc32_update, sha1};
+
+/// # Cartridge Metadata
+/// Identifies the ROM inside a cartridge, independently of its header.
+///
+/// Hashes cover the PRG-ROM followed by the CHR-ROM, without the header or
+/// trainer, which is how ROM databases such as NesCartDB key their entries.
+/// CHR-RAM is not part of the ROM and is never hashed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CartridgeMetadata {
+    pub crc32: u32,
+    pub prg_crc32: u32,
+    pub chr_crc32: u32,
+    pub sha1: [u8; 20],
+    /// Format of the file the cartridge was loaded from
+    pub file_format: FileFormat,
+    /// Game title, from the ROM database or the file
+    pub name: Option<String>,
+    pub publisher: Option<String>,
+    /// Board the game was released on, e.g. `NES-SNROM`, when known
+    pub board: Option<String>,
+}
+
+/// File formats a [`super::Cartridge`] can be loaded from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileFormat {
+    /// iNES or NES 2.0, see [`super::HeaderFormat`]
+    INes,
+    /// <https://www.nesdev.org/wiki/UNIF>
+    Unif,
+    /// Famicom Disk System image, see [`crate::fds::DiskImage`]
+    Fds,
+}
+
+impl CartridgeMetadata {
+    pub fn new(prg_rom: &[u8], chr_rom: &[u8], file_format: FileFormat) -> Self {
+        Self {
+            crc32: crc32_update(crc32(prg_rom), chr_rom),
+            prg_crc32: crc32(prg_rom),
+            chr_crc32: crc32(chr_rom),
+            sha1: sha1(&[prg_rom, chr_rom]),
+            file_format,
+            name: None,
+            publisher: None,
+            board: None,
+        }
+    }
+
+    /// SHA-1 as a lowercase hex string
+    pub fn sha1_hex(&self) -> String {
+        self.sha1
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+impl FileFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileFormat::INes => "iNES",
+            FileFormat::Unif => "UNIF",
+            FileFormat::Fds => "FDS",
+        }
+    }
+}
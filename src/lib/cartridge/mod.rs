@@ -1,8 +1,17 @@
 #![allow(clippy::module_inception)]
+mod battery;
 mod cartridge;
+mod convert;
 mod header;
+mod metadata;
+mod unif;
 
-pub use cartridge::Cartridge;
-
-pub use header::Header;
+pub use battery::BatterySave;
+pub use cartridge::{Cartridge, CartridgeParseError, SectionCutOff};
+pub(crate) use cartridge::take_section;
+pub use convert::convert_to_nes_two;
+pub use metadata::{CartridgeMetadata, FileFormat};
 
+pub use header::{
+    ConsoleType, Header, HeaderFormat, HeaderParseError, Mirroring, Timing, VsSystemType,
+};
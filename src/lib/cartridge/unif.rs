@@ -0,0 +1,214 @@
+use log::{debug, warn};
+
+use super::cartridge::take_section;
+use super::{Cartridge, CartridgeParseError, FileFormat, Header, Mirroring, Timing};
+
+/// First four bytes of every UNIF file
+pub const SIGNATURE: [u8; 4] = *b"UNIF";
+const HEADER_SIZE: usize = 32;
+/// Chunk ID followed by its length as a little endian `u32`
+const CHUNK_HEADER_SIZE: usize = 8;
+
+/// # UNIF
+/// Parse a UNIF (Universal NES Image Format) file.
+///
+/// ## Format
+/// [Wiki Source](https://www.nesdev.org/wiki/UNIF)
+///
+/// A 32 byte header (`UNIF`, a `u32` revision, 24 bytes of padding)
+/// followed by chunks of a 4 byte ID, a `u32` length and the data.
+/// Chunks read here:
+///
+/// | ID | Contents |
+/// |--------|----------------------------------------------------------|
+/// | `MAPR` | Board name, null terminated, e.g. `NES-SNROM` |
+/// | `PRG0`-`PRGF` | PRG-ROM, concatenated in order |
+/// | `CHR0`-`CHRF` | CHR-ROM, concatenated in order |
+/// | `MIRR` | Mirroring: 0 horizontal, 1 vertical, 2/3 one screen, 4 four screen, 5 mapper controlled |
+/// | `BATR` | Present if PRG-RAM is battery backed |
+/// | `NAME` | Game title, null terminated |
+/// | `TVCI` | 0 NTSC, 1 PAL, 2 both |
+///
+/// UNIF identifies boards by name rather than mapper number, so the name is
+/// looked up in [`board_mapper`] and the cartridge gets a NES 2.0 header
+/// describing the board.
+pub(super) fn parse(bytestream: &[u8]) -> Result<Cartridge, CartridgeParseError> {
+    use CartridgeParseError::*;
+
+    let file_size = bytestream.len();
+    if file_size < HEADER_SIZE {
+        return Err(TooShort(file_size));
+    }
+    let revision = u32::from_le_bytes(bytestream[4..8].try_into().expect("Checked Length"));
+    debug!("UNIF revision: {}", revision);
+
+    let mut board = None;
+    let mut name = None;
+    let mut program_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut character_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut mirroring = None;
+    let mut battery = false;
+    let mut timing = Timing::Ntsc;
+
+    let mut bytestream = &bytestream[HEADER_SIZE..];
+    while !bytestream.is_empty() {
+        let (chunk_header, rest) =
+            take_section(bytestream, CHUNK_HEADER_SIZE, file_size).map_err(UnifChunkCutsOff)?;
+        let id: [u8; 4] = chunk_header[..4].try_into().expect("Checked Length");
+        let length = u32::from_le_bytes(chunk_header[4..].try_into().expect("Checked Length"));
+        let (data, rest) =
+            take_section(rest, length as usize, file_size).map_err(UnifChunkCutsOff)?;
+        bytestream = rest;
+        debug!(
+            "UNIF chunk {:?}: {} bytes",
+            String::from_utf8_lossy(&id),
+            length
+        );
+
+        match &id {
+            b"MAPR" => board = Some(read_string(data)),
+            b"NAME" => name = Some(read_string(data)),
+            b"MIRR" => mirroring = data.first().copied(),
+            b"BATR" => battery = data.first().is_none_or(|&battery| battery != 0),
+            b"TVCI" => {
+                timing = match data.first() {
+                    Some(1) => Timing::Pal,
+                    Some(2) => Timing::MultipleRegion,
+                    _ => Timing::Ntsc,
+                }
+            }
+            [b'P', b'R', b'G', index] => match chunk_index(*index) {
+                Some(index) => program_chunks[index] = Some(data),
+                None => warn!("Ignoring UNIF chunk PRG{}", *index as char),
+            },
+            [b'C', b'H', b'R', index] => match chunk_index(*index) {
+                Some(index) => character_chunks[index] = Some(data),
+                None => warn!("Ignoring UNIF chunk CHR{}", *index as char),
+            },
+            _ => {}
+        }
+    }
+
+    let board = board.ok_or(UnifNoBoard)?;
+    let (mapper, submapper) = match board_mapper(&board) {
+        Some(mapper) => mapper,
+        None => return Err(UnifUnknownBoard(board)),
+    };
+    let program_rom: Vec<u8> = program_chunks
+        .iter()
+        .flatten()
+        .copied()
+        .flatten()
+        .copied()
+        .collect();
+    let character_rom: Vec<u8> = character_chunks
+        .iter()
+        .flatten()
+        .copied()
+        .flatten()
+        .copied()
+        .collect();
+
+    let mut header = Header::nes_two();
+    header.set_mapper(mapper, submapper);
+    header.set_prg_rom_bytes(program_rom.len());
+    header.set_chr_rom_bytes(character_rom.len());
+    // UNIF doesn't record RAM sizes; 8K is what nearly every board has
+    match battery {
+        true => header.set_prg_ram_bytes(0, 8 * 1024),
+        false => header.set_prg_ram_bytes(8 * 1024, 0),
+    }
+    if character_rom.is_empty() {
+        header.set_chr_ram_bytes(8 * 1024, 0);
+    }
+    header.set_battery(battery);
+    header.set_timing(timing);
+    // One screen and mapper controlled mirroring are left to the mapper
+    match mirroring {
+        Some(1) => header.set_mirroring(Mirroring::Vertical),
+        Some(4) => header.set_mirroring(Mirroring::FourScreen),
+        _ => header.set_mirroring(Mirroring::Horizontal),
+    }
+    debug!("Header: {:?}", header);
+
+    let mut cartridge =
+        Cartridge::from_parts(header, None, program_rom, character_rom, FileFormat::Unif)?;
+    // The database's names take priority
+    let metadata = &mut cartridge.metadata;
+    metadata.name = metadata.name.take().or(name);
+    metadata.board = metadata.board.take().or(Some(board));
+    Ok(cartridge)
+}
+
+/// Index of a `PRGx`/`CHRx` chunk from its last character, a hex digit
+fn chunk_index(digit: u8) -> Option<usize> {
+    (digit as char).to_digit(16).map(|index| index as usize)
+}
+
+/// Text up to the first null byte, which UNIF strings end with
+fn read_string(data: &[u8]) -> String {
+    let end = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+/// Mapper and submapper numbers for a UNIF board name.
+///
+/// Nintendo boards are matched without their `NES-`/`HVC-` prefix. Boards
+/// from unlicensed and multicart makers (`UNL-`, `BMC-`) are matched on
+/// their full name.
+///
+/// <https://www.nesdev.org/wiki/UNIF_to_NES_2.0_Mapper_Mapping_Table>
+fn board_mapper(board: &str) -> Option<(u16, u8)> {
+    let board = board.trim().to_ascii_uppercase();
+    let nintendo_board = board
+        .strip_prefix("NES-")
+        .or_else(|| board.strip_prefix("HVC-"))
+        .unwrap_or(&board);
+
+    let mapper = match nintendo_board {
+        "NROM" | "NROM-128" | "NROM-256" | "RROM" | "RROM-128" => (0, 0),
+        "SAROM" | "SBROM" | "SCROM" | "SC1ROM" | "SEROM" | "SFROM" | "SGROM" | "SHROM"
+        | "SJROM" | "SKROM" | "SLROM" | "SL1ROM" | "SL2ROM" | "SL3ROM" | "SLRROM" | "SNROM"
+        | "SOROM" | "SUROM" | "SXROM" => (1, 0),
+        "UNROM" | "UOROM" => (2, 0),
+        "CNROM" => (3, 0),
+        "TBROM" | "TEROM" | "TFROM" | "TGROM" | "TKROM" | "TLROM" | "TL1ROM" | "TL2ROM"
+        | "TNROM" | "TR1ROM" | "TSROM" | "TVROM" | "B4" => (4, 0),
+        "EKROM" | "ELROM" | "ETROM" | "EWROM" => (5, 0),
+        "AMROM" | "ANROM" | "AN1ROM" | "AOROM" => (7, 0),
+        "PNROM" | "PEEOROM" => (9, 0),
+        "FJROM" | "FKROM" => (10, 0),
+        "CPROM" => (13, 0),
+        "GNROM" | "MHROM" => (66, 0),
+        _ => match board.as_str() {
+            "UNL-SACHEN-8259A" => (141, 0),
+            "UNL-SACHEN-8259B" => (138, 0),
+            "UNL-SACHEN-8259C" => (139, 0),
+            "UNL-SACHEN-8259D" => (137, 0),
+            "UNL-SA-0036" => (149, 0),
+            "UNL-SA-0037" => (148, 0),
+            "UNL-SA-72007" => (145, 0),
+            "UNL-SA-72008" => (133, 0),
+            "UNL-SA-NROM" => (143, 0),
+            "UNL-TC-U01-1.5M" => (147, 0),
+            "UNL-H2288" => (123, 0),
+            "UNL-KOF97" => (263, 0),
+            "UNL-8237" => (215, 0),
+            "UNL-AX5705" => (530, 0),
+            "BMC-GS-2004" | "BMC-GS-2013" => (283, 0),
+            "UNL-EDU2000" => (329, 0),
+            "BMC-SUPER24IN1SC03" => (176, 0),
+            "UNL-SMB2J" => (304, 0),
+            "BMC-70IN1" | "BMC-70IN1B" => (236, 0),
+            "UNL-TF1201" => (298, 0),
+            "BMC-NTD-03" => (290, 0),
+            "BMC-BS-5" => (286, 0),
+            "UNL-DREAMTECH01" => (521, 0),
+            _ => return None,
+        },
+    };
+    Some(mapper)
+}
@@ -0,0 +1,110 @@
+/// Lookup table for the reflected CRC-32 polynomial `0xEDB88320`, as used
+/// by zip, png, and every ROM database.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continue a CRC-32 from a previous result, to checksum data split across
+/// multiple buffers.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// SHA-1 of data that may be split across multiple buffers, such as the
+/// PRG and CHR ROM of a cartridge.
+///
+/// <https://datatracker.ietf.org/doc/html/rfc3174>
+pub fn sha1(parts: &[&[u8]]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let length: usize = parts.iter().map(|part| part.len()).sum();
+
+    // Message, then a single 1 bit, zero padding up to 56 bytes mod 64, and
+    // the message length in bits
+    let mut padding = vec![0x80];
+    padding.resize(1 + (55 + 64 - length % 64) % 64, 0);
+    padding.extend_from_slice(&((length as u64) * 8).to_be_bytes());
+
+    let mut block = [0u8; 64];
+    let mut filled = 0;
+    for byte in parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .chain(padding.iter())
+    {
+        block[filled] = *byte;
+        filled += 1;
+        if filled == block.len() {
+            sha1_block(&mut state, &block);
+            filled = 0;
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn sha1_block(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut words = [0u32; 80];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    for i in 16..80 {
+        words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, word) in words.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+            20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+            _ => (b ^ c ^ d, 0xCA62_C1D6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+        *value = value.wrapping_add(added);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::Reset;
+use crate::{
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+    Reset,
+};
 
 pub struct Clock {
     total_ticks: u64,
@@ -18,7 +21,7 @@ impl Clock {
 
     pub fn tick(&mut self) -> bool {
         self.total_ticks += 1;
-        self.ticks_left = self.ticks_left.saturating_sub(0);
+        self.ticks_left = self.ticks_left.saturating_sub(1);
         self.ticks_left == 0
     }
 
@@ -52,3 +55,15 @@ impl Reset for Clock {
     }
 }
 
+impl SaveState for Clock {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u64(self.total_ticks);
+        state.write_u64(self.ticks_left);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.total_ticks = state.read_u64()?;
+        self.ticks_left = state.read_u64()?;
+        Ok(())
+    }
+}
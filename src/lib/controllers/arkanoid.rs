@@ -0,0 +1,106 @@
+use crate::{
+    controllers::{ControllerInput, PortDevice, PortDeviceKind},
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+};
+
+/// # Arkanoid Controller (Vaus)
+/// Paddle controller bundled with Arkanoid and Arkanoid II.
+///
+/// <https://www.nesdev.org/wiki/Arkanoid_controller>
+///
+/// ## Specification
+/// The knob is a potentiometer whose position is converted to an 8-bit value.
+/// Writing the strobe latches the value into a shift register, which is then
+/// read out serially, most significant bit first, and inverted.
+///
+/// | Bit | Description                                |
+/// |-----|--------------------------------------------|
+/// | D3  | Fire button (1 if pressed)                 |
+/// | D4  | Serial potentiometer data (inverted)       |
+///
+/// The useful range of the potentiometer is roughly `0x62 - 0xF2`. The frontend
+/// maps the pointer's horizontal position onto this range.
+pub struct Arkanoid {
+    /// Current, un-latched, position of the knob.
+    pub position: u8,
+    pub fire: bool,
+    shift_register: u8,
+    strobe: bool,
+}
+
+impl Arkanoid {
+    pub const POSITION_MIN: u8 = 0x62;
+    pub const POSITION_MAX: u8 = 0xF2;
+
+    pub fn new() -> Self {
+        Self {
+            position: Self::POSITION_MIN,
+            fire: false,
+            shift_register: 0,
+            strobe: false,
+        }
+    }
+
+    fn latch(&mut self) {
+        // Data is sent inverted
+        self.shift_register = !self.position;
+    }
+}
+
+impl Default for Arkanoid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortDevice for Arkanoid {
+    fn write_strobe(&mut self, data: u8) {
+        self.strobe = data & 0x01 != 0;
+        if self.strobe {
+            self.latch();
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        if self.strobe {
+            self.latch();
+        }
+
+        let data_bit = (self.shift_register & 0x80) >> 7;
+        let fire_bit = self.fire as u8;
+
+        // Once the position has been shifted out, the register fills with 1s,
+        // so every further read returns 1 on D4 until the next strobe.
+        self.shift_register = (self.shift_register << 1) | 0x01;
+
+        (data_bit << 4) | (fire_bit << 3)
+    }
+
+    fn update_input(&mut self, input: &ControllerInput) {
+        let span = (Self::POSITION_MAX - Self::POSITION_MIN) as f32;
+        let offset = (input.pointer_x.clamp(0.0, 1.0) * span).round() as u8;
+        self.position = Self::POSITION_MIN + offset;
+        self.fire = input.pointer_down;
+    }
+
+    fn kind(&self) -> PortDeviceKind {
+        PortDeviceKind::Arkanoid
+    }
+}
+
+impl SaveState for Arkanoid {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.position);
+        state.write_bool(self.fire);
+        state.write_u8(self.shift_register);
+        state.write_bool(self.strobe);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.position = state.read_u8()?;
+        self.fire = state.read_bool()?;
+        self.shift_register = state.read_u8()?;
+        self.strobe = state.read_bool()?;
+        Ok(())
+    }
+}
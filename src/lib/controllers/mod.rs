@@ -0,0 +1,15 @@
+mod arkanoid;
+mod port_device;
+mod power_pad;
+
+pub use arkanoid::Arkanoid;
+pub use port_device::{ControllerInput, PortDevice, PortDeviceKind};
+pub use power_pad::PowerPad;
+
+pub fn select_port_device(kind: PortDeviceKind) -> Option<Box<dyn PortDevice>> {
+    match kind {
+        PortDeviceKind::Disconnected => None,
+        PortDeviceKind::Arkanoid => Some(Box::new(Arkanoid::new())),
+        PortDeviceKind::PowerPad => Some(Box::new(PowerPad::new())),
+    }
+}
@@ -0,0 +1,72 @@
+use crate::savestate::SaveState;
+
+/// # Port Device
+/// Trait to emulate a peripheral plugged into one of the two controller ports.
+///
+/// <https://www.nesdev.org/wiki/Input_devices>
+///
+/// The CPU talks to the ports through two registers:
+/// - `$4016` write: bit 0 is the strobe (latch) line, shared by both ports.
+/// - `$4016` read: serial data from port 1.
+/// - `$4017` read: serial data from port 2.
+///
+/// Only bits `D0-D4` are driven by the device. The rest of the byte is open bus.
+pub trait PortDevice: SaveState {
+    /// Called whenever the CPU writes to `$4016`.
+    /// While the strobe (bit 0) is high, the device continually reloads its
+    /// shift registers from its current state.
+    fn write_strobe(&mut self, data: u8);
+
+    /// Called whenever the CPU reads this port's register.
+    /// Returns the bits `D0-D4` for the read and advances any serial streams.
+    fn read(&mut self) -> u8;
+
+    /// Update the device with the latest input from the frontend.
+    fn update_input(&mut self, input: &ControllerInput);
+
+    /// Which kind of device this is.
+    fn kind(&self) -> PortDeviceKind;
+}
+
+/// The different devices that can be plugged into a controller port.
+/// Discriminants are stored in save states, so must not be re-ordered.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PortDeviceKind {
+    /// Nothing plugged in. Reads return `0`.
+    #[default]
+    Disconnected,
+    /// Arkanoid "Vaus" paddle controller.
+    Arkanoid,
+    /// Power Pad / Family Trainer mat.
+    PowerPad,
+}
+
+impl PortDeviceKind {
+    /// All device kinds, in the order of their discriminants
+    pub const ALL: [PortDeviceKind; 3] = [
+        PortDeviceKind::Disconnected,
+        PortDeviceKind::Arkanoid,
+        PortDeviceKind::PowerPad,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PortDeviceKind::Disconnected => "Disconnected",
+            PortDeviceKind::Arkanoid => "Arkanoid Paddle",
+            PortDeviceKind::PowerPad => "Power Pad",
+        }
+    }
+}
+
+/// Frontend-agnostic snapshot of the user's input for a single frame.
+/// Each device picks out the parts it cares about.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ControllerInput {
+    /// Horizontal position of the pointer across the screen, from `0.0`
+    /// (left edge) to `1.0` (right edge).
+    pub pointer_x: f32,
+    /// Whether the primary pointer button is held.
+    pub pointer_down: bool,
+    /// Power Pad buttons `1-12`, stored at indices `0-11`.
+    pub power_pad: [bool; 12],
+}
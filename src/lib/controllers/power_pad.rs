@@ -0,0 +1,123 @@
+use crate::{
+    controllers::{ControllerInput, PortDevice, PortDeviceKind},
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+};
+
+/// # Power Pad / Family Trainer
+/// Floor mat with 12 pressure sensitive buttons, laid out in a 4x3 grid.
+///
+/// <https://www.nesdev.org/wiki/Power_Pad>
+///
+/// ## Specification
+/// The buttons are read out over two separate serial streams. Writing the
+/// strobe latches the state of every button, and each read shifts both
+/// streams by one bit.
+///
+/// | Bit | Description                                                   |
+/// |-----|---------------------------------------------------------------|
+/// | D3  | Buttons 2, 1, 5, 9, 6, 10, 11, 7                              |
+/// | D4  | Buttons 4, 3, 12, 8, followed by four 1s                      |
+///
+/// A pressed button is read back as a 1. Once both streams have been read
+/// out, all further reads return 1s until the next strobe.
+pub struct PowerPad {
+    /// Buttons `1-12`, stored at indices `0-11`.
+    pub buttons: [bool; 12],
+    stream_d3: u8,
+    stream_d4: u8,
+    strobe: bool,
+}
+
+impl PowerPad {
+    /// Order in which buttons are sent on `D3`
+    const D3_ORDER: [usize; 8] = [2, 1, 5, 9, 6, 10, 11, 7];
+    /// Order in which buttons are sent on `D4`. Only the first four bits
+    /// carry buttons; the rest are always 1.
+    const D4_ORDER: [usize; 4] = [4, 3, 12, 8];
+
+    pub fn new() -> Self {
+        Self {
+            buttons: [false; 12],
+            stream_d3: 0,
+            stream_d4: 0,
+            strobe: false,
+        }
+    }
+
+    fn latch(&mut self) {
+        let pressed = |button: usize| self.buttons[button - 1] as u8;
+
+        // Streams are shifted out of bit 0, so the first button goes in bit 0
+        self.stream_d3 = Self::D3_ORDER
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (bit, &button)| acc | (pressed(button) << bit));
+        self.stream_d4 = Self::D4_ORDER
+            .iter()
+            .enumerate()
+            .fold(0xF0, |acc, (bit, &button)| acc | (pressed(button) << bit));
+    }
+}
+
+impl Default for PowerPad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortDevice for PowerPad {
+    fn write_strobe(&mut self, data: u8) {
+        self.strobe = data & 0x01 != 0;
+        if self.strobe {
+            self.latch();
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        if self.strobe {
+            self.latch();
+        }
+
+        let d3 = self.stream_d3 & 0x01;
+        let d4 = self.stream_d4 & 0x01;
+
+        // Shift in 1s so the streams read back as 1 once exhausted
+        self.stream_d3 = (self.stream_d3 >> 1) | 0x80;
+        self.stream_d4 = (self.stream_d4 >> 1) | 0x80;
+
+        (d4 << 4) | (d3 << 3)
+    }
+
+    fn update_input(&mut self, input: &ControllerInput) {
+        self.buttons = input.power_pad;
+    }
+
+    fn kind(&self) -> PortDeviceKind {
+        PortDeviceKind::PowerPad
+    }
+}
+
+impl SaveState for PowerPad {
+    fn save_state(&self, state: &mut StateWriter) {
+        let buttons = self
+            .buttons
+            .iter()
+            .enumerate()
+            .fold(0u16, |acc, (i, &pressed)| acc | ((pressed as u16) << i));
+        state.write_u16(buttons);
+        state.write_u8(self.stream_d3);
+        state.write_u8(self.stream_d4);
+        state.write_bool(self.strobe);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        let buttons = state.read_u16()?;
+        for (i, pressed) in self.buttons.iter_mut().enumerate() {
+            *pressed = buttons & (1 << i) != 0;
+        }
+        self.stream_d3 = state.read_u8()?;
+        self.stream_d4 = state.read_u8()?;
+        self.strobe = state.read_bool()?;
+        Ok(())
+    }
+}
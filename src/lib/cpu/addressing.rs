@@ -1,11 +1,8 @@
-use crate::Cpu;
-
 /// Functionality related to addressing modes.
 /// Exports an `AddressingMode` enum, where the variants are the different addressing
-/// modes. AddressingMode implements a `fetch` method to fetch the value of the
-/// operand from the memory, in the given CPU.
+/// modes. The bus accesses each mode makes to find its operand are in `cycles.rs`.
 /// Goated Resource: <https://www.svaught.com/posts/addr-modes-6502>
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum AddressingMode {
     /// Implied: No data in the instruction.
@@ -59,149 +56,37 @@ pub enum AddressingMode {
 }
 
 impl AddressingMode {
-    pub fn fetch(&self) -> fn(&mut Cpu) -> u8 {
+    /// All addressing modes, in the order of their discriminants
+    pub const ALL: [AddressingMode; 12] = [
+        AddressingMode::IMP,
+        AddressingMode::IMM,
+        AddressingMode::ZP0,
+        AddressingMode::ZPX,
+        AddressingMode::ZPY,
+        AddressingMode::REL,
+        AddressingMode::ABS,
+        AddressingMode::ABX,
+        AddressingMode::ABY,
+        AddressingMode::IND,
+        AddressingMode::IZX,
+        AddressingMode::IZY,
+    ];
+
+    /// Number of bytes following the opcode
+    pub fn operand_len(&self) -> u16 {
         match *self {
-            AddressingMode::IMP => fetching::fetch_imp,
-            AddressingMode::IMM => fetching::fetch_imm,
-            AddressingMode::ZP0 => fetching::fetch_zp0,
-            AddressingMode::ZPX => fetching::fetch_zpx,
-            AddressingMode::ZPY => fetching::fetch_zpy,
-            AddressingMode::REL => fetching::fetch_rel,
-            AddressingMode::ABS => fetching::fetch_abs,
-            AddressingMode::ABX => fetching::fetch_abx,
-            AddressingMode::ABY => fetching::fetch_aby,
-            AddressingMode::IND => fetching::fetch_ind,
-            AddressingMode::IZX => fetching::fetch_izx,
-            AddressingMode::IZY => fetching::fetch_izy,
+            AddressingMode::IMP => 0,
+            AddressingMode::IMM
+            | AddressingMode::ZP0
+            | AddressingMode::ZPX
+            | AddressingMode::ZPY
+            | AddressingMode::REL
+            | AddressingMode::IZX
+            | AddressingMode::IZY => 1,
+            AddressingMode::ABS
+            | AddressingMode::ABX
+            | AddressingMode::ABY
+            | AddressingMode::IND => 2,
         }
     }
 }
-
-pub(self) mod fetching {
-    use crate::Cpu;
-
-    pub(super) fn fetch_imp(cpu: &mut Cpu) -> u8 {
-        cpu.fetched_data = cpu.a_register;
-        0
-    }
-
-    pub(super) fn fetch_imm(cpu: &mut Cpu) -> u8 {
-        cpu.absolute_addr += 1;
-        0
-    }
-
-    pub(super) fn fetch_zp0(cpu: &mut Cpu) -> u8 {
-        let offset = cpu.read(cpu.program_counter);
-
-        cpu.absolute_addr = offset as u16;
-
-        0
-    }
-
-    pub(super) fn fetch_zpx(cpu: &mut Cpu) -> u8 {
-        let offset = cpu.read(cpu.program_counter) + cpu.x_register;
-        cpu.absolute_addr = (offset as u16) & 0x00FF;
-        cpu.program_counter += 1;
-        0
-    }
-
-    pub(super) fn fetch_zpy(cpu: &mut Cpu) -> u8 {
-        let offset = cpu.read(cpu.program_counter) + cpu.y_register;
-        cpu.absolute_addr = (offset as u16) & 0x00FF;
-        cpu.program_counter += 1;
-        0
-    }
-
-    pub(super) fn fetch_rel(cpu: &mut Cpu) -> u8 {
-        // TODO: check impl when signed
-        cpu.relative_addr = cpu.read(cpu.program_counter) as i8;
-        cpu.program_counter += 1;
-
-        0
-    }
-
-    pub(super) fn fetch_abs(cpu: &mut Cpu) -> u8 {
-        let lo = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-        let hi = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-
-        cpu.absolute_addr = u16::from_le_bytes([lo, hi]);
-
-        0
-    }
-
-    pub(super) fn fetch_abx(cpu: &mut Cpu) -> u8 {
-        let lo = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-        let hi = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-
-        cpu.absolute_addr = u16::from_le_bytes([lo, hi]) + cpu.x_register as u16;
-
-        // If page overflow, then add a cycle
-        match (cpu.absolute_addr & 0xFF00) != ((hi as u16) << 8) {
-            true => 1,
-            false => 0,
-        }
-    }
-
-    pub(super) fn fetch_aby(cpu: &mut Cpu) -> u8 {
-        let lo = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-        let hi = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-
-        cpu.absolute_addr = u16::from_le_bytes([lo, hi]) + cpu.y_register as u16;
-
-        // If page overflow, then add a cycle
-        // (cpu.absolute_addr & 0xFF00) != (hi << 8)).into()
-        ((cpu.absolute_addr & 0xFF00) != ((hi as u16) << 8)).into()
-    }
-
-    pub(super) fn fetch_ind(cpu: &mut Cpu) -> u8 {
-        let lo = cpu.read(cpu.program_counter);
-        cpu.program_counter += 1;
-        let hi = cpu.read(cpu.program_counter);
-        let ptr_addr: u16 = u16::from_le_bytes([lo, hi]);
-
-        // Simulate page boundary hardware bug
-        cpu.absolute_addr = match lo == 0x00FF {
-            // Bug: Lo byte of pointer is 0x00FF.
-            // Getting Hi bytes of pointer is at ptr_addr + 1, which is crossing a
-            // page boundary. Instead, just wrap around to the start of the same
-            // page
-            true => u16::from_le_bytes([cpu.read(ptr_addr), cpu.read(ptr_addr & 0xFF00)]),
-            // Normal behaviour
-            false => u16::from_le_bytes([cpu.read(ptr_addr), cpu.read(ptr_addr + 1)]),
-        };
-
-        0
-    }
-
-    pub(super) fn fetch_izx(cpu: &mut Cpu) -> u8 {
-        let offset: u16 = (cpu.read(cpu.program_counter) + cpu.x_register) as u16;
-        cpu.program_counter += 1;
-
-        // & 0x00FF to wrap around instead of moving to the next page
-        let lo = cpu.read(offset & 0x00FF);
-        let hi = cpu.read((offset + 1) & 0x00FF);
-
-        cpu.absolute_addr = u16::from_le_bytes([lo, hi]);
-
-        0
-    }
-
-    pub(super) fn fetch_izy(cpu: &mut Cpu) -> u8 {
-        let offset: u16 = cpu.read(cpu.program_counter) as u16;
-        cpu.program_counter += 1;
-
-        let lo = cpu.read(offset & 0x00FF);
-        let hi = cpu.read((offset + 1) & 0x00FF);
-
-        cpu.absolute_addr = u16::from_le_bytes([lo, hi]) + cpu.y_register as u16;
-
-        // If page overflow, then add a cycle
-        ((cpu.absolute_addr & 0xFF00) != ((hi as u16) << 8)).into()
-    }
-}
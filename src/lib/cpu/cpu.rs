@@ -2,12 +2,14 @@ use crate::{
     clock::Clock,
     cpu::{
         flags::{clear_flag, set_flag, CpuFlag},
-        AddressingMode,
+        AddressingMode, Memory,
     },
-    opcodes::OpCode,
+    disassembler::{disassemble, Instruction},
+    opcodes::{OpCode, OpCodeType},
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
     Bus, RcCell, Reset,
 };
-use std::{cell::RefCell, fmt::format, rc::Rc};
+use std::{cell::RefCell, rc::Rc};
 
 /// Emulator for the `6502` CPU.
 ///
@@ -29,10 +31,10 @@ use std::{cell::RefCell, fmt::format, rc::Rc};
 ///
 ///
 ///  -Ram - 64k
-
+///
 /// 56 instructions
 /// Care about the size (1-3 bytes) and how many cycles it takes to execute (duration)
-
+///
 /// Registers:
 /// A: Accumulator
 /// X: Register
@@ -40,8 +42,11 @@ use std::{cell::RefCell, fmt::format, rc::Rc};
 /// SP: Stack pointer
 /// PC: Program Counter
 /// Status: Status register
-pub struct Cpu {
-    pub bus: RcCell<Bus>,
+///
+/// The CPU runs on any [`Memory`]. In the NES, that is the [`Bus`] shared
+/// with the rest of the machine.
+pub struct Cpu<M: Memory = RcCell<Bus>> {
+    pub bus: M,
     pub clock: Clock,
     pub a_register: u8,       // Accumulator
     pub x_register: u8,       // X Register
@@ -55,22 +60,40 @@ pub struct Cpu {
     pub absolute_addr: u16,   // Absolute address being read off
     pub relative_addr: i8,    // Address relative to abs address
     pub addressing_mode: AddressingMode, // Addressing mode
-    pub additional_cycle_addrmode: u8, // Additional cycles for addressing mode
     pub additional_cycle_operation: u8, // Additional cycles for operation
+    pub opcode: u8,           // Opcode of the current instruction
+    /// Cycles of the current instruction run so far. `0` between instructions
+    pub cycle: u8,
+    pub pointer: u16,       // Indirect address being read off
+    pub page_crossed: bool, // Indexing carried into the high byte
+    /// Set by a JAM instruction. The CPU stops until it is reset.
+    pub jammed: bool,
+    /// One bit per [`IrqSource`] holding the IRQ line
+    pub irq_lines: u8,
+    pub nmi_line: bool,
+    /// The NMI line has been raised, and the NMI not started yet
+    pub nmi_pending: bool,
+    /// Result of the last interrupt check. Starts an interrupt instead of
+    /// the next instruction.
+    pub interrupt_polled: bool,
+    /// The BRK sequence running is an IRQ or NMI, rather than a BRK
+    pub servicing_interrupt: bool,
+    /// Whether the decimal flag makes ADC and SBC work in BCD, as on other
+    /// 6502s. The NES's CPU has it disconnected, so this is off by default.
+    pub decimal_mode: bool,
 }
 
-impl Cpu {
+impl<M: Memory> Cpu<M> {
     pub const STACK_BASE: u16 = 0x0100;
     pub const RESET_VECTOR: u16 = 0xFFFC;
     pub const STACK_POINTER_RESET: u8 = 0xFD;
     pub const IRQ_VECTOR: u16 = 0xFFFE;
     pub const NMI_VECTOR: u16 = 0xFFFA;
 
-    pub fn new() -> RcCell<Self> {
-        let tmp_bus = Rc::new(RefCell::new(Bus::default()));
-
-        let new_cpu = Rc::new(RefCell::new(Self {
-            bus: tmp_bus,
+    /// A CPU on `bus`. Call [`Reset::reset`] to start it.
+    pub fn with_memory(bus: M) -> Self {
+        Self {
+            bus,
             clock: Clock::default(),
             a_register: 0,
             x_register: 0,
@@ -82,138 +105,116 @@ impl Cpu {
             absolute_addr: 0,
             relative_addr: 0,
             addressing_mode: AddressingMode::IMP,
-            additional_cycle_addrmode: 0,
             additional_cycle_operation: 0,
-        }));
+            opcode: 0,
+            cycle: 0,
+            pointer: 0,
+            page_crossed: false,
+            jammed: false,
+            irq_lines: 0,
+            nmi_line: false,
+            nmi_pending: false,
+            interrupt_polled: false,
+            servicing_interrupt: false,
+            decimal_mode: false,
+        }
+    }
 
-        new_cpu
-            .borrow_mut()
-            .bus
-            .borrow_mut()
-            .connect_cpu(Rc::downgrade(&new_cpu));
+    /// Emulate a single clock cycle, making the one bus access the CPU makes
+    /// on that cycle. An instruction takes several calls to this.
+    /// See `cycles.rs` for what happens on each cycle.
+    pub fn tick(&mut self) {
+        let busy = !self.clock.is_ready();
+        self.clock.tick();
+        if busy || self.jammed {
+            return;
+        }
 
-        new_cpu
+        self.cycle += 1;
+        match self.cycle {
+            1 => self.fetch_opcode(),
+            _ => self.run_cycle(),
+        }
+        if self.cycle != 0 {
+            self.poll_interrupts();
+        }
     }
 
-    /// Emulate a single clock cycle. This does not translate to one
-    /// instruction.
-    /// The CPU will execute an instruction over multiple clock cycles.
-    /// However, the emulation occurs on a per-instruction basis, rather
-    /// than a per-clock cycle basis.
-    /// As such, if an instructions takes `n` clock cycles, `1` call of this
-    /// will execute the instruction and there will be `n - 1` calls to this
-    /// that do nothing (except increment the clock).
-    pub fn tick(&mut self) {
-        if !self.clock.is_ready() {
-            self.clock.tick();
-            // return;
+    /// Tick until the current instruction is finished, or the next one if
+    /// between instructions. Returns the number of cycles taken.
+    pub fn step(&mut self) -> u64 {
+        let start = self.clock.total_ticks();
+        loop {
+            self.tick();
+            if self.is_between_instructions() || self.jammed {
+                return self.clock.total_ticks() - start;
+            }
         }
-        self.execute_clock_cycle();
     }
 
-    pub fn execute_clock_cycle(&mut self) {
-        // Not ready yet.
+    /// Whether the next cycle fetches an opcode
+    pub fn is_between_instructions(&self) -> bool {
+        self.cycle == 0 && self.clock.is_ready()
+    }
 
-        // Fetch next instruction
-        let opcode: OpCode = self.read(self.program_counter).into();
-        // println!("Instruction:\t {opcode:?}");
-        self.program_counter += 1;
+    /// First cycle of every instruction. If an interrupt is due, the opcode
+    /// read is thrown away and replaced with BRK.
+    fn fetch_opcode(&mut self) {
+        self.opcode = self.read(self.program_counter);
+        match self.interrupt_polled {
+            true => {
+                self.interrupt_polled = false;
+                self.servicing_interrupt = true;
+                self.opcode = 0x00;
+            }
+            false => self.program_counter = self.program_counter.wrapping_add(1),
+        }
+        self.addressing_mode = OpCode::decode(self.opcode).addressing_mode;
 
         // Always unused
         self.set_flag(&CpuFlag::Unused);
-
-        // Initial (minimum) amount of cycles - May be extra later
-        self.clock.set_cycles(opcode.cycles as u64);
-
-        // Addressing Mode Lookup for `absolute_addr` or `relative_addr`
-        self.addressing_mode = opcode.addressing_mode;
-        // FIXME: Not sure if this is going to break things by doing a pre-emptive
-        // fetch of the data, since the operation_fn also fetches.
-        // PC may move out of sync.
-        // Might want to do this in the actual addressing mode functions.
-        self.additional_cycle_addrmode = self.addressing_mode.fetch()(self);
-
-        // Do the operation
-        self.additional_cycle_operation = opcode.code_type.executable()(self);
-
-        // Calculate total cycles;
-        // Refactor: Don't use members but actually return / pass the data around
-        let additional_cycles = self.additional_cycle_operation & self.additional_cycle_addrmode;
-        self.clock.add_cycles(additional_cycles as u64);
-
-        // println!("reached end");
     }
 
-    /// Fetch based on the current addressing mode. Stored in `self.fetched_data`
-    /// Also returns the fetched_data
+    /// The operand of the current instruction, read on an earlier cycle, or
+    /// the accumulator for implied instructions.
+    /// Stored in `self.fetched_data`. Also returns the fetched_data
     pub fn fetch(&mut self) -> u8 {
-        // todo: special case for imp
-        self.addressing_mode.fetch()(self);
+        if self.addressing_mode == AddressingMode::IMP {
+            self.fetched_data = self.a_register;
+        }
         self.fetched_data
     }
 
     #[inline(always)]
-    pub fn get_bus(&self) -> RcCell<Bus> {
-        self.bus.clone()
+    pub fn read(&mut self, address: u16) -> u8 {
+        self.bus.read(address)
     }
 
-    #[inline(always)]
-    pub fn read(&self, address: u16) -> u8 {
-        self.bus.borrow().read_cpu(address)
+    /// Read without side effects, see [`Memory::peek`]
+    pub fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
+    /// Decode the instruction at `address`, without side effects
+    pub fn disassemble(&self, address: u16) -> Instruction {
+        disassemble(address, |address| self.peek(address))
     }
 
     #[inline(always)]
     pub fn write(&mut self, address: u16, data: u8) {
-        self.bus.borrow_mut().write_cpu(address, data)
+        self.bus.write(address, data)
     }
 
     #[inline(always)]
     pub fn push_stack(&mut self, data: u8) {
-        self.write(Cpu::STACK_BASE + self.stack_pointer as u16, data);
+        self.write(Self::STACK_BASE + self.stack_pointer as u16, data);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
     }
 
     #[inline(always)]
     pub fn pop_stack(&mut self) -> u8 {
-        self.stack_pointer += 1;
-        self.read(Cpu::STACK_BASE + self.stack_pointer as u16)
-    }
-
-    /// Trigger an interrupt if the disable interrupt flag is not set.
-    /// They happen at any time but, we want to only execute these between
-    /// instructions
-    /// TODO: In future, an event queue can be very useful to handle this
-    /// trickiness
-    pub fn interrupt_request(&mut self) {
-        if self.get_flag(&CpuFlag::Interrupt) {
-            self.interrupt(Self::IRQ_VECTOR, 7);
-        }
-    }
-
-    /// Non-Maskable Interrupt that cannot be disabled. Runs irrespective
-    /// of the status register.
-    pub fn non_maskable_interrupt_request(&mut self) {
-        self.interrupt(Self::NMI_VECTOR, 8)
-    }
-
-    fn interrupt(&mut self, pc_location: u16, cycles: u64) {
-        // Push PC to Stack
-        self.push_stack((self.program_counter & 0x00FF) as u8); // Lo
-        self.push_stack((self.program_counter & 0xFF00) as u8); // Hi
-
-        // Push Status Register to Stack
-        self.set_flag(&CpuFlag::Break);
-        self.set_flag(&CpuFlag::Interrupt);
-        self.set_flag(&CpuFlag::Unused);
-        self.push_stack(self.status_register);
-
-        // Go to Interrupt Vector
-        self.absolute_addr = pc_location;
-        let lo = self.read(self.absolute_addr);
-        let hi = self.read(self.absolute_addr + 1);
-        self.program_counter = u16::from_le_bytes([lo, hi]);
-
-        self.clock.set_cycles(cycles);
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        self.read(Self::STACK_BASE + self.stack_pointer as u16)
     }
 
     #[inline]
@@ -249,46 +250,173 @@ impl Cpu {
             program_counter: self.program_counter,
         }
     }
+}
 
-    /// Trace current cpu state in nestest.log format
+/// # NES
+/// The CPU connected to the rest of the NES through its bus
+impl Cpu {
+    pub fn new() -> RcCell<Self> {
+        let new_cpu = Rc::new(RefCell::new(Self::with_memory(Rc::new(RefCell::new(
+            Bus::default(),
+        )))));
+
+        new_cpu
+            .borrow_mut()
+            .bus
+            .borrow_mut()
+            .connect_cpu(Rc::downgrade(&new_cpu));
+
+        new_cpu
+    }
+
+    #[inline(always)]
+    pub fn get_bus(&self) -> RcCell<Bus> {
+        self.bus.clone()
+    }
+
+    /// Trace current cpu state in nestest.log format. Call between
+    /// instructions.
     /// Example output line:
-    /// ```
+    /// ```text
     /// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
     /// ```
     ///
     /// Columns:
     /// - `program_counter`: `C000`
     /// - `CPU opcode`: `4C F5 C5` - Variable len - Recall that opcodes are 1-3 bytes. In the case
-    /// of shorter opcodes, we keep the columns spacing consistent and left-align the text
-    /// - `CPU_opcode in ASM` -
-    /// - $80 + X = real address = mem value at real address ???
-    ///     @ 80 = 0100 = 00
-    ///     - first num is mem referenc ethat we get if we apply offsert to the request
-    ///       address based on addressing mode
-    ///     - then a u16  target fecthed from [0x80..0x81]
-    ///     - the content of that address cell
+    ///   of shorter opcodes, we keep the columns spacing consistent and left-align the text
+    /// - `CPU_opcode in ASM`: `JMP $C5F5` - Unofficial opcodes are marked with a `*`. After the
+    ///   operand come the addresses it resolves to and the value in memory there, e.g.
+    ///   `LDA ($80,X) @ 82 = 0300 = 5A`
+    ///     - `@ 82` is the zero page pointer, after adding X
+    ///     - `0300` is the u16 target fetched from [0x82..0x83]
+    ///     - `5A` is the content of that address cell
     /// - rest of the cpu registers: A, X, Y, P, SP
-    /// - CPU and PPU clock cycles
-
-    pub fn nestest_trace(&mut self) -> String {
+    /// - PPU scanline and dot, and the CPU cycle count
+    ///
+    /// Memory is peeked, so tracing doesn't disturb registers with side
+    /// effects on read.
+    pub fn nestest_trace(&self) -> String {
         // Allocing = cringe?
         let mut trace = String::with_capacity(92);
         let pad_till_col = |s: &mut String, col: usize| {
             let amount_to_pad = col - s.len();
-            s.extend(std::iter::repeat(' ').take(amount_to_pad));
+            s.extend(std::iter::repeat_n(' ', amount_to_pad));
         };
 
-        trace.extend(format!("{:04X}", self.program_counter).chars());
-        pad_till_col(&mut trace, 6);
         // Program Counter
-        let opcode: OpCode = self.read(self.program_counter).into();
+        let instruction = self.disassemble(self.program_counter);
+        trace.push_str(&format!("{:04X}", instruction.address));
+        pad_till_col(&mut trace, 6);
+
+        let bytes: Vec<String> = instruction
+            .bytes()
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect();
+        trace.push_str(&bytes.join(" "));
+        pad_till_col(&mut trace, 15);
+
+        trace.push(match instruction.is_official() {
+            true => ' ',
+            false => '*',
+        });
+        let mnemonic = match instruction.opcode.code_type {
+            // nestest's name for it
+            OpCodeType::ISC => "ISB".to_string(),
+            _ => instruction.mnemonic(),
+        };
+        trace.push_str(&mnemonic);
+        pad_till_col(&mut trace, 20);
+        trace.push_str(&self.trace_operand(&instruction));
+        pad_till_col(&mut trace, 48);
+
+        let (scanline, dot) = match self.bus.borrow().get_ppu() {
+            Some(ppu) => (ppu.borrow().scanline(), ppu.borrow().cycle()),
+            None => (0, 0),
+        };
+        trace.push_str(&format!(
+            "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:3},{:3} CYC:{}",
+            self.a_register,
+            self.x_register,
+            self.y_register,
+            self.status_register,
+            self.stack_pointer,
+            scanline,
+            dot,
+            self.clock.total_ticks(),
+        ));
+
+        trace
+    }
 
-        // todo!("put in the raw opcode");
-        pad_till_col(&mut trace, 16);
-        // let x = opcode.decompile();
+    /// Operand for [`Cpu::nestest_trace`]: the disassembled operand, followed
+    /// by the addresses it resolves to and the value there
+    fn trace_operand(&self, instruction: &Instruction) -> String {
+        let operand = instruction.operand();
+        let lo = instruction.bytes().get(1).copied().unwrap_or(0);
+        let absolute = u16::from_le_bytes([lo, instruction.bytes().get(2).copied().unwrap_or(0)]);
+        // Pointers in the zero page wrap around it
+        let read_pointer = |pointer: u8| {
+            u16::from_le_bytes([
+                self.peek(pointer as u16),
+                self.peek(pointer.wrapping_add(1) as u16),
+            ])
+        };
+        // The reference log doesn't read the APU and I/O registers, and
+        // shows them as FF
+        let value_at = |address: u16| match address {
+            0x4000..=0x401F => 0xFF,
+            _ => self.peek(address),
+        };
 
-        // C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
-        todo!("write the trace");
+        match instruction.opcode.addressing_mode {
+            AddressingMode::IMP | AddressingMode::IMM | AddressingMode::REL => operand,
+            AddressingMode::ZP0 => format!("{operand} = {:02X}", value_at(lo as u16)),
+            AddressingMode::ZPX | AddressingMode::ZPY => {
+                let index = match instruction.opcode.addressing_mode {
+                    AddressingMode::ZPX => self.x_register,
+                    _ => self.y_register,
+                };
+                let address = lo.wrapping_add(index);
+                let value = value_at(address as u16);
+                format!("{operand} @ {address:02X} = {value:02X}")
+            }
+            AddressingMode::ABS => match instruction.opcode.code_type {
+                OpCodeType::JMP | OpCodeType::JSR => operand,
+                _ => format!("{operand} = {:02X}", value_at(absolute)),
+            },
+            AddressingMode::ABX | AddressingMode::ABY => {
+                let index = match instruction.opcode.addressing_mode {
+                    AddressingMode::ABX => self.x_register,
+                    _ => self.y_register,
+                };
+                let address = absolute.wrapping_add(index as u16);
+                let value = value_at(address);
+                format!("{operand} @ {address:04X} = {value:02X}")
+            }
+            AddressingMode::IND => {
+                // Doesn't carry into the high byte, like the hardware
+                let [ptr_lo, ptr_hi] = absolute.to_le_bytes();
+                let target = u16::from_le_bytes([
+                    self.peek(absolute),
+                    self.peek(u16::from_le_bytes([ptr_lo.wrapping_add(1), ptr_hi])),
+                ]);
+                format!("{operand} = {target:04X}")
+            }
+            AddressingMode::IZX => {
+                let pointer = lo.wrapping_add(self.x_register);
+                let address = read_pointer(pointer);
+                let value = value_at(address);
+                format!("{operand} @ {pointer:02X} = {address:04X} = {value:02X}")
+            }
+            AddressingMode::IZY => {
+                let base = read_pointer(lo);
+                let address = base.wrapping_add(self.y_register as u16);
+                let value = value_at(address);
+                format!("{operand} = {base:04X} @ {address:04X} = {value:02X}")
+            }
+        }
     }
 }
 
@@ -302,11 +430,11 @@ pub struct Registers {
     pub program_counter: u16,
 }
 
-impl Reset for Cpu {
+impl<M: Memory> Reset for Cpu<M> {
     // Maybe these should be in their own file
     fn reset(&mut self) {
         // Go to reset vector
-        self.absolute_addr = Cpu::RESET_VECTOR;
+        self.absolute_addr = Self::RESET_VECTOR;
         let lo = self.read(self.absolute_addr);
         let hi = self.read(self.absolute_addr + 1);
         self.program_counter = u16::from_le_bytes([lo, hi]);
@@ -315,19 +443,80 @@ impl Reset for Cpu {
         self.a_register = 0;
         self.x_register = 0;
         self.y_register = 0;
-        self.stack_pointer = Cpu::STACK_POINTER_RESET;
-        self.status_register = CpuFlag::Unused as u8;
+        self.stack_pointer = Self::STACK_POINTER_RESET;
+        self.status_register = CpuFlag::Unused as u8 | CpuFlag::Interrupt as u8;
 
-        self.additional_cycle_addrmode = 0;
         self.additional_cycle_operation = 0;
+        self.cycle = 0;
+        self.pointer = 0;
+        self.page_crossed = false;
         self.addressing_mode = AddressingMode::IMP;
         self.absolute_addr = 0;
         self.fetched_data = 0;
+        self.jammed = false;
+        self.nmi_pending = false;
+        self.interrupt_polled = false;
+        self.servicing_interrupt = false;
 
-        // Take 8 cycles to reset but, don't consume a cylce in this func
-        // as this gets called between ticks.
-        // Maybe use clock.reset differently?
+        // Takes 7 cycles before the first instruction is fetched but, don't
+        // consume a cycle in this func as this gets called between ticks.
         self.clock.reset();
-        self.clock.set_cycles(8);
+        self.clock.set_cycles(7);
+    }
+}
+
+impl<M: Memory> SaveState for Cpu<M> {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.a_register);
+        state.write_u8(self.x_register);
+        state.write_u8(self.y_register);
+        state.write_u8(self.stack_pointer);
+        state.write_u16(self.program_counter);
+        state.write_u8(self.status_register);
+        state.write_u8(self.fetched_data);
+        state.write_u16(self.absolute_addr);
+        state.write_u8(self.relative_addr as u8);
+        state.write_u8(self.addressing_mode as u8);
+        state.write_u8(self.additional_cycle_operation);
+        state.write_u8(self.opcode);
+        state.write_u8(self.cycle);
+        state.write_u16(self.pointer);
+        state.write_bool(self.page_crossed);
+        state.write_bool(self.jammed);
+        state.write_bool(self.decimal_mode);
+        state.write_u8(self.irq_lines);
+        state.write_bool(self.nmi_line);
+        state.write_bool(self.nmi_pending);
+        state.write_bool(self.interrupt_polled);
+        state.write_bool(self.servicing_interrupt);
+        self.clock.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.a_register = state.read_u8()?;
+        self.x_register = state.read_u8()?;
+        self.y_register = state.read_u8()?;
+        self.stack_pointer = state.read_u8()?;
+        self.program_counter = state.read_u16()?;
+        self.status_register = state.read_u8()?;
+        self.fetched_data = state.read_u8()?;
+        self.absolute_addr = state.read_u16()?;
+        self.relative_addr = state.read_u8()? as i8;
+        self.addressing_mode = *AddressingMode::ALL
+            .get(state.read_u8()? as usize)
+            .ok_or(SaveStateError::InvalidValue("addressing mode"))?;
+        self.additional_cycle_operation = state.read_u8()?;
+        self.opcode = state.read_u8()?;
+        self.cycle = state.read_u8()?;
+        self.pointer = state.read_u16()?;
+        self.page_crossed = state.read_bool()?;
+        self.jammed = state.read_bool()?;
+        self.decimal_mode = state.read_bool()?;
+        self.irq_lines = state.read_u8()?;
+        self.nmi_line = state.read_bool()?;
+        self.nmi_pending = state.read_bool()?;
+        self.interrupt_polled = state.read_bool()?;
+        self.servicing_interrupt = state.read_bool()?;
+        self.clock.load_state(state)
     }
 }
@@ -0,0 +1,377 @@
+use crate::{
+    cpu::{
+        AddressingMode::{self, *},
+        CpuFlag, Memory,
+    },
+    opcodes::{MemoryAccess, OpCode, OpCodeType::*},
+    Cpu,
+};
+
+/// # Cycles
+/// What the CPU does on each cycle of an instruction. Every cycle makes
+/// exactly one bus access, including the dummy reads and writes the hardware
+/// makes while it is busy working out an address or an operand. These are
+/// visible to anything with side effects on read or write, such as the PPU
+/// registers or mapper registers.
+///
+/// Cycle 1 fetches the opcode. The instruction's operation runs on its last
+/// cycle, with the operand already in `fetched_data` and the effective
+/// address in `absolute_addr`.
+///
+/// Reference: <https://www.nesdev.org/6502_cpu.txt>
+impl<M: Memory> Cpu<M> {
+    /// Run cycle `self.cycle` of the current instruction, and go back to
+    /// fetching opcodes if it was the last one
+    pub(super) fn run_cycle(&mut self) {
+        let opcode = OpCode::decode(self.opcode);
+        let finished = match (opcode.code_type, opcode.addressing_mode) {
+            (BRK, _) => self.brk_cycle(),
+            (JSR, _) => self.jsr_cycle(),
+            (RTI, _) => self.rti_cycle(),
+            (RTS, _) => self.rts_cycle(),
+            (PHA | PHP, _) => self.push_cycle(),
+            (PLA | PLP, _) => self.pull_cycle(),
+            (JMP, ABS) => self.jump_cycle(),
+            (JMP, IND) => self.jump_indirect_cycle(),
+            (_, IMP) => {
+                self.read(self.program_counter);
+                self.operate();
+                true
+            }
+            (_, IMM) => {
+                self.absolute_addr = self.program_counter;
+                self.fetched_data = self.read_program_byte();
+                self.operate();
+                true
+            }
+            (_, REL) => self.branch_cycle(),
+            (_, _) => self.memory_cycle(opcode),
+        };
+
+        if finished {
+            self.cycle = 0;
+        }
+    }
+
+    /// Run the instruction's operation
+    fn operate(&mut self) -> u8 {
+        Self::OPERATIONS[self.opcode as usize](self)
+    }
+
+    /// Read the byte at the program counter, and move past it
+    fn read_program_byte(&mut self) -> u8 {
+        let data = self.read(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_add(1);
+        data
+    }
+
+    /// Read the top of the stack without popping it
+    fn peek_stack(&mut self) -> u8 {
+        self.read(Self::STACK_BASE + self.stack_pointer as u16)
+    }
+
+    /// Set `absolute_addr` to `base + index`, noting whether it crossed a page
+    fn index_address(&mut self, base: u16, index: u8) {
+        self.absolute_addr = base.wrapping_add(index as u16);
+        self.page_crossed = (base ^ self.absolute_addr) & 0xFF00 != 0;
+    }
+
+    /// Instructions that operate on memory, in every addressing mode with an
+    /// effective address. Returns `true` on the last cycle.
+    fn memory_cycle(&mut self, opcode: &OpCode) -> bool {
+        let mode = opcode.addressing_mode;
+        let access = opcode.access;
+        // First cycle after the address is known
+        let operand_cycle = match mode {
+            ZP0 => 3,
+            ZPX | ZPY | ABS => 4,
+            ABX | ABY => 5,
+            IZX | IZY => 6,
+            _ => unreachable!("{mode:?} has no effective address"),
+        };
+
+        // Indexing carries into the high byte a cycle late, so the address is
+        // read before it is fixed. Reads that don't cross a page are done.
+        if self.cycle + 1 == operand_cycle && matches!(mode, ABX | ABY | IZY) {
+            let unfixed = match self.page_crossed {
+                true => self.absolute_addr.wrapping_sub(0x0100),
+                false => self.absolute_addr,
+            };
+            self.fetched_data = self.read(unfixed);
+            if !self.page_crossed && access == MemoryAccess::Read {
+                self.operate();
+                return true;
+            }
+            return false;
+        }
+
+        if self.cycle < operand_cycle {
+            self.address_cycle(mode);
+            return false;
+        }
+
+        match (access, self.cycle - operand_cycle) {
+            (MemoryAccess::Read, _) => {
+                self.fetched_data = self.read(self.absolute_addr);
+                self.operate();
+                true
+            }
+            (MemoryAccess::Write, _) => {
+                self.operate();
+                true
+            }
+            (MemoryAccess::ReadModifyWrite, 0) => {
+                self.fetched_data = self.read(self.absolute_addr);
+                false
+            }
+            (MemoryAccess::ReadModifyWrite, 1) => {
+                // Written back unmodified while the ALU works on it
+                self.write(self.absolute_addr, self.fetched_data);
+                false
+            }
+            (MemoryAccess::ReadModifyWrite, _) => {
+                self.operate();
+                true
+            }
+        }
+    }
+
+    /// Work out the effective address, one cycle at a time
+    fn address_cycle(&mut self, mode: AddressingMode) {
+        match (mode, self.cycle) {
+            (ZP0 | ZPX | ZPY | ABS | ABX | ABY, 2) => {
+                self.absolute_addr = self.read_program_byte() as u16;
+            }
+            (ZPX | ZPY, 3) => {
+                // Reads the unindexed address while adding
+                self.read(self.absolute_addr);
+                let index = match mode {
+                    ZPX => self.x_register,
+                    _ => self.y_register,
+                };
+                self.absolute_addr = (self.absolute_addr as u8).wrapping_add(index) as u16;
+            }
+            (ABS, 3) => {
+                self.absolute_addr |= (self.read_program_byte() as u16) << 8;
+            }
+            (ABX | ABY, 3) => {
+                let base = self.absolute_addr | (self.read_program_byte() as u16) << 8;
+                let index = match mode {
+                    ABX => self.x_register,
+                    _ => self.y_register,
+                };
+                self.index_address(base, index);
+            }
+            (IZX | IZY, 2) => {
+                self.pointer = self.read_program_byte() as u16;
+            }
+            (IZX, 3) => {
+                // Reads the unindexed pointer while adding
+                self.read(self.pointer);
+                self.pointer = (self.pointer as u8).wrapping_add(self.x_register) as u16;
+            }
+            (IZX, 4) => {
+                self.absolute_addr = self.read(self.pointer) as u16;
+            }
+            (IZX, 5) => {
+                // The pointer wraps around the zero page
+                let hi = self.read((self.pointer as u8).wrapping_add(1) as u16);
+                self.absolute_addr |= (hi as u16) << 8;
+            }
+            (IZY, 3) => {
+                self.absolute_addr = self.read(self.pointer) as u16;
+            }
+            (IZY, 4) => {
+                let hi = self.read((self.pointer as u8).wrapping_add(1) as u16);
+                let base = self.absolute_addr | (hi as u16) << 8;
+                self.index_address(base, self.y_register);
+            }
+            _ => unreachable!("{mode:?} has no cycle {}", self.cycle),
+        }
+    }
+
+    /// Branches take 2 cycles, 3 if taken, 4 if taken to another page.
+    /// The extra cycles read the next opcode, then the address before its
+    /// high byte is fixed.
+    fn branch_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.relative_addr = self.read_program_byte() as i8;
+                self.absolute_addr = self.program_counter;
+                self.additional_cycle_operation = self.operate();
+                self.additional_cycle_operation == 0
+            }
+            3 => {
+                self.read(self.absolute_addr);
+                self.additional_cycle_operation == 1
+            }
+            _ => {
+                self.read((self.absolute_addr & 0xFF00) | (self.program_counter & 0x00FF));
+                true
+            }
+        }
+    }
+
+    fn jump_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.absolute_addr = self.read_program_byte() as u16;
+                false
+            }
+            _ => {
+                self.absolute_addr |= (self.read_program_byte() as u16) << 8;
+                self.operate();
+                true
+            }
+        }
+    }
+
+    /// The pointer doesn't carry into its high byte when reading the second
+    /// byte of the target. <https://nesdev.com/6502bugs.txt>
+    fn jump_indirect_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.pointer = self.read_program_byte() as u16;
+                false
+            }
+            3 => {
+                self.pointer |= (self.read_program_byte() as u16) << 8;
+                false
+            }
+            4 => {
+                self.absolute_addr = self.read(self.pointer) as u16;
+                false
+            }
+            _ => {
+                let [lo, hi] = self.pointer.to_le_bytes();
+                let target_hi = self.read(u16::from_le_bytes([lo.wrapping_add(1), hi]));
+                self.absolute_addr |= (target_hi as u16) << 8;
+                self.operate();
+                true
+            }
+        }
+    }
+
+    /// Also runs IRQs and NMIs, see `interrupts.rs`
+    fn brk_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                // Padding byte, skipped over by BRK only. It isn't part of
+                // the instruction, so BRK is decoded as implied.
+                match self.servicing_interrupt {
+                    true => self.read(self.program_counter),
+                    false => self.read_program_byte(),
+                };
+            }
+            3 => self.push_stack((self.program_counter >> 8) as u8),
+            4 => self.push_stack(self.program_counter as u8),
+            5 => {
+                self.pointer = self.take_interrupt_vector();
+                let status = match self.servicing_interrupt {
+                    true => self.status_register,
+                    false => self.status_register | CpuFlag::Break as u8,
+                };
+                self.push_stack(status | CpuFlag::Unused as u8);
+            }
+            6 => self.absolute_addr = self.read(self.pointer) as u16,
+            _ => {
+                self.absolute_addr |= (self.read(self.pointer + 1) as u16) << 8;
+                self.operate();
+                self.servicing_interrupt = false;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn jsr_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => self.absolute_addr = self.read_program_byte() as u16,
+            3 => {
+                self.peek_stack();
+            }
+            4 => self.push_stack((self.program_counter >> 8) as u8),
+            5 => self.push_stack(self.program_counter as u8),
+            _ => {
+                self.absolute_addr |= (self.read(self.program_counter) as u16) << 8;
+                self.operate();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn rti_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.read(self.program_counter);
+            }
+            3 => {
+                self.peek_stack();
+            }
+            4 => {
+                // Before the interrupt check, so I takes effect straight away
+                self.status_register = self.pop_stack();
+                self.clear_flag(&CpuFlag::Break);
+                self.set_flag(&CpuFlag::Unused);
+            }
+            5 => self.absolute_addr = self.pop_stack() as u16,
+            _ => {
+                self.absolute_addr |= (self.pop_stack() as u16) << 8;
+                self.operate();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn rts_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.read(self.program_counter);
+            }
+            3 => {
+                self.peek_stack();
+            }
+            4 => self.absolute_addr = self.pop_stack() as u16,
+            5 => self.absolute_addr |= (self.pop_stack() as u16) << 8,
+            _ => {
+                // Reads the return address while incrementing it
+                self.read(self.absolute_addr);
+                self.operate();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn push_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.read(self.program_counter);
+                false
+            }
+            _ => {
+                self.operate();
+                true
+            }
+        }
+    }
+
+    fn pull_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.read(self.program_counter);
+                false
+            }
+            3 => {
+                self.peek_stack();
+                false
+            }
+            _ => {
+                self.operate();
+                true
+            }
+        }
+    }
+}
@@ -4,7 +4,7 @@ pub enum CpuFlag {
     Carry = 1 << 0,
     Zero = 1 << 1,      // Set when result of operation is 0
     Interrupt = 1 << 2, // Disable interrupts; TODO: what is order?
-    Decimal = 1 << 3,   // If in Decimal mode; TODO: unused
+    Decimal = 1 << 3,   // If in Decimal mode; only used with `Cpu::decimal_mode`
     Break = 1 << 4,     // Set when a break instruction is executed
     Unused = 1 << 5,    // Unused
     Overflow = 1 << 6,  // Set when an overflow occurs. Only when using signed values
@@ -0,0 +1,92 @@
+use crate::{
+    cpu::{AddressingMode, CpuFlag, Memory},
+    opcodes::{OpCode, OpCodeType},
+    Cpu,
+};
+
+/// Devices that can pull the shared IRQ line low. The line is active while
+/// any of them holds it, and stays active until the device is acknowledged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqSource {
+    /// APU frame counter
+    ApuFrame = 1 << 0,
+    /// APU delta modulation channel
+    Dmc = 1 << 1,
+    /// Mapper or other cartridge hardware, such as the FDS drive
+    Mapper = 1 << 2,
+}
+
+/// # Interrupts
+/// IRQ is level sensitive: it is taken whenever the line is active and the
+/// interrupt disable flag is clear. NMI is edge sensitive: it is taken once
+/// each time the line goes from inactive to active.
+///
+/// The CPU only checks for interrupts at the end of the second to last cycle
+/// of each instruction. As a result:
+/// - CLI, SEI and PLP change the I flag on their last cycle, so an IRQ that
+///   they unmask is taken after the next instruction, and one they mask is
+///   still taken straight after them. RTI changes it earlier, so takes effect
+///   immediately.
+/// - A taken branch that doesn't cross a page doesn't check on its last two
+///   cycles, so an interrupt arriving then waits for another instruction.
+///
+/// An interrupt runs the BRK sequence, without skipping a byte and without
+/// the break flag in the pushed status. The vector is chosen part way
+/// through, so an NMI arriving during a BRK or IRQ hijacks it, and runs
+/// the NMI handler instead.
+///
+/// <https://www.nesdev.org/wiki/CPU_interrupts>
+impl<M: Memory> Cpu<M> {
+    /// Raise or release the IRQ line from one device
+    pub fn set_irq_line(&mut self, source: IrqSource, active: bool) {
+        match active {
+            true => self.irq_lines |= source as u8,
+            false => self.irq_lines &= !(source as u8),
+        }
+    }
+
+    /// Whether any device holds the IRQ line
+    pub fn irq_line(&self) -> bool {
+        self.irq_lines != 0
+    }
+
+    /// Set the level of the NMI line. Only going from inactive to active
+    /// triggers an interrupt.
+    pub fn set_nmi_line(&mut self, active: bool) {
+        if active && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = active;
+    }
+
+    /// Check for interrupts at the end of a cycle that isn't the last of its
+    /// instruction. The last check before the instruction finishes decides
+    /// whether the next cycle starts an interrupt.
+    pub(super) fn poll_interrupts(&mut self) {
+        let opcode = OpCode::decode(self.opcode);
+        match (opcode.code_type, opcode.addressing_mode) {
+            // Interrupts don't check, so the handler's first instruction always runs
+            (OpCodeType::BRK, _) => return,
+            // Taken branch that didn't cross a page
+            (_, AddressingMode::REL) if self.cycle == 2 && self.additional_cycle_operation == 1 => {
+                return
+            }
+            _ => {}
+        }
+
+        self.interrupt_polled =
+            self.nmi_pending || (self.irq_line() && !self.get_flag(&CpuFlag::Interrupt));
+    }
+
+    /// Vector to load on the current BRK or interrupt. An NMI arriving
+    /// before this is chosen takes over.
+    pub(super) fn take_interrupt_vector(&mut self) -> u16 {
+        match self.nmi_pending {
+            true => {
+                self.nmi_pending = false;
+                Self::NMI_VECTOR
+            }
+            false => Self::IRQ_VECTOR,
+        }
+    }
+}
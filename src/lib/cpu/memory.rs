@@ -0,0 +1,76 @@
+use crate::RcCell;
+
+/// # Memory
+/// Everything the CPU can see on its address bus. The NES [`crate::Bus`] is
+/// one implementation, [`FlatMemory`] is another, for running the CPU on its
+/// own.
+pub trait Memory {
+    /// Read a byte, with any side effects the read has
+    fn read(&mut self, address: u16) -> u8;
+
+    fn write(&mut self, address: u16, data: u8);
+
+    /// Read a byte without any side effects, for debuggers, tracing and tests
+    fn peek(&self, address: u16) -> u8;
+}
+
+/// 64K of RAM filling the whole address space, with no side effects on any
+/// access. For 6502 test suites, and for testing the CPU without the rest of
+/// the NES.
+pub struct FlatMemory {
+    pub ram: Box<[u8; 64 * 1024]>,
+}
+
+impl FlatMemory {
+    /// Zero filled memory, with `program` loaded at `address`
+    pub fn with_program(address: u16, program: &[u8]) -> Self {
+        let mut memory = Self::default();
+        for (offset, &data) in program.iter().enumerate() {
+            memory.write(address.wrapping_add(offset as u16), data);
+        }
+        memory
+    }
+}
+
+impl Default for FlatMemory {
+    fn default() -> Self {
+        Self {
+            ram: Box::new([0; 64 * 1024]),
+        }
+    }
+}
+
+impl Memory for FlatMemory {
+    #[inline(always)]
+    fn read(&mut self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+
+    #[inline(always)]
+    fn write(&mut self, address: u16, data: u8) {
+        self.ram[address as usize] = data;
+    }
+
+    #[inline(always)]
+    fn peek(&self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+}
+
+/// Memory shared with the rest of the machine, like the NES bus
+impl<M: Memory> Memory for RcCell<M> {
+    #[inline(always)]
+    fn read(&mut self, address: u16) -> u8 {
+        self.borrow_mut().read(address)
+    }
+
+    #[inline(always)]
+    fn write(&mut self, address: u16, data: u8) {
+        self.borrow_mut().write(address, data)
+    }
+
+    #[inline(always)]
+    fn peek(&self, address: u16) -> u8 {
+        self.borrow().peek(address)
+    }
+}
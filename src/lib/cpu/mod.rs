@@ -1,10 +1,14 @@
 #![allow(clippy::module_inception)]
-pub mod cpu;
 mod addressing;
+pub mod cpu;
+mod cycles;
+mod interrupts;
+mod memory;
 
 pub mod flags;
 
-pub use cpu::Cpu;
 pub use addressing::AddressingMode;
+pub use cpu::Cpu;
 pub use flags::CpuFlag;
-
+pub use interrupts::IrqSource;
+pub use memory::{FlatMemory, Memory};
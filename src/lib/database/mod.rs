@@ -0,0 +1,5 @@
+mod rom_database;
+mod xml;
+
+pub use rom_database::{DatabaseError, GameInfo, RomDatabase};
+pub use xml::XmlError;
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::cartridge::{CartridgeMetadata, Header, Mirroring, Timing};
+
+use super::xml::{self, XmlError, XmlEvent};
+
+/// Database compiled into the emulator
+const BUNDLED_DATABASE: &str = include_str!("database.xml");
+
+/// # ROM Database
+/// Known dumps and the boards they run on, used to correct bad headers
+/// and to name games.
+///
+/// ## Format
+/// XML in the style of [NesCartDB](https://nescartdb.com), with the board
+/// fields of the [NES 2.0 XML database](https://forums.nesdev.org/viewtopic.php?t=19940)
+/// as attributes of `<board>`:
+///
+/// ```xml
+/// <database>
+///   <game name="Super Mario Bros." publisher="Nintendo">
+///     <cartridge system="NES-NTSC" crc="3337EC46">
+///       <board type="NES-NROM-256" mapper="0" mirroring="V" battery="0"/>
+///       <prg size="32k" crc="..."/>
+///       <chr size="8k" crc="..."/>
+///     </cartridge>
+///   </game>
+/// </database>
+/// ```
+///
+/// | Attribute             | Description                                        |
+/// |-----------------------|----------------------------------------------------|
+/// | `game name`           | Title, required                                    |
+/// | `game publisher`      | Publisher                                          |
+/// | `cartridge system`    | `NES-NTSC`, `Famicom`, `NES-PAL`, `NES-PAL-A`, `NES-PAL-B` or `Dendy` |
+/// | `cartridge crc`       | CRC32 of the PRG-ROM followed by the CHR-ROM       |
+/// | `board type`          | Board name                                         |
+/// | `board mapper`        | iNES mapper number                                 |
+/// | `board submapper`     | NES 2.0 submapper number                           |
+/// | `board mirroring`     | `H`, `V` or `4`                                    |
+/// | `board battery`       | `1` if PRG-RAM is battery backed                   |
+/// | `prg crc`, `chr crc`  | CRC32 of each ROM, used if `cartridge crc` is missing |
+///
+/// Every cartridge a game was released on is its own entry. Attributes
+/// that are left out are not corrected.
+///
+/// The bundled `database.xml` is generated by `examples/import_database.rs`
+/// from the game database of [TetaNES](https://github.com/lukexor/tetanes),
+/// which covers the No-Intro NES set. It names every game and corrects
+/// mappers, mirroring, batteries and regions. Publishers and boards are only
+/// filled in when the importer is given a NesCartDB export.
+pub struct RomDatabase {
+    /// Keyed by the CRC32 of PRG-ROM and CHR-ROM together
+    by_crc32: HashMap<u32, GameInfo>,
+    /// Keyed by the CRC32s of PRG-ROM and CHR-ROM separately
+    by_rom_crc32: HashMap<(u32, u32), GameInfo>,
+}
+
+/// What the database knows about a dump
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GameInfo {
+    pub name: String,
+    pub publisher: Option<String>,
+    pub board: Option<String>,
+    pub mapper: Option<u16>,
+    pub submapper: Option<u8>,
+    pub mirroring: Option<Mirroring>,
+    pub battery: Option<bool>,
+    pub timing: Option<Timing>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseError {
+    InvalidXml(XmlError),
+    /// An attribute has a value that can't be used. Holds the attribute
+    /// name and value.
+    InvalidValue(String, String),
+    /// A `<game>` has no `name`
+    MissingName,
+    /// A `<cartridge>` has no CRC to find it by
+    MissingCrc(String),
+}
+
+impl RomDatabase {
+    /// The database bundled with the emulator, read on first use
+    pub fn bundled() -> &'static Self {
+        static DATABASE: OnceLock<RomDatabase> = OnceLock::new();
+        DATABASE.get_or_init(|| {
+            Self::from_xml(BUNDLED_DATABASE).expect("Bundled database should be valid")
+        })
+    }
+
+    pub fn from_xml(text: &str) -> Result<Self, DatabaseError> {
+        let mut database = Self {
+            by_crc32: HashMap::new(),
+            by_rom_crc32: HashMap::new(),
+        };
+
+        let mut game = GameInfo::default();
+        let mut cartridge = GameInfo::default();
+        let mut crc32 = None;
+        let mut prg_crc32 = None;
+        let mut chr_crc32 = None;
+        for event in xml::parse(text).map_err(DatabaseError::InvalidXml)? {
+            let (name, attributes) = match event {
+                XmlEvent::Start { name, attributes } => (name, attributes),
+                XmlEvent::End { name: "cartridge" } => {
+                    match (crc32.take(), prg_crc32.take(), chr_crc32.take()) {
+                        (Some(crc32), _, _) => database.by_crc32.insert(crc32, cartridge.clone()),
+                        (None, Some(prg), chr) => database
+                            .by_rom_crc32
+                            .insert((prg, chr.unwrap_or(0)), cartridge.clone()),
+                        _ => return Err(DatabaseError::MissingCrc(cartridge.name)),
+                    };
+                    continue;
+                }
+                XmlEvent::End { .. } => continue,
+            };
+
+            match name {
+                "game" => {
+                    game = GameInfo::default();
+                    for (attribute, value) in attributes {
+                        match attribute {
+                            "name" => game.name = value,
+                            "publisher" => game.publisher = Some(value),
+                            _ => {}
+                        }
+                    }
+                    if game.name.is_empty() {
+                        return Err(DatabaseError::MissingName);
+                    }
+                }
+                "cartridge" => {
+                    cartridge = game.clone();
+                    for (attribute, value) in attributes {
+                        match attribute {
+                            "system" => cartridge.timing = Some(parse_system(&value)?),
+                            "crc" => crc32 = Some(parse_crc(attribute, &value)?),
+                            _ => {}
+                        }
+                    }
+                }
+                "board" => {
+                    for (attribute, value) in attributes {
+                        match attribute {
+                            "type" => cartridge.board = Some(value),
+                            "mapper" => cartridge.mapper = Some(parse_number(attribute, &value)?),
+                            "submapper" => {
+                                cartridge.submapper = Some(parse_number(attribute, &value)?)
+                            }
+                            "mirroring" => {
+                                cartridge.mirroring = Some(match value.as_str() {
+                                    "H" => Mirroring::Horizontal,
+                                    "V" => Mirroring::Vertical,
+                                    "4" => Mirroring::FourScreen,
+                                    _ => return Err(invalid(attribute, &value)),
+                                })
+                            }
+                            "battery" => {
+                                cartridge.battery =
+                                    Some(parse_number::<u8>(attribute, &value)? != 0)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                "prg" | "chr" => {
+                    let crc = attributes
+                        .iter()
+                        .find(|(attribute, _)| *attribute == "crc")
+                        .map(|(attribute, value)| parse_crc(attribute, value))
+                        .transpose()?;
+                    match name {
+                        "prg" => prg_crc32 = crc,
+                        _ => chr_crc32 = crc,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(database)
+    }
+
+    /// Entry for a cartridge, by the CRC32s of its ROM
+    pub fn find(&self, metadata: &CartridgeMetadata) -> Option<&GameInfo> {
+        self.find_crc32(metadata.crc32).or_else(|| {
+            self.by_rom_crc32
+                .get(&(metadata.prg_crc32, metadata.chr_crc32))
+        })
+    }
+
+    /// Entry for the CRC32 of a PRG-ROM followed by its CHR-ROM
+    pub fn find_crc32(&self, crc32: u32) -> Option<&GameInfo> {
+        self.by_crc32.get(&crc32)
+    }
+
+    /// Number of cartridges in the database
+    pub fn len(&self) -> usize {
+        self.by_crc32.len() + self.by_rom_crc32.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl GameInfo {
+    /// `header` with the fields the database knows about replaced.
+    /// Headers that need correcting are converted to NES 2.0 to make room
+    /// for every field, see [`Header::to_nes_two`].
+    pub fn correct_header(&self, header: &Header) -> Header {
+        let needs_correcting = self
+            .mapper
+            .is_some_and(|mapper| mapper != header.mapper_id())
+            || self
+                .submapper
+                .is_some_and(|submapper| submapper != header.submapper_id())
+            || self
+                .mirroring
+                .is_some_and(|mirroring| mirroring != header.mirroring())
+            || self
+                .battery
+                .is_some_and(|battery| battery != header.has_battery())
+            || self.timing.is_some_and(|timing| timing != header.timing());
+        if !needs_correcting {
+            return *header;
+        }
+
+        let mut corrected = header.to_nes_two();
+        corrected.set_mapper(
+            self.mapper.unwrap_or(header.mapper_id()),
+            self.submapper.unwrap_or(header.submapper_id()),
+        );
+        if let Some(mirroring) = self.mirroring {
+            corrected.set_mirroring(mirroring);
+        }
+        if let Some(battery) = self.battery {
+            // Move PRG-RAM to match whether it is battery backed
+            let program_ram = corrected.prg_ram_bytes() + corrected.prg_nvram_bytes();
+            match battery {
+                true => corrected.set_prg_ram_bytes(0, program_ram),
+                false => corrected.set_prg_ram_bytes(program_ram, 0),
+            }
+            corrected.set_battery(battery);
+        }
+        if let Some(timing) = self.timing {
+            corrected.set_timing(timing);
+        }
+        corrected
+    }
+}
+
+impl Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::InvalidXml(e) => write!(f, "Invalid database XML, {}", e),
+            DatabaseError::InvalidValue(attribute, value) => {
+                write!(f, "Invalid database value {}={:?}", attribute, value)
+            }
+            DatabaseError::MissingName => write!(f, "Database game has no name"),
+            DatabaseError::MissingCrc(game) => {
+                write!(f, "Database cartridge of {:?} has no CRC", game)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+fn invalid(attribute: &str, value: &str) -> DatabaseError {
+    DatabaseError::InvalidValue(attribute.to_string(), value.to_string())
+}
+
+fn parse_crc(attribute: &str, value: &str) -> Result<u32, DatabaseError> {
+    u32::from_str_radix(value, 16).map_err(|_| invalid(attribute, value))
+}
+
+fn parse_number<T: std::str::FromStr>(attribute: &str, value: &str) -> Result<T, DatabaseError> {
+    value.parse().map_err(|_| invalid(attribute, value))
+}
+
+/// Timing from NesCartDB's `system` attribute
+fn parse_system(value: &str) -> Result<Timing, DatabaseError> {
+    match value {
+        "NES-NTSC" | "Famicom" => Ok(Timing::Ntsc),
+        "NES-PAL" | "NES-PAL-A" | "NES-PAL-B" => Ok(Timing::Pal),
+        "Dendy" => Ok(Timing::Dendy),
+        _ => Err(invalid("system", value)),
+    }
+}
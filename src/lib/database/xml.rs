@@ -0,0 +1,122 @@
+use std::fmt::Display;
+
+/// Start or end of an element, in document order. Self-closing elements
+/// give a `Start` followed by an `End`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum XmlEvent<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+    },
+    End {
+        name: &'a str,
+    },
+}
+
+/// Where and why a document could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for XmlError {}
+
+/// # XML
+/// Read the elements and attributes of an XML document, which is all the
+/// ROM database needs. Text content, comments, processing instructions
+/// and doctypes are skipped. Every element must be closed, in order.
+pub fn parse(text: &str) -> Result<Vec<XmlEvent<'_>>, XmlError> {
+    let mut events = Vec::new();
+    // Elements that have started but not ended yet
+    let mut open = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let offset = text.len() - rest.len() + start;
+        let error = |message| XmlError {
+            line: text[..offset].matches('\n').count() + 1,
+            message,
+        };
+        rest = &rest[start + 1..];
+
+        // Comments, <?xml ...?> and <!DOCTYPE ...>
+        let skipped = [("!--", "-->"), ("?", "?>"), ("!", ">")]
+            .into_iter()
+            .find(|(open, _)| rest.starts_with(open));
+        if let Some((_, close)) = skipped {
+            let end = rest.find(close).ok_or_else(|| error("unclosed markup"))?;
+            rest = &rest[end + close.len()..];
+            continue;
+        }
+
+        let end = rest.find('>').ok_or_else(|| error("unclosed tag"))?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            if open.pop() != Some(name) {
+                return Err(error("end tag doesn't match start tag"));
+            }
+            events.push(XmlEvent::End { name });
+            continue;
+        }
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = &tag[..name_end];
+        if name.is_empty() {
+            return Err(error("tag has no name"));
+        }
+        let attributes =
+            parse_attributes(&tag[name_end..]).ok_or_else(|| error("bad attribute"))?;
+
+        events.push(XmlEvent::Start { name, attributes });
+        match self_closing {
+            true => events.push(XmlEvent::End { name }),
+            false => open.push(name),
+        }
+    }
+    if !open.is_empty() {
+        return Err(XmlError {
+            line: text.matches('\n').count() + 1,
+            message: "element is never closed",
+        });
+    }
+    Ok(events)
+}
+
+/// `name="value"` pairs, with either kind of quote
+fn parse_attributes(mut text: &str) -> Option<Vec<(&str, String)>> {
+    let mut attributes = Vec::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return Some(attributes);
+        }
+        let equals = text.find('=')?;
+        let name = text[..equals].trim();
+        text = text[equals + 1..].trim_start();
+        let quote = text.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = text[1..].find(quote)? + 1;
+        attributes.push((name, unescape(&text[1..end])));
+        text = &text[end + 1..];
+    }
+}
+
+/// Replace the predefined entities
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
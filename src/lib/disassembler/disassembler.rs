@@ -0,0 +1,127 @@
+use std::fmt::Display;
+
+use crate::{
+    cpu::AddressingMode,
+    opcodes::{OpCode, OpCodeType},
+};
+
+/// # Disassembled Instruction
+/// One instruction, decoded from the bytes at `address`.
+///
+/// Operands are written in the usual assembler syntax for their addressing
+/// mode, e.g. `#$10`, `$10,X`, `($1234)` or `($10),Y`. Branches show the
+/// address they jump to rather than their offset.
+///
+/// <https://www.nesdev.org/wiki/CPU_addressing_modes>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: OpCode,
+    /// The opcode followed by its operand
+    bytes: Vec<u8>,
+}
+
+/// Decode the instruction at `address`. `peek` reads a byte of memory, and
+/// should be free of side effects, e.g. [`crate::Bus::peek_cpu`].
+pub fn disassemble(address: u16, peek: impl Fn(u16) -> u8) -> Instruction {
+    let opcode = OpCode::from(peek(address));
+    let bytes = (0..=opcode.addressing_mode.operand_len())
+        .map(|offset| peek(address.wrapping_add(offset)))
+        .collect();
+    Instruction {
+        address,
+        opcode,
+        bytes,
+    }
+}
+
+/// Decode `count` instructions one after another, starting at `address`
+pub fn disassemble_range(address: u16, count: usize, peek: impl Fn(u16) -> u8) -> Vec<Instruction> {
+    let mut address = address;
+    (0..count)
+        .map(|_| {
+            let instruction = disassemble(address, &peek);
+            address = instruction.next_address();
+            instruction
+        })
+        .collect()
+}
+
+impl Instruction {
+    /// The opcode followed by its operand
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Number of bytes, 1 to 3
+    pub fn length(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+
+    /// Address of the instruction that follows in memory
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.length())
+    }
+
+    /// Whether this is one of the opcodes documented by MOS
+    pub fn is_official(&self) -> bool {
+        OpCode::is_official(self.bytes[0])
+    }
+
+    pub fn mnemonic(&self) -> String {
+        format!("{:?}", self.opcode.code_type)
+    }
+
+    /// The operand bytes as a little endian value
+    fn operand_value(&self) -> u16 {
+        let lo = self.bytes.get(1).copied().unwrap_or(0);
+        let hi = self.bytes.get(2).copied().unwrap_or(0);
+        u16::from_le_bytes([lo, hi])
+    }
+
+    /// Where a branch goes if it is taken
+    pub fn branch_target(&self) -> Option<u16> {
+        match self.opcode.addressing_mode {
+            AddressingMode::REL => {
+                let offset = self.operand_value() as u8 as i8;
+                Some(self.next_address().wrapping_add(offset as u16))
+            }
+            _ => None,
+        }
+    }
+
+    /// The operand, formatted for its addressing mode. Empty for implied
+    /// instructions, except for shifts and rotates on the accumulator.
+    pub fn operand(&self) -> String {
+        let value = self.operand_value();
+        match self.opcode.addressing_mode {
+            AddressingMode::IMP => match self.opcode.code_type {
+                OpCodeType::ASL | OpCodeType::LSR | OpCodeType::ROL | OpCodeType::ROR => {
+                    "A".to_string()
+                }
+                _ => String::new(),
+            },
+            AddressingMode::IMM => format!("#${value:02X}"),
+            AddressingMode::ZP0 => format!("${value:02X}"),
+            AddressingMode::ZPX => format!("${value:02X},X"),
+            AddressingMode::ZPY => format!("${value:02X},Y"),
+            AddressingMode::REL => format!("${:04X}", self.branch_target().unwrap_or(0)),
+            AddressingMode::ABS => format!("${value:04X}"),
+            AddressingMode::ABX => format!("${value:04X},X"),
+            AddressingMode::ABY => format!("${value:04X},Y"),
+            AddressingMode::IND => format!("(${value:04X})"),
+            AddressingMode::IZX => format!("(${value:02X},X)"),
+            AddressingMode::IZY => format!("(${value:02X}),Y"),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operand = self.operand();
+        match operand.is_empty() {
+            true => write!(f, "{}", self.mnemonic()),
+            false => write!(f, "{} {operand}", self.mnemonic()),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+#![allow(clippy::module_inception)]
+mod disassembler;
+
+pub use disassembler::{disassemble, disassemble_range, Instruction};
@@ -1,21 +1,36 @@
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use eframe::App;
 use eframe::NativeOptions;
 use egui::CentralPanel;
+use egui::ColorImage;
 use egui::Context;
+use egui::Key;
+use egui::Rect;
 use egui::SidePanel;
+use egui::TextureHandle;
+use egui::TextureOptions;
 use egui::Ui;
 use egui_file::FileDialog;
 use fstrings::f;
 use fstrings::format_args_f;
 
+use log::{error, info};
 use thousands::Separable;
 
+use super::save_slots::{format_timestamp, SaveSlots, Thumbnail};
+
+use crate::cartridge::{BatterySave, FileFormat};
+
+use crate::controllers::{ControllerInput, PortDeviceKind};
 use crate::cpu::cpu::Registers;
+use crate::fds::{DiskDiff, DiskImage};
+use crate::patch::Patch;
+use crate::savestate::{Rewind, RewindConfig, RunAhead};
 use crate::Cartridge;
 use crate::Clock;
 use crate::Nes;
@@ -31,10 +46,54 @@ pub struct Gui {
     open_file_dialog: Option<FileDialog>,
     opened_file: Option<PathBuf>,
     playback_speed: Option<f64>,
+    /// Devices plugged into each controller port, remembered per ROM
+    port_devices: HashMap<PathBuf, [PortDeviceKind; 2]>,
+    /// Area the game is drawn in, used to map the pointer onto the screen
+    screen_rect: Rect,
+    /// Save state slots for the opened ROM
+    save_slots: Option<SaveSlots>,
+    selected_slot: usize,
+    show_slot_picker: bool,
+    /// Thumbnails of each save slot, uploaded to the GPU when first shown
+    slot_textures: HashMap<usize, TextureHandle>,
+    /// Shown in a dialog until dismissed
+    error_message: Option<String>,
+    rewind: Rewind,
+    rewind_enabled: bool,
+    /// Rewind button was held down last frame
+    rewind_held: bool,
+    run_ahead: RunAhead,
+    /// `.sav` file for the inserted cartridge, if it has a battery
+    battery: Option<BatterySave>,
+    /// `.fdsdiff` file for the inserted disk, if a Disk System game is open
+    disk_diff: Option<DiskDiff>,
+    last_save_flush: Instant,
+    /// Disk System BIOS chosen by the user. If unset, `disksys.rom` is
+    /// looked for next to the disk image and in the working directory.
+    disk_bios: Option<PathBuf>,
+    bios_file_dialog: Option<FileDialog>,
 }
 
 impl Gui {
     const FRAMERATE_UPDATE_INTERVAL: u64 = 10;
+    /// How often battery-backed RAM and disk writes are saved while
+    /// playing, in case the emulator does not exit cleanly
+    const SAVE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+    const QUICK_SAVE_KEY: Key = Key::F5;
+    const PREVIOUS_SLOT_KEY: Key = Key::F6;
+    const NEXT_SLOT_KEY: Key = Key::F7;
+    const QUICK_LOAD_KEY: Key = Key::F8;
+    /// Plays the game backwards while held
+    const REWIND_KEY: Key = Key::Backspace;
+    /// Frames stepped back per GUI frame while rewinding
+    const REWIND_SPEED: usize = 2;
+    /// Power Pad buttons 1-12, laid out in the same 4x3 grid as the mat
+    #[rustfmt::skip]
+    const POWER_PAD_KEYS: [Key; 12] = [
+        Key::Q, Key::W, Key::E, Key::R,
+        Key::A, Key::S, Key::D, Key::F,
+        Key::Z, Key::X, Key::C, Key::V,
+    ];
 
     pub fn new(nes: Nes) -> Self {
         Self {
@@ -46,6 +105,22 @@ impl Gui {
             open_file_dialog: None,
             opened_file: None,
             playback_speed: None,
+            port_devices: HashMap::new(),
+            screen_rect: Rect::NOTHING,
+            save_slots: None,
+            selected_slot: 0,
+            show_slot_picker: false,
+            slot_textures: HashMap::new(),
+            error_message: None,
+            rewind: Rewind::new(RewindConfig::default()),
+            rewind_enabled: true,
+            rewind_held: false,
+            run_ahead: RunAhead::default(),
+            battery: None,
+            disk_diff: None,
+            last_save_flush: Instant::now(),
+            disk_bios: None,
+            bios_file_dialog: None,
         }
     }
 
@@ -74,7 +149,7 @@ impl Gui {
             let elapsed_time = self.startup_time.elapsed().as_secs_f32();
             let elapsed_str = fstrings::f!("Elapsed Time: {elapsed_time:.4}s");
 
-            if tick_number % Self::FRAMERATE_UPDATE_INTERVAL == 0 {
+            if tick_number.is_multiple_of(Self::FRAMERATE_UPDATE_INTERVAL) {
                 self.update_framerate();
                 self.update_delta_time();
             }
@@ -116,16 +191,39 @@ impl Gui {
         });
     }
 
-    fn simulate_nes_frame(&mut self) {
+    fn simulate_nes_frame(&mut self, input: ControllerInput) {
         // Each frame is exactly 33277.5 frames; need to alternate
-        let cycles = match self.clock.total_ticks() % 2 == 0 {
+        let cycles = match self.clock.total_ticks().is_multiple_of(2) {
             true => 33278,
             false => 33278,
         } as f64
             * self.playback_speed.unwrap_or(1.0);
-        for _ in 0..cycles.round() as u64 {
-            self.nes.tick()
+        let ticks = cycles.round() as u64;
+
+        if self.rewind_enabled {
+            self.rewind.run_frame(&mut self.nes, input, ticks);
+        } else {
+            self.nes.update_port_input(&input);
+            for _ in 0..ticks {
+                self.nes.tick()
+            }
+        }
+        self.run_ahead.run_ahead(&mut self.nes, &input, ticks);
+    }
+
+    /// Step back through the rewind history while the rewind key or button
+    /// is held. Returns `true` if the game is being rewound this frame.
+    fn rewind_nes_frame(&mut self, ctx: &Context) -> bool {
+        let held = self.rewind_held || ctx.input().key_down(Self::REWIND_KEY);
+        if !self.rewind_enabled || !held {
+            return false;
+        }
+        for _ in 0..Self::REWIND_SPEED {
+            if !self.rewind.step_back(&mut self.nes) {
+                break;
+            }
         }
+        true
     }
 
     fn update_delta_time(&mut self) {
@@ -144,12 +242,260 @@ impl Gui {
     fn playback_speed(&self) -> f64 {
         self.playback_speed.unwrap_or(1.0)
     }
+
+    fn controller_input(&self, ctx: &Context) -> ControllerInput {
+        let input = ctx.input();
+        let pointer_x = match input.pointer.hover_pos() {
+            Some(pos) if self.screen_rect.width() > 0.0 => {
+                (pos.x - self.screen_rect.left()) / self.screen_rect.width()
+            }
+            _ => 0.0,
+        };
+
+        ControllerInput {
+            pointer_x,
+            pointer_down: input.pointer.primary_down(),
+            power_pad: Self::POWER_PAD_KEYS.map(|key| input.key_down(key)),
+        }
+    }
+
+    /// Plug in the devices last used with the currently opened ROM
+    fn connect_port_devices(&mut self) {
+        let devices = self
+            .opened_file
+            .as_ref()
+            .and_then(|file| self.port_devices.get(file))
+            .copied()
+            .unwrap_or_default();
+        for (port, kind) in devices.into_iter().enumerate() {
+            self.nes.connect_port_device(port, kind);
+        }
+    }
+
+    /// Apply the patch next to the ROM, if there is one, see [`Patch`]
+    fn apply_patch(file: &Path, file_contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        let patch = match Patch::for_rom(file) {
+            Ok(Some(patch)) => patch,
+            Ok(None) => return Ok(file_contents),
+            Err(e) => return Err(f!("Error reading patch for {file:?}: {e}")),
+        };
+        patch.apply(&file_contents).map_err(|e| {
+            let path = patch.path();
+            f!("Could not apply patch {path:?}: {e}")
+        })
+    }
+
+    /// Insert the ROM at `file`, replacing any cartridge already inserted.
+    /// A patch with the same name is applied first. Errors are shown in a
+    /// dialog.
+    pub fn open_rom(&mut self, file: PathBuf) {
+        let file_contents = match std::fs::read(&file) {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.error_message = Some(f!("Error reading {file:?}: {e}"));
+                return;
+            }
+        };
+        info!("Read {} bytes from {:?}", file_contents.len(), file);
+        let file_contents = match Self::apply_patch(&file, file_contents) {
+            Ok(contents) => contents,
+            Err(message) => {
+                self.error_message = Some(message);
+                return;
+            }
+        };
+
+        let cartridge = match DiskImage::is_disk_image(&file_contents) {
+            true => match self.read_disk_bios(&file) {
+                Some(bios) => Cartridge::from_disk_image(&file_contents, &bios),
+                None => {
+                    self.error_message = Some(
+                        "Choose the Disk System BIOS (disksys.rom) to play FDS games".to_string(),
+                    );
+                    return;
+                }
+            },
+            false => Cartridge::try_from(file_contents),
+        };
+        let mut cartridge = match cartridge {
+            Ok(cartridge) => cartridge,
+            Err(e) => {
+                self.error_message = Some(f!("Failed to load cartridge: {e}"));
+                return;
+            }
+        };
+
+        self.eject();
+
+        if cartridge.has_battery() {
+            let mut battery = BatterySave::for_rom(&file);
+            if let Err(e) = battery.load(&mut cartridge) {
+                let path = battery.path();
+                self.error_message = Some(f!("Could not load save {path:?}: {e}"));
+            }
+            self.battery = Some(battery);
+            self.last_save_flush = Instant::now();
+            // Restore the trainer over anything the save had at 0x7000
+            cartridge.power_on();
+        }
+        if cartridge.disk_system.is_some() {
+            let mut disk_diff = DiskDiff::for_rom(&file);
+            if let Err(e) = disk_diff.load(&mut cartridge) {
+                let path = disk_diff.path();
+                self.error_message = Some(f!("Could not load disk changes {path:?}: {e}"));
+            }
+            self.disk_diff = Some(disk_diff);
+            self.last_save_flush = Instant::now();
+        }
+
+        for line in Self::cartridge_description(&cartridge) {
+            info!("{}", line);
+        }
+        self.nes
+            .insert_cartidge(Some(Rc::new(RefCell::new(cartridge))));
+        self.opened_file = Some(file.clone());
+        self.connect_port_devices();
+        self.nes.reset();
+
+        self.save_slots = Some(SaveSlots::for_rom(&file, self.nes.rom_crc32()));
+        self.slot_textures.clear();
+        self.rewind.clear();
+    }
+
+    /// Use the Disk System BIOS at `path` for FDS games
+    pub fn set_disk_bios(&mut self, path: PathBuf) {
+        self.disk_bios = Some(path);
+    }
+
+    /// Read the Disk System BIOS, from the file the user chose or else
+    /// `disksys.rom` next to `rom_path` or in the working directory
+    fn read_disk_bios(&self, rom_path: &std::path::Path) -> Option<Vec<u8>> {
+        let candidates = match &self.disk_bios {
+            Some(path) => vec![path.clone()],
+            None => vec![
+                rom_path.with_file_name("disksys.rom"),
+                PathBuf::from("disksys.rom"),
+            ],
+        };
+        candidates.iter().find_map(|path| {
+            let bios = std::fs::read(path).ok()?;
+            info!("Using Disk System BIOS {:?}", path);
+            Some(bios)
+        })
+    }
+
+    /// Remove the cartridge, saving its battery-backed RAM and disk writes
+    /// first
+    fn eject(&mut self) {
+        self.flush_saves();
+        self.battery = None;
+        self.disk_diff = None;
+
+        self.nes.insert_cartidge(None);
+        self.nes.reset();
+        self.opened_file = None;
+        self.save_slots = None;
+        self.slot_textures.clear();
+        self.rewind.clear();
+    }
+
+    /// Write battery-backed PRG-RAM to its `.sav` file, and disk writes to
+    /// the `.fdsdiff` file, if they have changed
+    fn flush_saves(&mut self) {
+        self.last_save_flush = Instant::now();
+        let cartridge = match self.nes.cartridge_ref() {
+            Some(cartridge) => cartridge,
+            None => return,
+        };
+        if let Some(battery) = &mut self.battery {
+            let result = battery.flush(&cartridge.borrow());
+            if let Err(e) = result {
+                let path = battery.path();
+                self.error_message = Some(f!("Could not write save {path:?}: {e}"));
+            }
+        }
+        if let Some(disk_diff) = &mut self.disk_diff {
+            let result = disk_diff.flush(&cartridge.borrow());
+            if let Err(e) = result {
+                let path = disk_diff.path();
+                self.error_message = Some(f!("Could not write disk changes {path:?}: {e}"));
+            }
+        }
+    }
+
+    fn save_to_slot(&mut self, slot: usize) {
+        let slots = match &mut self.save_slots {
+            Some(slots) => slots,
+            None => {
+                self.error_message = Some("Open a ROM before saving a state".to_string());
+                return;
+            }
+        };
+        match slots.save(slot, &self.nes) {
+            Ok(()) => {
+                self.slot_textures.remove(&slot);
+            }
+            Err(e) => self.error_message = Some(e.to_string()),
+        }
+    }
+
+    fn load_from_slot(&mut self, slot: usize) {
+        let slots = match &self.save_slots {
+            Some(slots) => slots,
+            None => {
+                self.error_message = Some("Open a ROM before loading a state".to_string());
+                return;
+            }
+        };
+        // Restore the current state if the slot can't be loaded, so a
+        // corrupt slot doesn't leave the machine half restored.
+        let backup = self.nes.save_state();
+        match slots.load(slot, &mut self.nes) {
+            Ok(()) => self.rewind.clear(),
+            Err(e) => {
+                self.nes
+                    .load_state(&backup)
+                    .expect("State saved this frame should load");
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    fn handle_save_state_keys(&mut self, ctx: &Context) {
+        let (save, load, previous, next) = {
+            let input = ctx.input();
+            (
+                input.key_pressed(Self::QUICK_SAVE_KEY),
+                input.key_pressed(Self::QUICK_LOAD_KEY),
+                input.key_pressed(Self::PREVIOUS_SLOT_KEY),
+                input.key_pressed(Self::NEXT_SLOT_KEY),
+            )
+        };
+
+        if previous {
+            self.selected_slot =
+                (self.selected_slot + SaveSlots::SLOT_COUNT - 1) % SaveSlots::SLOT_COUNT;
+        }
+        if next {
+            self.selected_slot = (self.selected_slot + 1) % SaveSlots::SLOT_COUNT;
+        }
+        if save {
+            self.save_to_slot(self.selected_slot);
+        }
+        if load {
+            self.load_from_slot(self.selected_slot);
+        }
+    }
 }
 
 impl App for Gui {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.clock.tick();
-        self.simulate_nes_frame();
+        self.handle_save_state_keys(ctx);
+        if !self.rewind_nes_frame(ctx) {
+            let input = self.controller_input(ctx);
+            self.simulate_nes_frame(input);
+        }
 
         SidePanel::right("Debug").show(ctx, |ui| {
             ui.heading("Debug Panel");
@@ -166,12 +512,31 @@ impl App for Gui {
             self.render_toolbar(ctx, ui);
         });
 
-        CentralPanel::default().show(ctx, |_ui: &mut egui::Ui| {
-            // TODO: put the nes image herecargo run --profile=release-lto
-        });
+        self.screen_rect = CentralPanel::default()
+            .show(ctx, |_ui: &mut egui::Ui| {
+                // TODO: put the nes image herecargo run --profile=release-lto
+            })
+            .response
+            .rect;
+
+        self.render_slot_picker(ctx);
+        self.render_error(ctx);
+
+        if self.last_save_flush.elapsed() >= Self::SAVE_FLUSH_INTERVAL {
+            self.flush_saves();
+        }
+
         // force refresh
         ctx.request_repaint();
     }
+
+    fn on_close_event(&mut self) -> bool {
+        self.flush_saves();
+        if let Some(message) = &self.error_message {
+            error!("{}", message);
+        }
+        true
+    }
 }
 
 impl Gui {
@@ -190,39 +555,96 @@ impl Gui {
             };
             let c = c.borrow();
 
-            let header = c.header;
-            let program_rom_size = header.prg_rom_size;
-            let program_chr_size = header.prg_chr_size;
-            let mapper = c.mapper_id;
-            let flag_6 = header.flag_6;
-            let flag_7 = header.flag_7;
-
-            let program_rom_size_str = f!("Program ROM Size: {program_rom_size} chunks");
-            let program_chr_size_str = f!("Program CHR Size: {program_chr_size} chunks");
-            let flag_6_str = f!("Flag 6: {flag_6:#04x}");
-            let flag_7_str = f!("Flag 7: {flag_7:#04x}");
-            let mapper_str = f!("Mapper: {mapper}");
-
             let open_file_str = match opened_file {
                 Some(path) => path.to_str().unwrap_or(""),
                 None => "None",
             };
             ui.label(open_file_str);
             ui.add_space(3.0);
-            ui.label(program_rom_size_str);
-            ui.label(program_chr_size_str);
-            ui.label(flag_6_str);
-            ui.label(flag_7_str);
-            ui.label(mapper_str);
+
+            for line in Self::cartridge_description(&c) {
+                ui.label(line);
+            }
         });
     }
 
+    /// Human readable summary of the board and ROM, one line per property
+    fn cartridge_description(cartridge: &Cartridge) -> Vec<String> {
+        let header = &cartridge.header;
+        let metadata = &cartridge.metadata;
+        let kilobytes = |bytes: usize| bytes / 1024;
+
+        let format = match metadata.file_format {
+            FileFormat::INes => header.format.name(),
+            file_format => file_format.name(),
+        };
+        let mapper = header.mapper_id();
+        let submapper = header.submapper_id();
+        let prg_rom = kilobytes(header.prg_rom_bytes());
+        let chr = match cartridge.has_character_ram {
+            true => f!(
+                "CHR-RAM: {}KB",
+                kilobytes(cartridge.virtual_character_memory.len())
+            ),
+            false => f!("CHR-ROM: {}KB", kilobytes(header.chr_rom_bytes())),
+        };
+        let prg_ram = kilobytes(cartridge.program_ram.len());
+        let battery = match header.has_battery() {
+            true => " (battery)",
+            false => "",
+        };
+        let console = header.console_type().name();
+        let timing = header.timing().name();
+        let trainer = match header.has_trainer() {
+            true => "Yes",
+            false => "No",
+        };
+        let crc32 = metadata.crc32;
+        let sha1 = metadata.sha1_hex();
+        let mirroring = cartridge.mirroring().name();
+
+        let mut description = Vec::new();
+        if let Some(name) = &metadata.name {
+            description.push(f!("Name: {name}"));
+        }
+        if let Some(publisher) = &metadata.publisher {
+            description.push(f!("Publisher: {publisher}"));
+        }
+        if let Some(board) = &metadata.board {
+            description.push(f!("Board: {board}"));
+        }
+        if let Some(disk_system) = &cartridge.disk_system {
+            let sides = disk_system.side_count();
+            let inserted = match disk_system.inserted_side() {
+                Some(side) => DiskImage::side_name(side),
+                None => "None".to_string(),
+            };
+            description.push(f!("Disk sides: {sides}"));
+            description.push(f!("Inserted: {inserted}"));
+        }
+        description.extend([
+            f!("Format: {format}"),
+            f!("Mapper: {mapper}, submapper {submapper}"),
+            f!("PRG-ROM: {prg_rom}KB"),
+            chr,
+            f!("PRG-RAM: {prg_ram}KB{battery}"),
+            f!("Mirroring: {mirroring}"),
+            f!("Console: {console}"),
+            f!("TV System: {timing}"),
+            f!("Trainer: {trainer}"),
+            f!("CRC32: {crc32:08X}"),
+            f!("SHA-1: {sha1}"),
+        ]);
+        description
+    }
+
     fn render_toolbar(&mut self, ctx: &Context, ui: &mut Ui) {
         ui.heading("Toolbar");
         ui.separator();
         if ui.button("Reset").clicked() {
             self.nes.reset();
             self.clock.reset();
+            self.rewind.clear();
         }
         if (ui.button("Open")).clicked() {
             let mut dialog = FileDialog::open_file(self.opened_file.clone());
@@ -233,35 +655,24 @@ impl Gui {
         if let Some(dialog) = &mut self.open_file_dialog {
             if dialog.show(ctx).selected() {
                 if let Some(file) = dialog.path() {
-                    self.opened_file = Some(file.clone());
-                    let file_contents = match std::fs::read(file) {
-                        Ok(contents) => contents,
-                        Err(e) => {
-                            eprintln!("Error reading file: {}", e);
-                            return;
-                        }
-                    };
-                    println!("File contents: {}", file_contents.len());
-
-                    let cartridge = Cartridge::try_from(file_contents)
-                        .map(|c| Rc::new(RefCell::new(c)))
-                        .expect("Failed to load cartridge");
-
-                    println!("Made cartridge");
-
-                    self.nes.insert_cartidge(Some(cartridge));
-                    self.nes.reset();
-                    assert!(self.nes.cartridge_ref().is_some());
+                    self.open_rom(file);
                 }
             }
         }
 
         if (ui.button("Eject")).clicked() {
-            self.nes.insert_cartidge(None);
-            self.nes.reset();
-            self.opened_file = None;
+            self.eject();
         }
 
+        self.disk_system_controls(ctx, ui);
+        self.save_state_controls(ui);
+        self.rewind_controls(ui);
+
+        ui.heading("Run-Ahead");
+        ui.add(egui::Slider::new(&mut self.run_ahead.frames, 0..=4).text("Frames"))
+            .on_hover_text("Has no visible effect until the PPU renders");
+        self.port_device_selection(ui);
+
         ui.heading("Playback Speed");
         let mut playback = self.playback_speed();
         ui.add(egui::widgets::Slider::new(&mut playback, 0.0_f64..=3.0_f64));
@@ -289,4 +700,210 @@ impl Gui {
             }
         });
     }
+
+    fn port_device_selection(&mut self, ui: &mut Ui) {
+        ui.heading("Controllers");
+        for port in 0..2 {
+            let current = self.nes.port_device_kind(port);
+            let mut selected = current;
+            let port_number = port + 1;
+            egui::ComboBox::from_label(f!("Port {port_number}"))
+                .selected_text(selected.name())
+                .show_ui(ui, |ui| {
+                    for kind in PortDeviceKind::ALL {
+                        ui.selectable_value(&mut selected, kind, kind.name());
+                    }
+                });
+
+            if selected != current {
+                self.nes.connect_port_device(port, selected);
+                if let Some(file) = &self.opened_file {
+                    let devices = self.port_devices.entry(file.clone()).or_default();
+                    devices[port] = selected;
+                }
+            }
+        }
+        if (0..2).any(|port| self.nes.port_device_kind(port) == PortDeviceKind::PowerPad) {
+            ui.label("Power Pad: Q W E R / A S D F / Z X C V");
+        }
+    }
+
+    /// BIOS selection, and disk side swapping while a Disk System game is
+    /// running
+    fn disk_system_controls(&mut self, ctx: &Context, ui: &mut Ui) {
+        ui.heading("Disk System");
+        let bios = match &self.disk_bios {
+            Some(path) => path.to_str().unwrap_or("").to_string(),
+            None => "disksys.rom".to_string(),
+        };
+        ui.label(f!("BIOS: {bios}"));
+        if ui.button("Choose BIOS").clicked() {
+            let mut dialog = FileDialog::open_file(self.disk_bios.clone());
+            dialog.open();
+            self.bios_file_dialog = Some(dialog);
+        }
+        if let Some(dialog) = &mut self.bios_file_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(file) = dialog.path() {
+                    self.disk_bios = Some(file);
+                }
+            }
+        }
+
+        let cartridge = match self.nes.cartridge_ref() {
+            Some(cartridge) => cartridge,
+            None => return,
+        };
+        let mut cartridge = cartridge.borrow_mut();
+        let disk_system = match &mut cartridge.disk_system {
+            Some(disk_system) => disk_system,
+            None => return,
+        };
+        let inserted = disk_system.inserted_side();
+        for side in 0..disk_system.side_count() {
+            let name = DiskImage::side_name(side);
+            if ui.selectable_label(inserted == Some(side), name).clicked() {
+                disk_system.swap_side(side);
+            }
+        }
+        if ui.button("Eject disk").clicked() {
+            disk_system.eject();
+        }
+    }
+
+    fn save_state_controls(&mut self, ui: &mut Ui) {
+        ui.heading("Save States");
+        let slot_number = self.selected_slot + 1;
+        ui.label(f!("Slot {slot_number} (F6/F7 to change)"));
+        ui.horizontal(|ui| {
+            if ui.button("Save (F5)").clicked() {
+                self.save_to_slot(self.selected_slot);
+            }
+            if ui.button("Load (F8)").clicked() {
+                self.load_from_slot(self.selected_slot);
+            }
+            if ui.button("Slots").clicked() {
+                self.show_slot_picker = true;
+            }
+        });
+    }
+
+    fn rewind_controls(&mut self, ui: &mut Ui) {
+        ui.heading("Rewind");
+        ui.checkbox(&mut self.rewind_enabled, "Enabled");
+        if !self.rewind_enabled {
+            self.rewind_held = false;
+            return;
+        }
+
+        self.rewind_held = ui
+            .button("Hold to rewind (Backspace)")
+            .is_pointer_button_down_on();
+
+        let mut config = self.rewind.config();
+        let mut budget_mb = config.memory_budget / (1024 * 1024);
+        ui.add(egui::Slider::new(&mut config.interval, 1..=60).text("Frames per snapshot"));
+        ui.add(egui::Slider::new(&mut budget_mb, 1..=1024).text("Memory budget (MB)"));
+        config.memory_budget = budget_mb * 1024 * 1024;
+        if config != self.rewind.config() {
+            self.rewind.set_config(config);
+        }
+
+        let frames = self.rewind.frames_available();
+        let used_kb = (self.rewind.memory_used() / 1024).separate_with_commas();
+        ui.label(f!("{frames} frames, {used_kb} KB"));
+    }
+
+    fn render_slot_picker(&mut self, ctx: &Context) {
+        let mut open = self.show_slot_picker;
+        let mut save_slot = None;
+        let mut load_slot = None;
+
+        egui::Window::new("Save States")
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let slots = match &self.save_slots {
+                    Some(slots) => slots,
+                    None => {
+                        ui.label("No ROM opened");
+                        return;
+                    }
+                };
+
+                for slot in 0..SaveSlots::SLOT_COUNT {
+                    ui.horizontal(|ui| {
+                        let slot_number = slot + 1;
+                        ui.selectable_value(&mut self.selected_slot, slot, f!("{slot_number:>2}"));
+
+                        match slots.info(slot) {
+                            Some(info) if info.thumbnail.is_empty() => {
+                                let [width, height] = Thumbnail::SIZE;
+                                ui.add_sized(
+                                    [width as f32, height as f32],
+                                    egui::Label::new("No PPU output yet"),
+                                );
+                                ui.label(format_timestamp(info.timestamp));
+                            }
+                            Some(info) => {
+                                let texture = self.slot_textures.entry(slot).or_insert_with(|| {
+                                    let thumbnail = &info.thumbnail;
+                                    ctx.load_texture(
+                                        f!("save-slot-{slot}"),
+                                        ColorImage::from_rgb(
+                                            [thumbnail.width, thumbnail.height],
+                                            &thumbnail.rgb,
+                                        ),
+                                        TextureOptions::NEAREST,
+                                    )
+                                });
+                                ui.image(texture.id(), texture.size_vec2());
+                                ui.label(format_timestamp(info.timestamp));
+                            }
+                            None => {
+                                ui.label("Empty");
+                            }
+                        }
+
+                        if ui.button("Save").clicked() {
+                            save_slot = Some(slot);
+                        }
+                        if ui
+                            .add_enabled(slots.info(slot).is_some(), egui::Button::new("Load"))
+                            .clicked()
+                        {
+                            load_slot = Some(slot);
+                        }
+                    });
+                }
+            });
+
+        self.show_slot_picker = open;
+        if let Some(slot) = save_slot {
+            self.save_to_slot(slot);
+        }
+        if let Some(slot) = load_slot {
+            self.load_from_slot(slot);
+        }
+    }
+
+    fn render_error(&mut self, ctx: &Context) {
+        let message = match &self.error_message {
+            Some(message) => message,
+            None => return,
+        };
+        let mut dismissed = false;
+        egui::Window::new("Error")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(message);
+                if ui.button("OK").clicked() {
+                    dismissed = true;
+                }
+            });
+        if dismissed {
+            self.error_message = None;
+        }
+    }
 }
@@ -1,4 +1,5 @@
 mod gui;
+mod save_slots;
 
 pub use gui::Gui;
-
+pub use save_slots::{format_timestamp, SaveSlots, SlotError, SlotInfo, Thumbnail};
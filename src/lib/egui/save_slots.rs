@@ -0,0 +1,303 @@
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::savestate::SaveStateError;
+use crate::{Nes, Ppu};
+
+/// # Save Slots
+/// Numbered save states for a single ROM, stored on disk under the settings
+/// directory.
+///
+/// Each slot is a file laid out as:
+///
+/// | Bytes   | Description                                      |
+/// |---------|--------------------------------------------------|
+/// | 0-3     | Constant `NSLT`                                  |
+/// | 4       | Slot format version, [`SaveSlots::VERSION`]      |
+/// | 5-12    | Time saved, in seconds since the Unix epoch      |
+/// | 13-14   | Thumbnail width                                  |
+/// | 15-16   | Thumbnail height                                 |
+/// | 17-     | Thumbnail, packed RGB                            |
+/// | ...     | Save state, see [`crate::savestate::StateWriter`]|
+pub struct SaveSlots {
+    directory: PathBuf,
+    slots: Vec<Option<SlotInfo>>,
+}
+
+/// Summary of a filled slot, for displaying in the slot picker
+pub struct SlotInfo {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub thumbnail: Thumbnail,
+}
+
+/// Shrunk copy of the frame at the time of saving. Empty when the PPU had
+/// not output anything yet.
+pub struct Thumbnail {
+    pub width: usize,
+    pub height: usize,
+    /// Packed RGB
+    pub rgb: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum SlotError {
+    Empty(usize),
+    /// Slot number is not below [`SaveSlots::SLOT_COUNT`]
+    OutOfRange(usize),
+    Io(io::Error),
+    Corrupt,
+    /// Slot file is from a different version of the slot format
+    UnsupportedVersion(u8),
+    State(SaveStateError),
+}
+
+impl Display for SlotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SlotError::Empty(slot) => write!(f, "Slot {} is empty", slot + 1),
+            SlotError::OutOfRange(slot) => write!(
+                f,
+                "There is no slot {}, only {}",
+                slot + 1,
+                SaveSlots::SLOT_COUNT
+            ),
+            SlotError::Io(e) => write!(f, "Could not access save slot: {}", e),
+            SlotError::Corrupt => write!(f, "Save slot file is corrupt"),
+            SlotError::UnsupportedVersion(version) => write!(
+                f,
+                "Save slot is from version {}, but only version {} is supported",
+                version,
+                SaveSlots::VERSION
+            ),
+            SlotError::State(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SlotError {}
+
+impl From<io::Error> for SlotError {
+    fn from(e: io::Error) -> Self {
+        SlotError::Io(e)
+    }
+}
+
+impl From<SaveStateError> for SlotError {
+    fn from(e: SaveStateError) -> Self {
+        SlotError::State(e)
+    }
+}
+
+impl SaveSlots {
+    pub const SLOT_COUNT: usize = 10;
+    /// Version of the slot file layout, bumped whenever it changes
+    pub const VERSION: u8 = 1;
+    const MAGIC: [u8; 4] = *b"NSLT";
+    const HEADER_SIZE: usize = 17;
+    /// Thumbnails are the frame, shrunk by this factor in each direction
+    const THUMBNAIL_SCALE: usize = 4;
+
+    /// Slots for the ROM at `rom_path`. The ROM's CRC-32 is part of the
+    /// directory name, so different dumps with the same file name do not
+    /// share slots.
+    pub fn for_rom(rom_path: &Path, rom_crc: u32) -> Self {
+        let stem = rom_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::in_directory(
+            settings_directory()
+                .join("states")
+                .join(format!("{}-{:08X}", stem, rom_crc)),
+        )
+    }
+
+    /// Slots stored in `directory`, which is created on the first save
+    pub fn in_directory(directory: PathBuf) -> Self {
+        let mut slots = Self {
+            directory,
+            slots: Vec::new(),
+        };
+        slots.refresh();
+        slots
+    }
+
+    /// Re-read the summary of every slot from disk
+    pub fn refresh(&mut self) {
+        self.slots = (0..Self::SLOT_COUNT)
+            .map(|slot| self.read_info(slot).ok())
+            .collect();
+    }
+
+    pub fn info(&self, slot: usize) -> Option<&SlotInfo> {
+        self.slots.get(slot).and_then(|info| info.as_ref())
+    }
+
+    pub fn save(&mut self, slot: usize, nes: &Nes) -> Result<(), SlotError> {
+        if slot >= Self::SLOT_COUNT {
+            return Err(SlotError::OutOfRange(slot));
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let thumbnail = Thumbnail::from_frame(nes.ppu.borrow().frame());
+
+        let mut contents = Vec::new();
+        contents.extend_from_slice(&Self::MAGIC);
+        contents.push(Self::VERSION);
+        contents.extend_from_slice(&timestamp.to_le_bytes());
+        contents.extend_from_slice(&(thumbnail.width as u16).to_le_bytes());
+        contents.extend_from_slice(&(thumbnail.height as u16).to_le_bytes());
+        contents.extend_from_slice(&thumbnail.rgb);
+        contents.extend(nes.save_state());
+
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.slot_path(slot), contents)?;
+
+        self.slots[slot] = Some(SlotInfo {
+            timestamp,
+            thumbnail,
+        });
+        Ok(())
+    }
+
+    /// Restore the state in `slot`. If an error is returned after the state
+    /// has started to be restored, the machine should be reset.
+    pub fn load(&self, slot: usize, nes: &mut Nes) -> Result<(), SlotError> {
+        if slot >= Self::SLOT_COUNT {
+            return Err(SlotError::OutOfRange(slot));
+        }
+        let contents = match std::fs::read(self.slot_path(slot)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(SlotError::Empty(slot)),
+            Err(e) => return Err(e.into()),
+        };
+        let (_, state) = Self::split(&contents)?;
+        nes.load_state(state)?;
+        Ok(())
+    }
+
+    fn read_info(&self, slot: usize) -> Result<SlotInfo, SlotError> {
+        let contents = std::fs::read(self.slot_path(slot))?;
+        let (info, _) = Self::split(&contents)?;
+        Ok(info)
+    }
+
+    /// Split a slot file into its summary and the save state
+    fn split(contents: &[u8]) -> Result<(SlotInfo, &[u8]), SlotError> {
+        if contents.len() < Self::HEADER_SIZE || contents[0..4] != Self::MAGIC {
+            return Err(SlotError::Corrupt);
+        }
+        if contents[4] != Self::VERSION {
+            return Err(SlotError::UnsupportedVersion(contents[4]));
+        }
+        let timestamp = u64::from_le_bytes(contents[5..13].try_into().expect("Checked Length"));
+        let width = u16::from_le_bytes([contents[13], contents[14]]) as usize;
+        let height = u16::from_le_bytes([contents[15], contents[16]]) as usize;
+
+        let thumbnail_end = Self::HEADER_SIZE + width * height * 3;
+        if contents.len() < thumbnail_end {
+            return Err(SlotError::Corrupt);
+        }
+        let thumbnail = Thumbnail {
+            width,
+            height,
+            rgb: contents[Self::HEADER_SIZE..thumbnail_end].to_vec(),
+        };
+
+        Ok((
+            SlotInfo {
+                timestamp,
+                thumbnail,
+            },
+            &contents[thumbnail_end..],
+        ))
+    }
+
+    fn slot_path(&self, slot: usize) -> PathBuf {
+        self.directory.join(format!("slot-{}.state", slot + 1))
+    }
+}
+
+impl Thumbnail {
+    /// Width and height of a thumbnail that isn't empty
+    pub const SIZE: [usize; 2] = [
+        Ppu::FRAME_WIDTH / SaveSlots::THUMBNAIL_SCALE,
+        Ppu::FRAME_HEIGHT / SaveSlots::THUMBNAIL_SCALE,
+    ];
+
+    /// Shrink a frame of palette indices, as output by [`Ppu::frame`]
+    pub fn from_frame(frame: &[u8]) -> Self {
+        // Until the PPU draws, its frame is a single colour. Store no picture
+        // rather than a solid block of the backdrop colour.
+        if frame.iter().all(|&colour| colour == frame[0]) {
+            return Self {
+                width: 0,
+                height: 0,
+                rgb: Vec::new(),
+            };
+        }
+
+        let scale = SaveSlots::THUMBNAIL_SCALE;
+        let [width, height] = Self::SIZE;
+        let rgb = (0..height)
+            .flat_map(|y| (0..width).map(move |x| frame[y * scale * Ppu::FRAME_WIDTH + x * scale]))
+            .flat_map(|colour| Ppu::PALETTE[(colour & 0x3F) as usize])
+            .collect();
+
+        Self { width, height, rgb }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// Directory holding the emulator's settings and save data.
+///
+/// `$XDG_CONFIG_HOME/rust-nes` or `~/.config/rust-nes` on unix-likes and
+/// `%APPDATA%\rust-nes` on Windows. Falls back to the working directory.
+pub fn settings_directory() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("rust-nes")
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Convert days since the epoch to a civil date
+    // <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = match month_index < 10 {
+        true => month_index + 3,
+        false => month_index - 9,
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
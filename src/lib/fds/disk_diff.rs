@@ -0,0 +1,141 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Cartridge;
+
+use super::SIDE_SIZE;
+
+/// # Disk Diff
+/// Keeps what a Disk System game has written to its disks in a `.fdsdiff`
+/// file next to the image, so the image itself is never modified.
+///
+/// ## Format
+/// `FDSD`, followed by records of a little endian `u32` offset, `u32`
+/// length and that many bytes, to be copied over the sides of the disk
+/// at that offset. Offsets are into the side data of the image, without
+/// the fwNES header, so they are the same whether or not the image has one.
+pub struct DiskDiff {
+    path: PathBuf,
+    /// Sides as they were in the image
+    original: Vec<u8>,
+    /// Sides when last loaded or written, to skip writing the file when
+    /// nothing has changed
+    flushed: Vec<u8>,
+}
+
+impl DiskDiff {
+    const MAGIC: [u8; 4] = *b"FDSD";
+    /// Changes closer together than this are merged into one record
+    const MERGE_DISTANCE: usize = 8;
+
+    /// `.fdsdiff` file for the image at `rom_path`, e.g. `zelda.fds` is
+    /// saved to `zelda.fdsdiff`
+    pub fn for_rom(rom_path: &Path) -> Self {
+        Self {
+            path: rom_path.with_extension("fdsdiff"),
+            original: Vec::new(),
+            flushed: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Apply the `.fdsdiff` file to the cartridge's disks. Not having a
+    /// file yet is not an error; the disks are left as they are.
+    pub fn load(&mut self, cartridge: &mut Cartridge) -> io::Result<()> {
+        let disk_system = match &mut cartridge.disk_system {
+            Some(disk_system) => disk_system,
+            None => return Ok(()),
+        };
+        self.original = disk_system.sides().concat();
+        self.flushed = self.original.clone();
+
+        let diff = match std::fs::read(&self.path) {
+            Ok(diff) => diff,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut data = self.original.clone();
+        Self::apply(&diff, &mut data)?;
+        let sides: Vec<Vec<u8>> = data.chunks(SIDE_SIZE).map(|side| side.to_vec()).collect();
+        disk_system.load_sides(&sides);
+        self.flushed = data;
+        Ok(())
+    }
+
+    /// Write the changes made to the cartridge's disks to the `.fdsdiff`
+    /// file, if there are any new ones since it was last loaded or written.
+    pub fn flush(&mut self, cartridge: &Cartridge) -> io::Result<()> {
+        let data = match &cartridge.disk_system {
+            Some(disk_system) => disk_system.sides().concat(),
+            None => return Ok(()),
+        };
+        if data == self.flushed {
+            return Ok(());
+        }
+        std::fs::write(&self.path, Self::diff(&self.original, &data))?;
+        self.flushed = data;
+        Ok(())
+    }
+
+    /// Records turning `original` into `modified`, which are the same length
+    pub fn diff(original: &[u8], modified: &[u8]) -> Vec<u8> {
+        let mut diff = Self::MAGIC.to_vec();
+        let mut offset = 0;
+        while offset < modified.len() {
+            if original.get(offset) == Some(&modified[offset]) {
+                offset += 1;
+                continue;
+            }
+            let start = offset;
+            let mut end = offset + 1;
+            // Extend the record until there is a long enough run of
+            // unchanged bytes
+            while end < modified.len() {
+                let unchanged = (end..modified.len().min(end + Self::MERGE_DISTANCE))
+                    .all(|index| original.get(index) == Some(&modified[index]));
+                if unchanged {
+                    break;
+                }
+                end += 1;
+            }
+            diff.extend((start as u32).to_le_bytes());
+            diff.extend(((end - start) as u32).to_le_bytes());
+            diff.extend(&modified[start..end]);
+            offset = end;
+        }
+        diff
+    }
+
+    /// Copy the records in `diff` over `data`
+    pub fn apply(diff: &[u8], data: &mut [u8]) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut records = match diff.strip_prefix(&Self::MAGIC) {
+            Some(records) => records,
+            None => return Err(invalid("not an FDS disk diff")),
+        };
+        while !records.is_empty() {
+            let (offset, length, rest) = match records {
+                [o0, o1, o2, o3, l0, l1, l2, l3, rest @ ..] => (
+                    u32::from_le_bytes([*o0, *o1, *o2, *o3]) as usize,
+                    u32::from_le_bytes([*l0, *l1, *l2, *l3]) as usize,
+                    rest,
+                ),
+                _ => return Err(invalid("disk diff record is cut off")),
+            };
+            let bytes = rest
+                .get(..length)
+                .ok_or_else(|| invalid("disk diff record is cut off"))?;
+            let target = offset
+                .checked_add(length)
+                .and_then(|end| data.get_mut(offset..end))
+                .ok_or_else(|| invalid("disk diff record is outside the disk"))?;
+            target.copy_from_slice(bytes);
+            records = &rest[length..];
+        }
+        Ok(())
+    }
+}
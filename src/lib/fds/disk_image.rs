@@ -0,0 +1,194 @@
+use log::debug;
+
+use crate::cartridge::{take_section, CartridgeParseError};
+
+/// Size of one side of a disk in a `.fds` file, without gaps or CRCs
+pub const SIDE_SIZE: usize = 65500;
+/// Optional 16 byte header added by fwNES
+const HEADER_SIGNATURE: [u8; 4] = *b"FDS\x1A";
+const HEADER_SIZE: usize = 16;
+/// Every side starts with a disk info block holding this string
+const DISK_VERIFICATION: &[u8] = b"\x01*NINTENDO-HVC*";
+
+/// Gap before the first block of a side, in bytes (28300 bits)
+const LEAD_IN_GAP: usize = 28300 / 8;
+/// Gap after each block, in bytes (976 bits)
+const BLOCK_GAP: usize = 976 / 8;
+/// Marks the end of a gap and the start of a block
+const BLOCK_START_MARK: u8 = 0x80;
+/// CRC written after each block. The drive doesn't check what it reads
+/// back, so images are given a fixed placeholder.
+const PLACEHOLDER_CRC: [u8; 2] = [0x4D, 0x62];
+/// Blank space at the end of each side as the drive sees it, so games
+/// can add files after the last block
+const TRAILING_SPACE: usize = 8 * 1024;
+
+/// # FDS Disk Image
+/// Sides of the disks of a Famicom Disk System game, as stored in a `.fds`
+/// file.
+///
+/// ## Format
+/// [Wiki Source](https://www.nesdev.org/wiki/FDS_file_format)
+///
+/// An optional 16 byte fwNES header (`FDS\x1A`, the number of sides and
+/// padding) followed by each side, 65500 bytes apiece. A side holds only
+/// the data of its blocks: the gaps, start marks and CRCs the drive reads
+/// are left out, see [`DiskImage::drive_side`].
+///
+/// | Block | Size | Contents |
+/// |-------|--------------|----------------------------------------------|
+/// | 1 | 56 | Disk info, starting `*NINTENDO-HVC*` |
+/// | 2 | 2 | Number of files |
+/// | 3 | 16 | File header, with the size of the file |
+/// | 4 | 1 + size | File data |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskImage {
+    pub sides: Vec<Vec<u8>>,
+    /// The file had a fwNES header, so one is written back out
+    pub has_header: bool,
+}
+
+impl DiskImage {
+    /// Returns true if `bytestream` looks like a `.fds` file, with or
+    /// without a header
+    pub fn is_disk_image(bytestream: &[u8]) -> bool {
+        bytestream.starts_with(&HEADER_SIGNATURE) || bytestream.starts_with(DISK_VERIFICATION)
+    }
+
+    /// All sides, one after the other, as in a headerless `.fds` file
+    pub fn side_data(&self) -> Vec<u8> {
+        self.sides.concat()
+    }
+
+    /// The image as a `.fds` file, with a header if it was loaded with one
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.sides.len() * SIDE_SIZE);
+        if self.has_header {
+            bytes.extend(HEADER_SIGNATURE);
+            bytes.push(self.sides.len() as u8);
+            bytes.extend([0; HEADER_SIZE - 5]);
+        }
+        bytes.extend(self.side_data());
+        bytes
+    }
+
+    /// Name of a side as printed on the disk label, e.g. `Disk 1 Side B`
+    pub fn side_name(side: usize) -> String {
+        let letter = match side % 2 {
+            0 => 'A',
+            _ => 'B',
+        };
+        format!("Disk {} Side {}", side / 2 + 1, letter)
+    }
+
+    /// A side as the drive reads it: a lead-in gap, then each block
+    /// preceded by a start mark and followed by its CRC and a gap.
+    ///
+    /// Parsing stops at the first byte that isn't a valid block type, as
+    /// the rest of the side is unused.
+    pub fn drive_side(side: &[u8]) -> Vec<u8> {
+        let mut drive_side = vec![0; LEAD_IN_GAP];
+        let mut position = 0;
+        let mut file_size = 0;
+        while let Some(length) = Self::block_length(side, position, file_size) {
+            let block = match side.get(position..position + length) {
+                Some(block) => block,
+                None => break,
+            };
+            if block[0] == 3 {
+                file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+            }
+            drive_side.push(BLOCK_START_MARK);
+            drive_side.extend(block);
+            drive_side.extend(PLACEHOLDER_CRC);
+            drive_side.extend([0; BLOCK_GAP]);
+            position += length;
+        }
+        let size = drive_side.len().max(LEAD_IN_GAP + SIDE_SIZE) + TRAILING_SPACE;
+        drive_side.resize(size, 0);
+        drive_side
+    }
+
+    /// Undo [`DiskImage::drive_side`], picking the blocks back out of a side
+    /// the drive may have written to
+    pub fn side_from_drive(drive_side: &[u8]) -> Vec<u8> {
+        let mut side = Vec::with_capacity(SIDE_SIZE);
+        let mut position = 0;
+        let mut gap_needed = LEAD_IN_GAP;
+        let mut file_size = 0;
+        while position < drive_side.len() {
+            // Skip the gap up to the next start mark
+            if drive_side[position] != BLOCK_START_MARK || gap_needed > 0 {
+                gap_needed = gap_needed.saturating_sub(1);
+                position += 1;
+                continue;
+            }
+            position += 1;
+
+            let length = match Self::block_length(drive_side, position, file_size) {
+                Some(length) => length,
+                None => continue,
+            };
+            let block = match drive_side.get(position..position + length) {
+                Some(block) => block,
+                None => break,
+            };
+            if block[0] == 3 {
+                file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+            }
+            side.extend(block);
+            position += length + PLACEHOLDER_CRC.len();
+            gap_needed = BLOCK_GAP - 1;
+        }
+        side.resize(SIDE_SIZE, 0);
+        side
+    }
+
+    /// Length of the block at `position`, from its type byte. File data
+    /// blocks take their size from the file header before them.
+    fn block_length(side: &[u8], position: usize, file_size: usize) -> Option<usize> {
+        match side.get(position)? {
+            1 => Some(56),
+            2 => Some(2),
+            3 => Some(16),
+            4 => Some(1 + file_size),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for DiskImage {
+    type Error = CartridgeParseError;
+
+    fn try_from(bytestream: &[u8]) -> Result<Self, Self::Error> {
+        use CartridgeParseError::*;
+
+        let file_size = bytestream.len();
+        let (has_header, side_count, mut bytestream) =
+            match bytestream.starts_with(&HEADER_SIGNATURE) {
+                true => match bytestream.get(HEADER_SIZE..) {
+                    Some(sides) => (true, bytestream[4] as usize, sides),
+                    None => return Err(TooShort(file_size)),
+                },
+                // A short headerless file is a cut off side
+                false => (false, (file_size / SIDE_SIZE).max(1), bytestream),
+            };
+        debug!("FDS header: {}, sides: {}", has_header, side_count);
+        if side_count == 0 {
+            return Err(NoDiskSides);
+        }
+
+        let mut sides = Vec::with_capacity(side_count);
+        for index in 0..side_count {
+            let (side, rest) =
+                take_section(bytestream, SIDE_SIZE, file_size).map_err(DiskSideCutsOff)?;
+            if !side.starts_with(DISK_VERIFICATION) {
+                return Err(NotADiskSide(index));
+            }
+            sides.push(side.to_vec());
+            bytestream = rest;
+        }
+
+        Ok(Self { sides, has_header })
+    }
+}
@@ -0,0 +1,545 @@
+use log::debug;
+
+use crate::{
+    cartridge::{Header, Mirroring},
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+};
+
+use super::DiskImage;
+
+/// # Famicom Disk System
+/// The RAM adapter plugged into the cartridge slot, and the disk drive
+/// connected to it.
+///
+/// <https://www.nesdev.org/wiki/Family_Computer_Disk_System>
+///
+/// ## Memory Layout - CPU
+///
+/// | Range           | Size | Description                                   |
+/// |-----------------|------|-----------------------------------------------|
+/// | 0x4020 - 0x4026 | 7B   | Write registers: IRQ timer, I/O, disk control |
+/// | 0x4030 - 0x4033 | 4B   | Read registers: status and disk data          |
+/// | 0x6000 - 0xDFFF | 32KB | PRG-RAM, programs are loaded here from disk   |
+/// | 0xE000 - 0xFFFF | 8KB  | BIOS ROM (`disksys.rom`)                      |
+///
+/// The PPU sees 8KB of CHR-RAM, and mirroring is set through `$4025`.
+///
+/// ## Registers
+///
+/// | Address | Access | Description                                        |
+/// |---------|--------|----------------------------------------------------|
+/// | 0x4020  | W      | IRQ timer reload, low byte                         |
+/// | 0x4021  | W      | IRQ timer reload, high byte                        |
+/// | 0x4022  | W      | IRQ timer control: `.... ..ER` enable, repeat      |
+/// | 0x4023  | W      | Master I/O enable: `.... ..SD` sound, disk         |
+/// | 0x4024  | W      | Data to write to disk                              |
+/// | 0x4025  | W      | Disk control: `IS.CMRTM`, see [`DiskSystem::write_register`] |
+/// | 0x4026  | W      | External connector output                          |
+/// | 0x4030  | R      | Status: `.......TD` byte transferred, timer IRQ    |
+/// | 0x4031  | R      | Data read from disk                                |
+/// | 0x4032  | R      | Drive status: `.....PRS` protected, not ready, no disk |
+/// | 0x4033  | R      | External connector input, bit 7 is battery good    |
+///
+/// The sound registers at `$4040` - `$4097` are not emulated.
+///
+/// ## Drive
+/// The drive streams one byte of the side under its head every
+/// [`DiskSystem::BYTE_CYCLES`] CPU cycles while the motor is on, raising an
+/// IRQ for each if asked to. When the head reaches the end of the side the
+/// motor stops, and the head takes [`DiskSystem::HEAD_RETURN_CYCLES`] to
+/// get back to the start.
+pub struct DiskSystem {
+    /// Each side as the drive sees it, see [`DiskImage::drive_side`]
+    sides: Vec<Vec<u8>>,
+    inserted_side: Option<usize>,
+    /// Side waiting to go in once the previous one has been out long
+    /// enough for the BIOS to notice
+    pending_side: Option<usize>,
+    insert_delay: u32,
+
+    irq_reload: u16,
+    irq_counter: u16,
+    irq_repeat: bool,
+    irq_enabled: bool,
+    timer_irq: bool,
+    disk_registers_enabled: bool,
+    sound_registers_enabled: bool,
+    external_output: u8,
+
+    // $4025
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    horizontal_mirroring: bool,
+    crc_control: bool,
+    transfer_enabled: bool,
+    disk_irq_enabled: bool,
+
+    read_data: u8,
+    write_data: u8,
+    /// A byte has been read or written since the last acknowledge
+    transfer_complete: bool,
+    disk_irq: bool,
+
+    /// Byte of the side under the head
+    position: usize,
+    /// Cycles until the next byte reaches the head
+    delay: u32,
+    end_of_head: bool,
+    /// The head is moving over the disk and can transfer data
+    scanning: bool,
+    /// A start mark has been found since reading was enabled
+    gap_ended: bool,
+    crc: u16,
+    previous_crc_control: bool,
+}
+
+impl DiskSystem {
+    pub const BIOS_SIZE: usize = 8 * 1024;
+    /// CPU cycles per byte, about 96.4 kbit/s
+    pub const BYTE_CYCLES: u32 = 150;
+    /// CPU cycles for the head to return to the start of the side
+    pub const HEAD_RETURN_CYCLES: u32 = 50_000;
+    /// CPU cycles a side is kept out of the drive when swapping, about a
+    /// second, so the BIOS sees the disk being ejected
+    pub const SWAP_CYCLES: u32 = 1_789_773;
+
+    const PRG_RAM_SIZE: usize = 32 * 1024;
+    const CHR_RAM_SIZE: usize = 8 * 1024;
+    /// Reserved for the Disk System in iNES
+    const MAPPER_ID: u16 = 20;
+
+    pub fn new(image: &DiskImage) -> Self {
+        Self {
+            sides: image
+                .sides
+                .iter()
+                .map(|side| DiskImage::drive_side(side))
+                .collect(),
+            inserted_side: Some(0),
+            pending_side: None,
+            insert_delay: 0,
+            irq_reload: 0,
+            irq_counter: 0,
+            irq_repeat: false,
+            irq_enabled: false,
+            timer_irq: false,
+            disk_registers_enabled: false,
+            sound_registers_enabled: false,
+            external_output: 0,
+            motor_on: false,
+            reset_transfer: false,
+            read_mode: true,
+            horizontal_mirroring: false,
+            crc_control: false,
+            transfer_enabled: false,
+            disk_irq_enabled: false,
+            read_data: 0,
+            write_data: 0,
+            transfer_complete: false,
+            disk_irq: false,
+            position: 0,
+            delay: 0,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            crc: 0,
+            previous_crc_control: false,
+        }
+    }
+
+    /// NES 2.0 header describing the RAM adapter, for the parts of the
+    /// emulator that look at the cartridge's header
+    pub fn header() -> Header {
+        let mut header = Header::nes_two();
+        header.set_mapper(Self::MAPPER_ID, 0);
+        header.set_prg_rom_bytes(Self::BIOS_SIZE);
+        header.set_prg_ram_bytes(Self::PRG_RAM_SIZE, 0);
+        header.set_chr_ram_bytes(Self::CHR_RAM_SIZE, 0);
+        header.set_mirroring(Mirroring::Vertical);
+        header
+    }
+
+    /// Put the adapter in its power-on state, with the first side inserted.
+    /// What has been written to the disks is kept.
+    pub fn power_on(&mut self) {
+        let sides = std::mem::take(&mut self.sides);
+        *self = Self {
+            sides,
+            ..Self::new(&DiskImage {
+                sides: Vec::new(),
+                has_header: false,
+            })
+        };
+    }
+
+    pub fn side_count(&self) -> usize {
+        self.sides.len()
+    }
+
+    /// Side in the drive, `None` while there is no disk inserted
+    pub fn inserted_side(&self) -> Option<usize> {
+        self.inserted_side
+    }
+
+    /// Take the disk out of the drive
+    pub fn eject(&mut self) {
+        self.inserted_side = None;
+        self.pending_side = None;
+    }
+
+    /// Eject the current side and insert `side` once the BIOS has had time
+    /// to notice, as swapping a disk by hand would
+    pub fn swap_side(&mut self, side: usize) {
+        if side >= self.sides.len() {
+            return;
+        }
+        debug!("Swapping to {}", DiskImage::side_name(side));
+        self.inserted_side = None;
+        self.pending_side = Some(side);
+        self.insert_delay = Self::SWAP_CYCLES;
+    }
+
+    /// Contents of each side in `.fds` form, including anything the game
+    /// has written
+    pub fn sides(&self) -> Vec<Vec<u8>> {
+        self.sides
+            .iter()
+            .map(|side| DiskImage::side_from_drive(side))
+            .collect()
+    }
+
+    /// Replace the contents of the disks, e.g. with a copy that has saved
+    /// game data on it. Sides beyond the ones the game has are ignored.
+    pub fn load_sides(&mut self, sides: &[Vec<u8>]) {
+        for (drive_side, side) in self.sides.iter_mut().zip(sides) {
+            *drive_side = DiskImage::drive_side(side);
+        }
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        match self.horizontal_mirroring {
+            true => Mirroring::Horizontal,
+            false => Mirroring::Vertical,
+        }
+    }
+
+    /// The timer or the drive is asserting the IRQ line
+    pub fn irq_pending(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    /// Returns `None` for addresses that aren't readable registers.
+    /// Reading `$4030` acknowledges the IRQs, and reading `$4031` acknowledges
+    /// the transfer.
+    pub fn read_register(&mut self, address: u16) -> Option<u8> {
+        let data = self.peek_register(address)?;
+        match address {
+            0x4030 => {
+                self.transfer_complete = false;
+                self.timer_irq = false;
+                self.disk_irq = false;
+            }
+            0x4031 => {
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            _ => {}
+        }
+        Some(data)
+    }
+
+    /// Like [`DiskSystem::read_register`], without acknowledging anything
+    pub fn peek_register(&self, address: u16) -> Option<u8> {
+        if !self.disk_registers_enabled {
+            return None;
+        }
+        let data = match address {
+            0x4030 => (self.timer_irq as u8) | ((self.transfer_complete as u8) << 1),
+            0x4031 => self.read_data,
+            0x4032 => {
+                let inserted = self.inserted_side.is_some();
+                (!inserted as u8)
+                    | ((!(inserted && self.scanning) as u8) << 1)
+                    | ((!inserted as u8) << 2)
+            }
+            // Battery good
+            0x4033 => 0x80,
+            _ => return None,
+        };
+        Some(data)
+    }
+
+    /// Writes to addresses that aren't registers are ignored
+    ///
+    /// `$4025`, disk control:
+    ///
+    /// | Bit | Description                                          |
+    /// |-----|------------------------------------------------------|
+    /// | 0   | Motor on                                             |
+    /// | 1   | Reset transfer: hold the head at the start           |
+    /// | 2   | Read mode (1) or write mode (0)                      |
+    /// | 3   | Horizontal (1) or vertical (0) mirroring             |
+    /// | 4   | Write the CRC instead of `$4024`                     |
+    /// | 6   | Transfer enabled, cleared to wait for a start mark   |
+    /// | 7   | IRQ on every byte transferred                        |
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x4020 => self.irq_reload = (self.irq_reload & 0xFF00) | data as u16,
+            0x4021 => self.irq_reload = (self.irq_reload & 0x00FF) | ((data as u16) << 8),
+            0x4022 => {
+                self.irq_repeat = data & 0b01 != 0;
+                self.irq_enabled = data & 0b10 != 0 && self.disk_registers_enabled;
+                match self.irq_enabled {
+                    true => self.irq_counter = self.irq_reload,
+                    false => self.timer_irq = false,
+                }
+            }
+            0x4023 => {
+                self.disk_registers_enabled = data & 0b01 != 0;
+                self.sound_registers_enabled = data & 0b10 != 0;
+                if !self.disk_registers_enabled {
+                    self.irq_enabled = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            }
+            _ if !self.disk_registers_enabled => {}
+            0x4024 => {
+                self.write_data = data;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4025 => {
+                self.motor_on = data & 0b0000_0001 != 0;
+                self.reset_transfer = data & 0b0000_0010 != 0;
+                self.read_mode = data & 0b0000_0100 != 0;
+                self.horizontal_mirroring = data & 0b0000_1000 != 0;
+                self.crc_control = data & 0b0001_0000 != 0;
+                self.transfer_enabled = data & 0b0100_0000 != 0;
+                self.disk_irq_enabled = data & 0b1000_0000 != 0;
+                self.disk_irq = false;
+            }
+            0x4026 => self.external_output = data,
+            _ => {}
+        }
+    }
+
+    /// Advance by one CPU cycle
+    pub fn tick(&mut self) {
+        self.tick_timer();
+        if self.pending_side.is_some() {
+            match self.insert_delay {
+                0 => self.inserted_side = self.pending_side.take(),
+                _ => self.insert_delay -= 1,
+            }
+        }
+        self.tick_drive();
+    }
+
+    fn tick_timer(&mut self) {
+        if !self.irq_enabled {
+            return;
+        }
+        match self.irq_counter {
+            0 => {
+                self.timer_irq = true;
+                self.irq_counter = self.irq_reload;
+                self.irq_enabled = self.irq_repeat;
+            }
+            _ => self.irq_counter -= 1,
+        }
+    }
+
+    fn tick_drive(&mut self) {
+        let side = match self.inserted_side {
+            Some(side) if self.motor_on => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning = false;
+                return;
+            }
+        };
+        if self.reset_transfer && !self.scanning {
+            return;
+        }
+        if self.end_of_head || self.position >= self.sides[side].len() {
+            self.delay = Self::HEAD_RETURN_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        match self.read_mode {
+            true => self.read_byte(side),
+            false => self.write_byte(side),
+        }
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        match self.position >= self.sides[side].len() {
+            true => self.motor_on = false,
+            false => self.delay = Self::BYTE_CYCLES,
+        }
+    }
+
+    fn read_byte(&mut self, side: usize) {
+        let data = self.sides[side][self.position];
+        if !self.previous_crc_control {
+            self.update_crc(data);
+        }
+        let mut irq = self.disk_irq_enabled;
+        if !self.transfer_enabled {
+            self.gap_ended = false;
+            self.crc = 0;
+        } else if data != 0 && !self.gap_ended {
+            // The start mark itself isn't handed to the CPU
+            self.gap_ended = true;
+            irq = false;
+        }
+        if self.gap_ended {
+            self.transfer_complete = true;
+            self.read_data = data;
+            self.disk_irq |= irq;
+        }
+    }
+
+    fn write_byte(&mut self, side: usize) {
+        let mut data = 0;
+        if !self.crc_control {
+            self.transfer_complete = true;
+            data = self.write_data;
+            self.disk_irq |= self.disk_irq_enabled;
+        }
+        if !self.transfer_enabled {
+            data = 0;
+        }
+        match self.crc_control {
+            false => self.update_crc(data),
+            true => {
+                if !self.previous_crc_control {
+                    // Finish the CRC before writing it out
+                    self.update_crc(0);
+                    self.update_crc(0);
+                }
+                data = self.crc as u8;
+                self.crc >>= 8;
+            }
+        }
+        self.sides[side][self.position] = data;
+        self.gap_ended = false;
+    }
+
+    /// CRC-16/KERMIT, which is what the drive writes after each block
+    fn update_crc(&mut self, data: u8) {
+        for bit in 0..8 {
+            let carry = self.crc & 1 != 0;
+            self.crc >>= 1;
+            if carry {
+                self.crc ^= 0x8408;
+            }
+            if data & (1 << bit) != 0 {
+                self.crc ^= 0x8000;
+            }
+        }
+    }
+}
+
+/// The disks are part of the state, as games write to them while running
+impl SaveState for DiskSystem {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.sides.len() as u8);
+        for side in self.sides.iter() {
+            state.write_vec(side);
+        }
+        state.write_u8(self.inserted_side.map_or(u8::MAX, |side| side as u8));
+        state.write_u8(self.pending_side.map_or(u8::MAX, |side| side as u8));
+        state.write_u32(self.insert_delay);
+
+        state.write_u16(self.irq_reload);
+        state.write_u16(self.irq_counter);
+        state.write_bool(self.irq_repeat);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.timer_irq);
+        state.write_bool(self.disk_registers_enabled);
+        state.write_bool(self.sound_registers_enabled);
+        state.write_u8(self.external_output);
+
+        state.write_bool(self.motor_on);
+        state.write_bool(self.reset_transfer);
+        state.write_bool(self.read_mode);
+        state.write_bool(self.horizontal_mirroring);
+        state.write_bool(self.crc_control);
+        state.write_bool(self.transfer_enabled);
+        state.write_bool(self.disk_irq_enabled);
+
+        state.write_u8(self.read_data);
+        state.write_u8(self.write_data);
+        state.write_bool(self.transfer_complete);
+        state.write_bool(self.disk_irq);
+
+        state.write_u32(self.position as u32);
+        state.write_u32(self.delay);
+        state.write_bool(self.end_of_head);
+        state.write_bool(self.scanning);
+        state.write_bool(self.gap_ended);
+        state.write_u16(self.crc);
+        state.write_bool(self.previous_crc_control);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        let side_count = state.read_u8()? as usize;
+        if side_count != self.sides.len() {
+            return Err(SaveStateError::InvalidValue("disk side count"));
+        }
+        for side in self.sides.iter_mut() {
+            state.read_vec_into(side, "disk side size")?;
+        }
+        let read_side = |side: u8| match side {
+            u8::MAX => Ok(None),
+            side if (side as usize) < side_count => Ok(Some(side as usize)),
+            _ => Err(SaveStateError::InvalidValue("disk side")),
+        };
+        self.inserted_side = read_side(state.read_u8()?)?;
+        self.pending_side = read_side(state.read_u8()?)?;
+        self.insert_delay = state.read_u32()?;
+
+        self.irq_reload = state.read_u16()?;
+        self.irq_counter = state.read_u16()?;
+        self.irq_repeat = state.read_bool()?;
+        self.irq_enabled = state.read_bool()?;
+        self.timer_irq = state.read_bool()?;
+        self.disk_registers_enabled = state.read_bool()?;
+        self.sound_registers_enabled = state.read_bool()?;
+        self.external_output = state.read_u8()?;
+
+        self.motor_on = state.read_bool()?;
+        self.reset_transfer = state.read_bool()?;
+        self.read_mode = state.read_bool()?;
+        self.horizontal_mirroring = state.read_bool()?;
+        self.crc_control = state.read_bool()?;
+        self.transfer_enabled = state.read_bool()?;
+        self.disk_irq_enabled = state.read_bool()?;
+
+        self.read_data = state.read_u8()?;
+        self.write_data = state.read_u8()?;
+        self.transfer_complete = state.read_bool()?;
+        self.disk_irq = state.read_bool()?;
+
+        self.position = state.read_u32()? as usize;
+        self.delay = state.read_u32()?;
+        self.end_of_head = state.read_bool()?;
+        self.scanning = state.read_bool()?;
+        self.gap_ended = state.read_bool()?;
+        self.crc = state.read_u16()?;
+        self.previous_crc_control = state.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+mod disk_diff;
+mod disk_image;
+mod disk_system;
+
+pub use disk_diff::DiskDiff;
+pub use disk_image::{DiskImage, SIDE_SIZE};
+pub use disk_system::DiskSystem;
@@ -1,14 +1,22 @@
+pub mod assembler;
 mod bus;
-mod cartridge;
+pub mod cartridge;
+pub mod checksum;
 mod clock;
-mod cpu;
+pub mod controllers;
+pub mod cpu;
+pub mod database;
+pub mod disassembler;
+pub mod fds;
 mod nes;
-mod opcodes;
+pub mod opcodes;
 mod mappers;
+pub mod patch;
 
 pub mod egui; // fix privacy
 
 pub mod ppu;
+pub mod savestate;
 
 pub use bus::Bus;
 pub use cartridge::Cartridge;
@@ -1,10 +1,12 @@
-use crate::cartridge::Header;
+use crate::{cartridge::Header, savestate::SaveState};
 
 /// # Mapper
 /// Trait to emulate the address mapper.
 ///
 /// <https://www.nesdev.org/wiki/Mapper>
-pub trait Mapper {
+///
+/// Mappers save their banking and IRQ state as part of a save state.
+pub trait Mapper: SaveState {
     /// Construct a new maper, given the header metadata for the ROM
     /// Most of this information will not be used by most mappers, however
     /// it is there if needed.
@@ -14,6 +16,9 @@ pub trait Mapper {
 
     /// Transform address from cpu to an address indexable in the ROM
     fn map_cpu_read(&mut self, addr: u16, new_addr: &mut u16) -> bool;
+    /// Same as `map_cpu_read`, but must not change the mapper's state, for
+    /// debuggers and tracing
+    fn map_cpu_peek(&self, addr: u16, new_addr: &mut u16) -> bool;
     /// Transform a
     fn map_cpu_write(&mut self, addr: u16, new_addr: &mut u16) -> bool;
 
@@ -1,4 +1,7 @@
-use crate::Mapper;
+use crate::{
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+    Mapper,
+};
 
 /// # Mapper000 (NROM)
 /// The generic designation NROM refers to the Nintendo cartridge boards NES-NROM-128,
@@ -14,29 +17,40 @@ use crate::Mapper;
 /// (CPU)First 16K: 0x8000 - 0xBFFF
 /// (CPU)Last  16K: 0xC000 - 0xFFFF
 ///
+/// (PPU) 8K of CHR at 0x0000 - 0x1FFF. Writable only if it is CHR-RAM.
+///
 pub struct Mapper000 {
     pub prg_banks_count: u8,
     pub chr_banks_count: u8,
     pub mirrored: bool,
+    /// No CHR-ROM, so the pattern tables are writable CHR-RAM
+    pub chr_ram: bool,
 }
 
 impl Mapper for Mapper000 {
     fn new(header: &crate::cartridge::Header) -> Self {
         Self {
-            prg_banks_count: header.prg_rom_size,
-            chr_banks_count: header.prg_chr_size,
-            mirrored: header.prg_rom_size == 16,
+            prg_banks_count: (header.prg_rom_bytes() / (16 * 1024)) as u8,
+            chr_banks_count: (header.chr_rom_bytes() / (8 * 1024)) as u8,
+            mirrored: header.prg_rom_bytes() <= 16 * 1024,
+            // Bank counts round down, so a CHR-ROM smaller than a bank
+            // still counts as ROM
+            chr_ram: header.chr_rom_bytes() == 0,
         }
     }
 
     fn map_cpu_read(&mut self, addr: u16, new_addr: &mut u16) -> bool {
+        self.map_cpu_peek(addr, new_addr)
+    }
+
+    fn map_cpu_peek(&self, addr: u16, new_addr: &mut u16) -> bool {
         if !(0x8000..=0xFFFF).contains(&addr) {
             return false;
         }
 
         *new_addr = match self.mirrored {
-            true => addr & 0x7FFF,
-            false => addr & 0x3FFF,
+            true => addr & 0x3FFF,
+            false => addr & 0x7FFF,
         };
 
         true
@@ -48,15 +62,15 @@ impl Mapper for Mapper000 {
         }
 
         *new_addr = match self.mirrored {
-            true => addr & 0x7FFF,
-            false => addr & 0x3FFF,
+            true => addr & 0x3FFF,
+            false => addr & 0x7FFF,
         };
 
         true
     }
 
     fn map_ppu_read(&mut self, addr: u16, new_addr: &mut u16) -> bool {
-        match (0x1FFF..=0x8000).contains(&addr) {
+        match (0x0000..=0x1FFF).contains(&addr) {
             true => {
                 *new_addr = addr;
                 true
@@ -66,7 +80,8 @@ impl Mapper for Mapper000 {
     }
 
     fn map_ppu_write(&mut self, addr: u16, new_addr: &mut u16) -> bool {
-        match (0x1FFF..=0x8000).contains(&addr) {
+        // CHR-ROM can't be written to
+        match (0x0000..=0x1FFF).contains(&addr) && self.chr_ram {
             true => {
                 *new_addr = addr;
                 true
@@ -75,3 +90,13 @@ impl Mapper for Mapper000 {
         }
     }
 }
+
+/// NROM has no registers, and everything else is fixed by the ROM, so
+/// there is nothing to save.
+impl SaveState for Mapper000 {
+    fn save_state(&self, _state: &mut StateWriter) {}
+
+    fn load_state(&mut self, _state: &mut StateReader) -> Result<(), SaveStateError> {
+        Ok(())
+    }
+}
@@ -8,9 +8,10 @@ use crate::cartridge::Header;
 
 // TODO: use a dynamic dispatch here with a boxed trait object
 // once more mappers are implemented
-pub fn select_mapper(mapper_id: u8, header: &Header) -> Mapper000 {
+/// Returns `None` if the mapper is not implemented
+pub fn select_mapper(mapper_id: u16, header: &Header) -> Option<Mapper000> {
     match mapper_id {
-        0 => Mapper000::new(header),
-        _ => unimplemented!("Mapper {} not implemented", mapper_id),
+        0 => Some(Mapper000::new(header)),
+        _ => None,
     }
 }
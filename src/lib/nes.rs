@@ -1,12 +1,24 @@
-use std::{rc::Rc, cell::{RefCell, Ref, RefMut}};
-
-use crate::{Bus, Clock, Cpu, Ppu, RcCell, Cartridge, Reset};
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    rc::Rc,
+};
+
+use crate::{
+    controllers::{ControllerInput, PortDeviceKind},
+    cpu::IrqSource,
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+    Bus, Cartridge, Clock, Cpu, Ppu, RcCell, Reset,
+};
 
 pub struct Nes {
     pub cpu: RcCell<Cpu>,
     pub bus: RcCell<Bus>,
     pub ppu: RcCell<Ppu>,
     pub clock: Clock,
+    /// CRC-32 of the inserted cartridge, worked out once on insertion as it
+    /// is needed for every save state
+    rom_crc: u32,
+    audio_muted: bool,
 }
 
 impl Nes {
@@ -16,22 +28,49 @@ impl Nes {
             bus,
             ppu,
             clock,
+            rom_crc: 0,
+            audio_muted: false,
         }
     }
 
     pub fn tick(&mut self) {
         self.bus.borrow_mut().tick();
-        self.ppu.borrow_mut().tick();
+        let nmi = {
+            let mut ppu = self.ppu.borrow_mut();
+            ppu.tick();
+            ppu.nmi_line()
+        };
+        self.cpu.borrow_mut().set_nmi_line(nmi);
 
         // Cpu is 3 times slower than PPU
-        if self.clock.total_ticks() % 3 == 0 {
+        if self.clock.total_ticks().is_multiple_of(3) {
             self.cpu.borrow_mut().tick();
+            self.tick_cartridge();
         }
 
         self.clock.tick();
     }
 
+    /// Clock hardware on the cartridge for one CPU cycle, and pass on the
+    /// level of its IRQ line
+    fn tick_cartridge(&mut self) {
+        let cartridge = match self.cartridge_ref() {
+            Some(cartridge) => cartridge,
+            None => return,
+        };
+        let irq = {
+            let mut cartridge = cartridge.borrow_mut();
+            cartridge.cpu_tick();
+            cartridge.irq_pending()
+        };
+        self.cpu.borrow_mut().set_irq_line(IrqSource::Mapper, irq);
+    }
+
     pub fn insert_cartidge(&mut self, cartridge: Option<RcCell<Cartridge>>) {
+        self.rom_crc = cartridge
+            .as_ref()
+            .map(|cartridge| cartridge.borrow().crc32())
+            .unwrap_or(0);
         self.bus.borrow_mut().insert_cartridge(cartridge.clone());
         self.ppu.borrow_mut().insert_cartidge(cartridge);
     }
@@ -40,7 +79,7 @@ impl Nes {
         self.cpu.clone()
     }
 
-    pub fn cpu_ref(&self) -> Ref<Cpu> {
+    pub fn cpu_ref(&self) -> Ref<'_, Cpu> {
         self.cpu.borrow()
     }
 
@@ -49,10 +88,98 @@ impl Nes {
         // let bus = self.bus.borrow();
     }
 
-    pub fn cpu_mut(&self) -> RefMut<Cpu> {
+    pub fn cpu_mut(&self) -> RefMut<'_, Cpu> {
         self.cpu.borrow_mut()
     }
 
+    /// Plug a device into a controller port. `port` is `0` for port 1 and
+    /// `1` for port 2.
+    pub fn connect_port_device(&mut self, port: usize, kind: PortDeviceKind) {
+        self.bus.borrow_mut().connect_port_device(port, kind);
+    }
+
+    pub fn port_device_kind(&self, port: usize) -> PortDeviceKind {
+        self.bus.borrow().port_device_kind(port)
+    }
+
+    pub fn update_port_input(&mut self, input: &ControllerInput) {
+        self.bus.borrow_mut().update_port_input(input);
+    }
+
+    /// Discard sound output while `muted`, for frames that will be rolled
+    /// back. There is no APU yet, so this only records the setting for it.
+    pub fn set_audio_muted(&mut self, muted: bool) {
+        self.audio_muted = muted;
+    }
+
+    pub fn audio_muted(&self) -> bool {
+        self.audio_muted
+    }
+
+    /// CRC-32 of the inserted cartridge's ROM, or `0` if there is none
+    pub fn rom_crc32(&self) -> u32 {
+        self.rom_crc
+    }
+
+    /// Serialize the state of the whole machine into a versioned binary blob.
+    /// See [`StateWriter`] for the format.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(32 * 1024);
+        self.save_state_into(&mut state);
+        state
+    }
+
+    /// Like [`Nes::save_state`], but reuses `buffer` instead of allocating.
+    /// Used where the machine is saved every frame.
+    pub fn save_state_into(&self, buffer: &mut Vec<u8>) {
+        let mut state = StateWriter::with_buffer(std::mem::take(buffer), self.rom_crc32());
+
+        state.begin_section(b"NES ");
+        self.clock.save_state(&mut state);
+        state.begin_section(b"CPU ");
+        self.cpu.borrow().save_state(&mut state);
+        state.begin_section(b"BUS ");
+        self.bus.borrow().save_state(&mut state);
+        state.begin_section(b"PPU ");
+        self.ppu.borrow().save_state(&mut state);
+        if let Some(cartridge) = self.cartridge_ref() {
+            state.begin_section(b"CART");
+            cartridge.borrow().save_state(&mut state);
+        }
+
+        *buffer = state.finish();
+    }
+
+    /// Restore a state produced by [`Nes::save_state`].
+    /// The same ROM must be inserted as when the state was saved.
+    ///
+    /// If an error is returned, the machine may be partially restored and
+    /// should be reset.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), SaveStateError> {
+        let mut state = StateReader::new(data)?;
+        let rom_crc = self.rom_crc32();
+        if state.rom_crc != rom_crc {
+            return Err(SaveStateError::RomMismatch {
+                expected: state.rom_crc,
+                found: rom_crc,
+            });
+        }
+
+        state.section(b"NES ")?;
+        self.clock.load_state(&mut state)?;
+        state.section(b"CPU ")?;
+        self.cpu.borrow_mut().load_state(&mut state)?;
+        state.section(b"BUS ")?;
+        self.bus.borrow_mut().load_state(&mut state)?;
+        state.section(b"PPU ")?;
+        self.ppu.borrow_mut().load_state(&mut state)?;
+        if let Some(cartridge) = self.cartridge_ref() {
+            state.section(b"CART")?;
+            cartridge.borrow_mut().load_state(&mut state)?;
+        }
+
+        state.finish()
+    }
 }
 
 impl Default for Nes {
@@ -75,4 +202,3 @@ impl Reset for Nes {
         self.clock.reset();
     }
 }
-
@@ -2,7 +2,6 @@ mod opcode;
 mod opcode_from_u8;
 mod opcode_types;
 
-pub(self) mod operations;
+mod operations;
 pub use opcode::OpCode;
-pub use opcode_types::OpCodeType;
-
+pub use opcode_types::{MemoryAccess, OpCodeType};
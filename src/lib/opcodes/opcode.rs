@@ -1,19 +1,39 @@
 use crate::cpu::AddressingMode;
-use crate::opcodes::OpCodeType;
+use crate::opcodes::{MemoryAccess, OpCodeType};
 
-#[derive(Debug)]
+/// An opcode's instruction and addressing mode, along with what the CPU
+/// needs to run it. Every opcode is decoded ahead of time, along with its
+/// operation, see `opcode_from_u8.rs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct OpCode {
     pub code_type: OpCodeType,
     pub addressing_mode: AddressingMode,
     pub cycles: u8,
+    /// See [`OpCodeType::access`]
+    pub access: MemoryAccess,
 }
 
 impl OpCode {
-    pub fn new(code_type: OpCodeType, addressing_mode: AddressingMode, cycles: u8) -> Self {
+    pub const fn new(code_type: OpCodeType, addressing_mode: AddressingMode, cycles: u8) -> Self {
         OpCode {
             code_type,
             addressing_mode,
             cycles,
+            access: code_type.access(),
+        }
+    }
+
+    /// Whether `raw` is one of the 151 opcodes documented by MOS. The rest
+    /// decode to unofficial instructions.
+    pub fn is_official(raw: u8) -> bool {
+        use crate::opcodes::OpCodeType::*;
+
+        match OpCode::from(raw).code_type {
+            NOP => raw == 0xEA,
+            SBC => raw != 0xEB,
+            ANC | ALR | ARR | AXS | DCP | ISC | JAM | LAS | LAX | RLA | RRA | SAX | SHA | SHX
+            | SHY | SLO | SRE | TAS | XAA => false,
+            _ => true,
         }
     }
 }
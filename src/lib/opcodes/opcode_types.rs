@@ -1,7 +1,7 @@
-use crate::Cpu;
+use crate::{cpu::Memory, Cpu};
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum OpCodeType {
     ADC,
     AND,
@@ -59,12 +59,78 @@ pub enum OpCodeType {
     TXA,
     TXS,
     TYA,
-    /// Illegal; Not a real thing in the actual NES. Anything not implemented is illegal
-    XXX,
+
+    // Unofficial opcodes. Stable unless noted otherwise.
+    // <https://www.nesdev.org/wiki/CPU_unofficial_opcodes>
+    /// AND then copy N to C
+    ANC,
+    /// AND then LSR A
+    ALR,
+    /// AND then ROR A, with C and V from bits 6 and 5
+    ARR,
+    /// Store (A & X) - M in X, without borrow
+    AXS,
+    /// DEC then CMP
+    DCP,
+    /// INC then SBC
+    ISC,
+    /// Halts the CPU until reset
+    JAM,
+    /// Load A, X and the stack pointer with M & SP
+    LAS,
+    /// LDA and LDX together. Unstable in immediate mode.
+    LAX,
+    /// ROL then AND
+    RLA,
+    /// ROR then ADC
+    RRA,
+    /// Store A & X
+    SAX,
+    /// Unstable: store A & X & (high byte of address + 1)
+    SHA,
+    /// Unstable: store X & (high byte of address + 1)
+    SHX,
+    /// Unstable: store Y & (high byte of address + 1)
+    SHY,
+    /// ASL then ORA
+    SLO,
+    /// LSR then EOR
+    SRE,
+    /// Unstable: set the stack pointer to A & X, then SHA
+    TAS,
+    /// Unstable: A = (A | magic) & X & M
+    XAA,
+}
+
+/// How an instruction uses the memory its addressing mode points at, which
+/// decides the bus accesses made on its last cycles.
+/// <https://www.nesdev.org/6502_cpu.txt>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryAccess {
+    /// Reads the operand on the last cycle
+    Read,
+    /// Writes on the last cycle, without reading first
+    Write,
+    /// Reads the operand, writes it back unmodified while working on it, then
+    /// writes the result
+    ReadModifyWrite,
 }
 
 impl OpCodeType {
-    pub fn executable(&self) -> fn(&mut Cpu) -> u8 {
+    /// Instructions without a memory operand count as reads
+    pub const fn access(&self) -> MemoryAccess {
+        use self::OpCodeType::*;
+
+        match *self {
+            STA | STX | STY | SAX | SHA | SHX | SHY | TAS => MemoryAccess::Write,
+            ASL | LSR | ROL | ROR | INC | DEC | SLO | RLA | SRE | RRA | DCP | ISC => {
+                MemoryAccess::ReadModifyWrite
+            }
+            _ => MemoryAccess::Read,
+        }
+    }
+
+    pub const fn executable<M: Memory>(&self) -> fn(&mut Cpu<M>) -> u8 {
         use self::OpCodeType::*;
         use super::operations::*;
 
@@ -125,7 +191,25 @@ impl OpCodeType {
             TXA => txa_fn,
             TXS => txs_fn,
             TYA => tya_fn,
-            XXX => xxx_fn,
+            ANC => anc_fn,
+            ALR => alr_fn,
+            ARR => arr_fn,
+            AXS => axs_fn,
+            DCP => dcp_fn,
+            ISC => isc_fn,
+            JAM => jam_fn,
+            LAS => las_fn,
+            LAX => lax_fn,
+            RLA => rla_fn,
+            RRA => rra_fn,
+            SAX => sax_fn,
+            SHA => sha_fn,
+            SHX => shx_fn,
+            SHY => shy_fn,
+            SLO => slo_fn,
+            SRE => sre_fn,
+            TAS => tas_fn,
+            XAA => xaa_fn,
         }
     }
 }
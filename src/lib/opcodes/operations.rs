@@ -4,6 +4,7 @@ use crate::cpu::CpuFlag;
 ///
 /// Documentation from:
 /// <https://www.nesdev.org/obelisk-6502-guide/reference.html>
+use crate::cpu::Memory;
 use crate::Cpu;
 
 /// # Add with carry
@@ -16,27 +17,9 @@ use crate::Cpu;
 /// Z    Zero Flag      - Set if A = 0
 /// V    Overflow Flag  - Set if sign bit is incorrect
 /// N    Negative Flag  - Set if bit 7 set
-pub fn adc_fn(cpu: &mut Cpu) -> u8 {
+pub fn adc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     let fetched = cpu.fetch();
-
-    // add as u16 for overflow detection
-    let raw_add = cpu.a_register as u16 + fetched as u16 + cpu.get_flag(&CpuFlag::Carry) as u16;
-    let final_add = (raw_add & 0xFF) as u8;
-
-    //
-
-    cpu.set_or_clear_flag(&CpuFlag::Carry, raw_add > u8::MAX as u16);
-    cpu.set_or_clear_flag(&CpuFlag::Zero, final_add == 0);
-    cpu.set_or_clear_flag(&CpuFlag::Negative, (final_add & 0x80) != 0);
-    // TODO: double check this logic
-    cpu.set_or_clear_flag(
-        &CpuFlag::Overflow,
-        {
-            !(cpu.a_register as u16 ^ fetched as u16) & ((cpu.a_register as u16 ^ raw_add) * 0x0080)
-        } == 0,
-    );
-
-    cpu.a_register = final_add;
+    add(cpu, fetched);
 
     1 // Can require extra cycle
 }
@@ -50,30 +33,101 @@ pub fn adc_fn(cpu: &mut Cpu) -> u8 {
 /// - Z - Zero Flag         - Set if A = 0
 /// - V - Overflow Flag     - Set if sign bit is incorrect
 /// - N - Negative Flag     - Set if bit 7 set
-pub fn sbc_fn(cpu: &mut Cpu) -> u8 {
-    // Take compliment and treat as addition
-    let fetched = cpu.fetch() ^ 0xFF;
+pub fn sbc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    subtract(cpu, fetched);
 
+    1 // Can require extra cycle
+}
+
+/// Helper for ADC and RRA, in binary or decimal
+pub fn add<M: Memory>(cpu: &mut Cpu<M>, value: u8) {
+    match cpu.decimal_mode && cpu.get_flag(&CpuFlag::Decimal) {
+        true => decimal_add(cpu, value),
+        false => add_with_carry(cpu, value),
+    }
+}
+
+/// Helper for SBC and ISC, in binary or decimal
+pub fn subtract<M: Memory>(cpu: &mut Cpu<M>, value: u8) {
+    match cpu.decimal_mode && cpu.get_flag(&CpuFlag::Decimal) {
+        true => decimal_subtract(cpu, value),
+        // Take compliment and treat as addition
+        false => add_with_carry(cpu, value ^ 0xFF),
+    }
+}
+
+/// Helper for addition and subtraction. Adds `value` and the carry flag to
+/// the accumulator.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set if overflow in bit 7
+/// - Z - Zero Flag         - Set if A = 0
+/// - V - Overflow Flag     - Set if both inputs have the same sign, but the result's differs
+/// - N - Negative Flag     - Set if bit 7 set
+pub fn add_with_carry<M: Memory>(cpu: &mut Cpu<M>, value: u8) {
     // add as u16 for overflow detection
-    let raw_add = cpu.a_register as u16 + fetched as u16 + cpu.get_flag(&CpuFlag::Carry) as u16;
+    let raw_add = cpu.a_register as u16 + value as u16 + cpu.get_flag(&CpuFlag::Carry) as u16;
     let final_add = (raw_add & 0xFF) as u8;
 
-    //
-
     cpu.set_or_clear_flag(&CpuFlag::Carry, raw_add > u8::MAX as u16);
     cpu.set_or_clear_flag(&CpuFlag::Zero, final_add == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, (final_add & 0x80) != 0);
-    // TODO: double check this logic
     cpu.set_or_clear_flag(
         &CpuFlag::Overflow,
-        {
-            !(cpu.a_register as u16 ^ fetched as u16) & ((cpu.a_register as u16 ^ raw_add) * 0x0080)
-        } == 0,
+        !(cpu.a_register ^ value) & (cpu.a_register ^ final_add) & 0x80 != 0,
     );
 
     cpu.a_register = final_add;
+}
 
-    1 // Can require extra cycle
+/// Helper for ADC in decimal mode, on CPUs that have it. Adds `value` and the
+/// carry flag to the accumulator as two digit BCD numbers.
+///
+/// Like the NMOS 6502, Z is set from the binary sum, and N and V from the
+/// sum before the high digit is adjusted.
+/// <http://www.6502.org/tutorials/decimal_mode.html#A>
+pub fn decimal_add<M: Memory>(cpu: &mut Cpu<M>, value: u8) {
+    let (a, value) = (cpu.a_register as i16, value as i16);
+    let carry = cpu.get_flag(&CpuFlag::Carry) as i16;
+
+    let mut low = (a & 0x0F) + (value & 0x0F) + carry;
+    if low >= 0x0A {
+        low = ((low + 0x06) & 0x0F) + 0x10;
+    }
+    let mut sum = (a & 0xF0) + (value & 0xF0) + low;
+    let signed = (a as i8 as i16 & !0x0F) + (value as i8 as i16 & !0x0F) + low;
+    let binary = (a + value + carry) as u8;
+    if sum >= 0xA0 {
+        sum += 0x60;
+    }
+
+    cpu.set_or_clear_flag(&CpuFlag::Carry, sum >= 0x100);
+    cpu.set_or_clear_flag(&CpuFlag::Zero, binary == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, signed & 0x80 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Overflow, !(-128..=127).contains(&signed));
+    cpu.a_register = sum as u8;
+}
+
+/// Helper for SBC in decimal mode, on CPUs that have it. Subtracts `value`
+/// and the borrow from the accumulator as two digit BCD numbers.
+///
+/// Like the NMOS 6502, the flags are set as for a binary subtraction.
+/// <http://www.6502.org/tutorials/decimal_mode.html#A>
+pub fn decimal_subtract<M: Memory>(cpu: &mut Cpu<M>, value: u8) {
+    let (a, operand) = (cpu.a_register as i16, value as i16);
+    let borrow = 1 - cpu.get_flag(&CpuFlag::Carry) as i16;
+
+    let mut low = (a & 0x0F) - (operand & 0x0F) - borrow;
+    if low < 0 {
+        low = ((low - 0x06) & 0x0F) - 0x10;
+    }
+    let mut difference = (a & 0xF0) - (operand & 0xF0) + low;
+    if difference < 0 {
+        difference -= 0x60;
+    }
+
+    add_with_carry(cpu, value ^ 0xFF);
+    cpu.a_register = difference as u8;
 }
 
 /// # Logical AND
@@ -82,12 +136,10 @@ pub fn sbc_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if A = 0
 /// - N - Negative Flag     - Set if bit 7 set
-pub fn and_fn(cpu: &mut Cpu) -> u8 {
-    let fetched = cpu.fetch();
-
-    let res = cpu.a_register & fetched;
-    cpu.set_or_clear_flag(&CpuFlag::Zero, res == 0);
-    cpu.set_or_clear_flag(&CpuFlag::Negative, (res & 0x80) != 0);
+pub fn and_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.a_register &= cpu.fetch();
+    cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, (cpu.a_register & 0x80) != 0);
 
     1
 }
@@ -99,29 +151,44 @@ pub fn and_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Multiplying result overflows 8bit register
 /// - Z - Zero Flag         - Set if A = 0
 /// - N - Negative Flag     - Set if bit 7 of result set
-pub fn asl_fn(cpu: &mut Cpu) -> u8 {
-    let raw_res: u16 = (cpu.fetch() as u16) << 0x01;
-    let result: u8 = (raw_res & 0xFF) as u8;
+pub fn asl_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let result = shift_left(cpu, fetched);
+    write_back(cpu, result);
 
-    cpu.set_or_clear_flag(&CpuFlag::Carry, raw_res > u8::MAX as u16);
+    0
+}
+
+/// Helper for ASL and SLO. Returns `value << 1`.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 7 before the shift
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn shift_left<M: Memory>(cpu: &mut Cpu<M>, value: u8) -> u8 {
+    let result = value << 1;
+    cpu.set_or_clear_flag(&CpuFlag::Carry, value & 0x80 != 0);
     cpu.set_or_clear_flag(&CpuFlag::Zero, result == 0);
-    cpu.set_or_clear_flag(&CpuFlag::Negative, (result & 0x80) != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, result & 0x80 != 0);
+    result
+}
 
-    // Store in either memory or accumulator
+/// Helper for shifts and rotates. Store the result in either the accumulator
+/// or memory, depending on the addressing mode.
+pub fn write_back<M: Memory>(cpu: &mut Cpu<M>, result: u8) {
     match cpu.addressing_mode {
         AddressingMode::IMP => cpu.a_register = result,
         _ => cpu.write(cpu.absolute_addr, result),
     };
-
-    0
 }
 
 /// Helper for branching.
 /// Returns 1 extra cycle if branch occurs to the same page.
 /// 2 Extra cycles if branch is onto a different bage
-pub fn relative_branch(cpu: &mut Cpu) -> u8 {
+pub fn relative_branch<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     let pc_old = cpu.program_counter;
-    cpu.program_counter += (cpu.relative_addr as i16) as u16;
+    cpu.program_counter = cpu
+        .program_counter
+        .wrapping_add(cpu.relative_addr as i16 as u16);
 
     match (pc_old & 0xFF00) == (cpu.program_counter & 0xFF00) {
         true => 1,  // Same page -> 1
@@ -139,7 +206,7 @@ pub fn relative_branch(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bcc_fn(cpu: &mut Cpu) -> u8 {
+pub fn bcc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Carry) {
         true => 0,
         false => relative_branch(cpu),
@@ -156,7 +223,7 @@ pub fn bcc_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bcs_fn(cpu: &mut Cpu) -> u8 {
+pub fn bcs_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Carry) {
         true => relative_branch(cpu),
         false => 0,
@@ -171,7 +238,7 @@ pub fn bcs_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn beq_fn(cpu: &mut Cpu) -> u8 {
+pub fn beq_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Zero) {
         true => relative_branch(cpu),
         false => 0,
@@ -186,7 +253,7 @@ pub fn beq_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bne_fn(cpu: &mut Cpu) -> u8 {
+pub fn bne_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Zero) {
         true => 0,
         false => relative_branch(cpu),
@@ -201,7 +268,7 @@ pub fn bne_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bmi_fn(cpu: &mut Cpu) -> u8 {
+pub fn bmi_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Negative) {
         true => relative_branch(cpu),
         false => 0,
@@ -216,7 +283,7 @@ pub fn bmi_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bpl_fn(cpu: &mut Cpu) -> u8 {
+pub fn bpl_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Negative) {
         true => 0,
         false => relative_branch(cpu),
@@ -227,24 +294,12 @@ pub fn bpl_fn(cpu: &mut Cpu) -> u8 {
 /// The BRK instruction forces the generation of an interrupt request. The program counter and
 /// processor status are pushed on the stack then the IRQ interrupt vector at $FFFE/F is loaded
 /// into the PC and the break flag in the status set to one.
-pub fn brk_fn(cpu: &mut Cpu) -> u8 {
-    // shouldn't ever need to wrap on a real program but, for completeness,
-    // have it overflow
-    cpu.program_counter = cpu.program_counter.wrapping_add(1);
+///
+/// The pushes and the vector read happen on the earlier cycles of the
+/// instruction, leaving the vector in `absolute_addr`.
+pub fn brk_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.set_flag(&CpuFlag::Interrupt);
-
-    // Push PC to stack (2 bytes)
-    let [lo, hi] = cpu.program_counter.to_le_bytes();
-    cpu.push_stack(hi);
-    cpu.push_stack(lo);
-
-    // Push status register but, with the break flag set
-    cpu.set_flag(&CpuFlag::Break);
-    cpu.push_stack(cpu.status_register);
-    cpu.clear_flag(&CpuFlag::Break);
-
-    // Load PC from interrupt vector (Last 2 bytes of address space)
-
+    cpu.program_counter = cpu.absolute_addr;
     0
 }
 
@@ -256,7 +311,7 @@ pub fn brk_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bvc_fn(cpu: &mut Cpu) -> u8 {
+pub fn bvc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Overflow) {
         true => 0,
         false => relative_branch(cpu),
@@ -271,7 +326,7 @@ pub fn bvc_fn(cpu: &mut Cpu) -> u8 {
 /// ## Cycles:
 /// +1 if branch occurs
 /// +2 if branch occurs to a new page
-pub fn bvs_fn(cpu: &mut Cpu) -> u8 {
+pub fn bvs_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     match cpu.get_flag(&CpuFlag::Overflow) {
         false => 0,
         true => relative_branch(cpu),
@@ -287,7 +342,7 @@ pub fn bvs_fn(cpu: &mut Cpu) -> u8 {
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
 /// - V - Overflow Flag     - Set if bit 6 of result is set
-pub fn bit_fn(cpu: &mut Cpu) -> u8 {
+pub fn bit_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     let fetched = cpu.fetch();
     let result = cpu.a_register & fetched;
 
@@ -302,7 +357,7 @@ pub fn bit_fn(cpu: &mut Cpu) -> u8 {
 /// Set the carry flag to 0
 /// ## Processor Status after use:
 /// - C - Carry Flag        - Set to 0
-pub fn clc_fn(cpu: &mut Cpu) -> u8 {
+pub fn clc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.clear_flag(&CpuFlag::Carry);
     0
 }
@@ -311,7 +366,7 @@ pub fn clc_fn(cpu: &mut Cpu) -> u8 {
 /// Set the decimal flag to 0
 /// ## Processor Status after use:
 /// - D - Decimal Flag      - Set to 0
-pub fn cld_fn(cpu: &mut Cpu) -> u8 {
+pub fn cld_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.clear_flag(&CpuFlag::Decimal);
     0
 }
@@ -320,7 +375,7 @@ pub fn cld_fn(cpu: &mut Cpu) -> u8 {
 /// Set the interrupt disable flag to 0
 /// ## Processor Status after use:
 /// - I - Interrupt Disable - Set to 0
-pub fn cli_fn(cpu: &mut Cpu) -> u8 {
+pub fn cli_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.clear_flag(&CpuFlag::Interrupt);
     0
 }
@@ -329,7 +384,7 @@ pub fn cli_fn(cpu: &mut Cpu) -> u8 {
 /// Set the overflow flag to 0
 /// ## Processor Status after use:
 /// - V - Overflow Flag     - Set to 0
-pub fn clv_fn(cpu: &mut Cpu) -> u8 {
+pub fn clv_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.clear_flag(&CpuFlag::Overflow);
     0
 }
@@ -343,8 +398,9 @@ pub fn clv_fn(cpu: &mut Cpu) -> u8 {
 /// - N - Negative Flag     - Set if bit 7 of A - M is set
 /// ## Cycles:
 /// +1 if page crosses in certain addressing modes
-pub fn cmp_fn(cpu: &mut Cpu) -> u8 {
-    compare_values(cpu, cpu.a_register);
+pub fn cmp_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    compare_values(cpu, cpu.a_register, fetched);
     1
 }
 
@@ -355,8 +411,9 @@ pub fn cmp_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Set if X >= M
 /// - Z - Zero Flag         - Set if X == M
 /// - N - Negative Flag     - Set if bit 7 of X - M is set
-pub fn cpx_fn(cpu: &mut Cpu) -> u8 {
-    compare_values(cpu, cpu.x_register);
+pub fn cpx_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    compare_values(cpu, cpu.x_register, fetched);
     0
 }
 
@@ -367,8 +424,9 @@ pub fn cpx_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Set if Y >= M
 /// - Z - Zero Flag         - Set if Y == M
 /// - N - Negative Flag     - Set if bit 7 of Y - M is set
-pub fn cpy_fn(cpu: &mut Cpu) -> u8 {
-    compare_values(cpu, cpu.y_register);
+pub fn cpy_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    compare_values(cpu, cpu.y_register, fetched);
     0
 }
 
@@ -377,13 +435,11 @@ pub fn cpy_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Set if Register >= M
 /// - Z - Zero Flag         - Set if Register == M
 /// - N - Negative Flag     - Set if bit 7 of Register - M is set
-pub fn compare_values(cpu: &mut Cpu, register_val: u8) {
-    let rhs = cpu.fetch();
-    // let result = ((register_val as i16 - rhs as u16) & 0x00FF) as u8;
+pub fn compare_values<M: Memory>(cpu: &mut Cpu<M>, register_val: u8, rhs: u8) {
+    let result = register_val.wrapping_sub(rhs);
     cpu.set_or_clear_flag(&CpuFlag::Carry, register_val >= rhs);
-    cpu.set_or_clear_flag(&CpuFlag::Carry, register_val == rhs);
-    // TODO: fix this to prevent underflow
-    // cpu.set_or_clear_flag(&CpuFlag::Negative, result & 0x80 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Zero, register_val == rhs);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, result & 0x80 != 0);
 }
 
 /// # Decrement Memory
@@ -391,8 +447,10 @@ pub fn compare_values(cpu: &mut Cpu, register_val: u8) {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn dec_fn(cpu: &mut Cpu) -> u8 {
+pub fn dec_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     let res = cpu.fetch().wrapping_sub(1);
+    cpu.write(cpu.absolute_addr, res);
+
     cpu.set_or_clear_flag(&CpuFlag::Zero, res == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, res & 0x80 != 0);
 
@@ -404,7 +462,7 @@ pub fn dec_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if X == 0
 /// - N - Negative Flag     - Set if bit 7 of X is set
-pub fn dex_fn(cpu: &mut Cpu) -> u8 {
+pub fn dex_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.x_register = cpu.x_register.wrapping_sub(1);
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.x_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.x_register & 0x80 != 0);
@@ -416,8 +474,8 @@ pub fn dex_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if y == 0
 /// - N - Negative Flag     - Set if bit 7 of y is set
-pub fn dey_fn(cpu: &mut Cpu) -> u8 {
-    cpu.y_register = cpu.x_register.wrapping_sub(1);
+pub fn dey_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.y_register = cpu.y_register.wrapping_sub(1);
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.y_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.y_register & 0x80 != 0);
     0
@@ -429,7 +487,7 @@ pub fn dey_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn eor_fn(cpu: &mut Cpu) -> u8 {
+pub fn eor_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.a_register ^= cpu.fetch();
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
@@ -441,9 +499,9 @@ pub fn eor_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn inc_fn(cpu: &mut Cpu) -> u8 {
-    let res = cpu.fetch() + 1;
-    cpu.write(cpu.absolute_addr + 1, res);
+pub fn inc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let res = cpu.fetch().wrapping_add(1);
+    cpu.write(cpu.absolute_addr, res);
 
     cpu.set_or_clear_flag(&CpuFlag::Zero, res == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, res & 0x80 != 0);
@@ -455,7 +513,7 @@ pub fn inc_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if X == 0
 /// - N - Negative Flag     - Set if bit 7 of X is set
-pub fn inx_fn(cpu: &mut Cpu) -> u8 {
+pub fn inx_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.x_register = cpu.x_register.wrapping_add(1);
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.x_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.x_register & 0x80 != 0);
@@ -467,8 +525,8 @@ pub fn inx_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if y == 0
 /// - N - Negative Flag     - Set if bit 7 of y is set
-pub fn iny_fn(cpu: &mut Cpu) -> u8 {
-    cpu.y_register = cpu.x_register.wrapping_add(1);
+pub fn iny_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.y_register = cpu.y_register.wrapping_add(1);
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.y_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.y_register & 0x80 != 0);
     0
@@ -476,7 +534,7 @@ pub fn iny_fn(cpu: &mut Cpu) -> u8 {
 
 /// # Jump to Address
 /// Sets the program counter to the address specified.
-pub fn jmp_fn(cpu: &mut Cpu) -> u8 {
+pub fn jmp_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.program_counter = cpu.absolute_addr;
     0
 }
@@ -484,15 +542,11 @@ pub fn jmp_fn(cpu: &mut Cpu) -> u8 {
 /// # Jump to Subroutine
 /// Pushes the program_counter to the stack and then sets the program_counter
 /// to the address specified.
-pub fn jsr_fn(cpu: &mut Cpu) -> u8 {
-    cpu.program_counter -= 1;
-
-    // Push Hi and Lo Seperately
-    cpu.push_stack((cpu.program_counter >> 8 & 0x00FF) as u8);
-    cpu.push_stack((cpu.program_counter & 0x00FF) as u8);
-
+///
+/// The program counter is pushed between reading the two bytes of the
+/// address, while it points at the last byte of the instruction.
+pub fn jsr_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.program_counter = cpu.absolute_addr;
-
     0
 }
 
@@ -501,7 +555,7 @@ pub fn jsr_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn lda_fn(cpu: &mut Cpu) -> u8 {
+pub fn lda_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.a_register = cpu.fetch();
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
@@ -514,7 +568,7 @@ pub fn lda_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn ldx_fn(cpu: &mut Cpu) -> u8 {
+pub fn ldx_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.x_register = cpu.fetch();
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.x_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.x_register & 0x80 != 0);
@@ -527,7 +581,7 @@ pub fn ldx_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn ldy_fn(cpu: &mut Cpu) -> u8 {
+pub fn ldy_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.y_register = cpu.fetch();
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.y_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.y_register & 0x80 != 0);
@@ -542,26 +596,35 @@ pub fn ldy_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Set to the value of bit 0 before the shift
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn lsr_fn(cpu: &mut Cpu) -> u8 {
+pub fn lsr_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     let fetched = cpu.fetch();
-    cpu.set_or_clear_flag(&CpuFlag::Carry, fetched & 0x01 != 0);
-
-    let res = fetched >> 1;
-    cpu.set_or_clear_flag(&CpuFlag::Zero, res == 0);
-    cpu.set_or_clear_flag(&CpuFlag::Negative, res & 0x80 != 0);
-
-    match cpu.addressing_mode {
-        AddressingMode::IMP => cpu.a_register = res,
-        _ => cpu.write(cpu.absolute_addr, res),
-    }
+    let result = shift_right(cpu, fetched);
+    write_back(cpu, result);
 
     0
 }
 
+/// Helper for LSR, ALR and SRE. Returns `value >> 1`.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 0 before the shift
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Cleared
+pub fn shift_right<M: Memory>(cpu: &mut Cpu<M>, value: u8) -> u8 {
+    let result = value >> 1;
+    cpu.set_or_clear_flag(&CpuFlag::Carry, value & 0x01 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Zero, result == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, false);
+    result
+}
+
 /// # No Operation
 /// No Operation. Do nothing
-pub fn nop_fn(_cpu: &mut Cpu) -> u8 {
-    // TODO: verify different no-op opcodes have different cycles
+/// Unofficial NOPs with an operand still read it, and take an extra cycle
+/// if indexing crosses a page.
+pub fn nop_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    if cpu.addressing_mode != AddressingMode::IMP {
+        cpu.fetch();
+    }
     // https://wiki.nesdev.com/w/index.php/CPU_unofficial_opcodes
     // The use of unofficial opcodes is rare in NES games. It appears to occur mostly in late or
     // unlicensed titles:
@@ -577,7 +640,7 @@ pub fn nop_fn(_cpu: &mut Cpu) -> u8 {
     // - Puzznic (all regions) (US release November 1990) uses $89 (a 2-byte NOP).
     // - Super Cars (U) (February 1991) uses $B3 (LAX).
     // ----------------------------------------------
-    0
+    1
 }
 
 /// # Bitwise Or
@@ -586,7 +649,7 @@ pub fn nop_fn(_cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn ora_fn(cpu: &mut Cpu) -> u8 {
+pub fn ora_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.a_register |= cpu.fetch();
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
@@ -596,7 +659,7 @@ pub fn ora_fn(cpu: &mut Cpu) -> u8 {
 
 /// # Push Accumulator
 /// Pushes the accumulator onto the stack
-pub fn pha_fn(cpu: &mut Cpu) -> u8 {
+pub fn pha_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.push_stack(cpu.a_register);
     0
 }
@@ -604,12 +667,9 @@ pub fn pha_fn(cpu: &mut Cpu) -> u8 {
 /// # Push Status Register
 /// Pushes the status register onto the stack
 /// ## Processor Status after use:
-/// - B - Break Flag        - Set to 1 before pushing. Unset after
-/// - U - Unused Flag       - Set to 1 before pushing. Unset after TODO: verify
-pub fn php_fn(cpu: &mut Cpu) -> u8 {
+/// No Changes. Break and Unused are set on the pushed copy only.
+pub fn php_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.push_stack(cpu.status_register | CpuFlag::Break as u8 | CpuFlag::Unused as u8);
-    cpu.clear_flag(&CpuFlag::Break);
-    cpu.clear_flag(&CpuFlag::Unused);
     0
 }
 
@@ -618,7 +678,7 @@ pub fn php_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn pla_fn(cpu: &mut Cpu) -> u8 {
+pub fn pla_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.a_register = cpu.pop_stack();
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
@@ -628,9 +688,11 @@ pub fn pla_fn(cpu: &mut Cpu) -> u8 {
 /// # Pull Status Register
 /// Pulls top value from the stack, into the status register
 /// ## Processor Status after use:
-/// - U - Unused Flag       - Set to 1 after pulling. TODO: verify
-pub fn plp_fn(cpu: &mut Cpu) -> u8 {
-    cpu.a_register = cpu.pop_stack();
+/// - B - Break Flag        - Ignored, as it only exists on the stack
+/// - U - Unused Flag       - Set to 1 after pulling
+pub fn plp_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.status_register = cpu.pop_stack();
+    cpu.clear_flag(&CpuFlag::Break);
     cpu.set_flag(&CpuFlag::Unused);
     0
 }
@@ -644,21 +706,27 @@ pub fn plp_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Set to the value of bit 7 before the shift
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn rol_fn(cpu: &mut Cpu) -> u8 {
-    let res: u16 = (cpu.fetch() as u16) << 1 | cpu.get_flag(&CpuFlag::Carry) as u16;
-
-    cpu.set_or_clear_flag(&CpuFlag::Zero, res == 0);
-    cpu.set_or_clear_flag(&CpuFlag::Carry, res & 0xFF00 != 0);
-    cpu.set_or_clear_flag(&CpuFlag::Negative, res & 0x80 != 0);
-
-    match cpu.addressing_mode {
-        AddressingMode::IMP => cpu.a_register = res as u8,
-        _ => cpu.write(cpu.absolute_addr, (res & 0x00FF) as u8),
-    };
+pub fn rol_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let result = rotate_left(cpu, fetched);
+    write_back(cpu, result);
 
     0
 }
 
+/// Helper for ROL and RLA. Returns `value` rotated left through the carry flag.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 7 before the shift
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn rotate_left<M: Memory>(cpu: &mut Cpu<M>, value: u8) -> u8 {
+    let result = value << 1 | cpu.get_flag(&CpuFlag::Carry) as u8;
+    cpu.set_or_clear_flag(&CpuFlag::Carry, value & 0x80 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Zero, result == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, result & 0x80 != 0);
+    result
+}
+
 /// # Rotate Right
 /// Shift the bits in A or M (depending on addressing mode) to the right by one
 /// place. Result Stored back in A or M
@@ -666,22 +734,27 @@ pub fn rol_fn(cpu: &mut Cpu) -> u8 {
 /// - C - Carry Flag        - Set to the value of bit 0 before the shift
 /// - Z - Zero Flag         - Set if result is zero
 /// - N - Negative Flag     - Set if bit 7 of result is set
-pub fn ror_fn(cpu: &mut Cpu) -> u8 {
+pub fn ror_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     let fetched = cpu.fetch();
-    cpu.set_or_clear_flag(&CpuFlag::Carry, fetched & 0x01 != 0);
-
-    let res = (fetched >> 1) | (cpu.get_flag(&CpuFlag::Carry) as u8) << 7;
-    cpu.set_or_clear_flag(&CpuFlag::Zero, res == 0);
-    cpu.set_or_clear_flag(&CpuFlag::Negative, res & 0x80 != 0);
-
-    match cpu.addressing_mode {
-        AddressingMode::IMP => cpu.a_register = res,
-        _ => cpu.write(cpu.absolute_addr, res),
-    };
+    let result = rotate_right(cpu, fetched);
+    write_back(cpu, result);
 
     0
 }
 
+/// Helper for ROR and RRA. Returns `value` rotated right through the carry flag.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 0 before the shift
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn rotate_right<M: Memory>(cpu: &mut Cpu<M>, value: u8) -> u8 {
+    let result = (value >> 1) | (cpu.get_flag(&CpuFlag::Carry) as u8) << 7;
+    cpu.set_or_clear_flag(&CpuFlag::Carry, value & 0x01 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Zero, result == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, result & 0x80 != 0);
+    result
+}
+
 /// # Return from Interrupt
 /// Used at the end of a interrupt routine to return to the main program.
 /// Pulls status and program counter from the stac
@@ -693,27 +766,22 @@ pub fn ror_fn(cpu: &mut Cpu) -> u8 {
 /// - B - Break Flag        - Set from stack
 /// - V - Overflow Flag     - Set from stack
 /// - N - Negative Flag     - Set from stack
-pub fn rti_fn(cpu: &mut Cpu) -> u8 {
-    cpu.status_register = cpu.pop_stack();
-
-    // Unset Break and Unused since out of interrupt
-    cpu.clear_flag(&CpuFlag::Break);
-    cpu.clear_flag(&CpuFlag::Unused);
-
-    let lo = cpu.pop_stack();
-    let hi = cpu.pop_stack();
-    cpu.program_counter = u16::from_le_bytes([lo, hi]);
+///
+/// The pulls happen on the earlier cycles of the instruction, leaving the
+/// program counter in `absolute_addr`. Break only exists on the stack, so
+/// is ignored.
+pub fn rti_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.program_counter = cpu.absolute_addr;
 
     0
 }
 
 /// # Return from Subroutine
 /// Used at the end of a subroutine to return to the calling routine.
-/// Pulls (program counter - 1) from the stack.
-pub fn rts_fn(cpu: &mut Cpu) -> u8 {
-    let lo = cpu.pop_stack();
-    let hi = cpu.pop_stack();
-    cpu.program_counter = u16::from_le_bytes([lo, hi]) + 1;
+/// Pulls (program counter - 1) from the stack, on the earlier cycles of the
+/// instruction, into `absolute_addr`.
+pub fn rts_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.program_counter = cpu.absolute_addr.wrapping_add(1);
     0
 }
 
@@ -721,7 +789,7 @@ pub fn rts_fn(cpu: &mut Cpu) -> u8 {
 /// Sets the carry flag to 1
 /// ## Processor Status after use:
 /// - C - Carry Flag        - Set to 1
-pub fn sec_fn(cpu: &mut Cpu) -> u8 {
+pub fn sec_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.set_flag(&CpuFlag::Carry);
     0
 }
@@ -730,7 +798,7 @@ pub fn sec_fn(cpu: &mut Cpu) -> u8 {
 /// Sets the decimal flag to 1
 /// ## Processor Status after use:
 /// - D - Decimal Flag      - Set to 1
-pub fn sed_fn(cpu: &mut Cpu) -> u8 {
+pub fn sed_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.set_flag(&CpuFlag::Decimal);
     0
 }
@@ -739,28 +807,28 @@ pub fn sed_fn(cpu: &mut Cpu) -> u8 {
 /// Sets the interrupt disable flag to 1
 /// ## Processor Status after use:
 /// - I - Interrupt Flag    - Set to 1
-pub fn sei_fn(cpu: &mut Cpu) -> u8 {
+pub fn sei_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.set_flag(&CpuFlag::Interrupt);
     0
 }
 
 /// # Store Accumulator
 /// Stores the contents of the accumulator into memory
-pub fn sta_fn(cpu: &mut Cpu) -> u8 {
+pub fn sta_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.write(cpu.absolute_addr, cpu.a_register);
     0
 }
 
 /// # Store X Register
 /// Stores the contents of X into memory
-pub fn stx_fn(cpu: &mut Cpu) -> u8 {
+pub fn stx_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.write(cpu.absolute_addr, cpu.x_register);
     0
 }
 
 /// # Store Y Register
 /// Stores the contents of Y into memory
-pub fn sty_fn(cpu: &mut Cpu) -> u8 {
+pub fn sty_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.write(cpu.absolute_addr, cpu.y_register);
     0
 }
@@ -770,7 +838,7 @@ pub fn sty_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if X is zero
 /// - N - Negative Flag     - Set if bit 7 of X is set
-pub fn tax_fn(cpu: &mut Cpu) -> u8 {
+pub fn tax_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.x_register = cpu.a_register;
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.x_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.x_register & 0x80 != 0);
@@ -782,7 +850,7 @@ pub fn tax_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if Y is zero
 /// - N - Negative Flag     - Set if bit 7 of Y is set
-pub fn tay_fn(cpu: &mut Cpu) -> u8 {
+pub fn tay_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.y_register = cpu.a_register;
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.y_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.y_register & 0x80 != 0);
@@ -794,7 +862,7 @@ pub fn tay_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if X is zero
 /// - N - Negative Flag     - Set if bit 7 of X is set
-pub fn tsx_fn(cpu: &mut Cpu) -> u8 {
+pub fn tsx_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.x_register = cpu.stack_pointer;
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.x_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.x_register & 0x80 != 0);
@@ -803,7 +871,7 @@ pub fn tsx_fn(cpu: &mut Cpu) -> u8 {
 
 /// # Transfer X to Stack Pointer
 /// Copies the contents of the X register into the stack pointer
-pub fn txs_fn(cpu: &mut Cpu) -> u8 {
+pub fn txs_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.stack_pointer = cpu.x_register;
     0
 }
@@ -813,7 +881,7 @@ pub fn txs_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if A is zero
 /// - N - Negative Flag     - Set if bit 7 of A is set
-pub fn txa_fn(cpu: &mut Cpu) -> u8 {
+pub fn txa_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.a_register = cpu.x_register;
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
@@ -825,16 +893,267 @@ pub fn txa_fn(cpu: &mut Cpu) -> u8 {
 /// ## Processor Status after use:
 /// - Z - Zero Flag         - Set if A is zero
 /// - N - Negative Flag     - Set if bit 7 of A is set
-pub fn tya_fn(cpu: &mut Cpu) -> u8 {
+pub fn tya_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
     cpu.a_register = cpu.y_register;
     cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
     cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
     0
 }
 
-/// # Unofficial / Illegal Instructions
-/// Undefined. Should do nothing until implemented later.
-/// Currently, acts equivalently to NOP
-pub fn xxx_fn(_cpu: &mut Cpu) -> u8 {
+/// Constant ORed into the accumulator by the unstable XAA and LAX
+/// immediate. It varies between chips and with temperature; `$EE` is the
+/// most commonly observed value.
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
+/// # AND with Carry (ANC)
+/// AND the accumulator with an immediate value, then copy the negative flag
+/// into the carry flag.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set if bit 7 of result is set
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn anc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.a_register &= cpu.fetch();
+    cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Carry, cpu.a_register & 0x80 != 0);
+    0
+}
+
+/// # AND then Logical Shift Right (ALR)
+/// AND the accumulator with an immediate value, then shift it right.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 0 before the shift
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Cleared
+pub fn alr_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let value = cpu.a_register & cpu.fetch();
+    cpu.a_register = shift_right(cpu, value);
+    0
+}
+
+/// # AND then Rotate Right (ARR)
+/// AND the accumulator with an immediate value, then rotate it right. The
+/// flags come from the adder, which is part way through an ADC.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to bit 6 of result
+/// - Z - Zero Flag         - Set if result is zero
+/// - V - Overflow Flag     - Set to bit 6 XOR bit 5 of result
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn arr_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let value = cpu.a_register & cpu.fetch();
+    let result = (value >> 1) | (cpu.get_flag(&CpuFlag::Carry) as u8) << 7;
+    cpu.a_register = result;
+    cpu.set_or_clear_flag(&CpuFlag::Zero, result == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, result & 0x80 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Carry, result & 0x40 != 0);
+    cpu.set_or_clear_flag(&CpuFlag::Overflow, ((result >> 6) ^ (result >> 5)) & 1 != 0);
+    0
+}
+
+/// # AND X then Subtract (AXS)
+/// Store `(A & X) - M` in X, without using or affecting the borrow. Sets
+/// flags like CMP.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set if A & X >= M
+/// - Z - Zero Flag         - Set if X is zero
+/// - N - Negative Flag     - Set if bit 7 of X is set
+pub fn axs_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let value = cpu.a_register & cpu.x_register;
+    compare_values(cpu, value, fetched);
+    cpu.x_register = value.wrapping_sub(fetched);
+    0
+}
+
+/// # Decrement then Compare (DCP)
+/// Decrement the value in memory, then compare the accumulator with it.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set if A >= M - 1
+/// - Z - Zero Flag         - Set if A == M - 1
+/// - N - Negative Flag     - Set if bit 7 of A - (M - 1) is set
+pub fn dcp_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let result = cpu.fetch().wrapping_sub(1);
+    cpu.write(cpu.absolute_addr, result);
+    compare_values(cpu, cpu.a_register, result);
     0
 }
+
+/// # Increment then Subtract (ISC)
+/// Increment the value in memory, then subtract it from the accumulator
+/// with borrow.
+/// ## Processor Status after use:
+/// As SBC
+pub fn isc_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let result = cpu.fetch().wrapping_add(1);
+    cpu.write(cpu.absolute_addr, result);
+    subtract(cpu, result);
+    0
+}
+
+/// # Jam (JAM)
+/// Locks up the CPU; it stops fetching instructions until it is reset.
+/// The program counter is left pointing at the JAM.
+pub fn jam_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.jammed = true;
+    cpu.program_counter = cpu.program_counter.wrapping_sub(1);
+    0
+}
+
+/// # Load A, X and Stack Pointer (LAS)
+/// AND the value in memory with the stack pointer, and store the result in
+/// the accumulator, X and the stack pointer.
+/// ## Processor Status after use:
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn las_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let value = cpu.fetch() & cpu.stack_pointer;
+    cpu.a_register = value;
+    cpu.x_register = value;
+    cpu.stack_pointer = value;
+    cpu.set_or_clear_flag(&CpuFlag::Zero, value == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, value & 0x80 != 0);
+    1
+}
+
+/// # Load A and X (LAX)
+/// Load a byte from memory into both the accumulator and X. The immediate
+/// version is unstable, mixing in the old accumulator.
+/// ## Processor Status after use:
+/// - Z - Zero Flag         - Set if result is zero
+/// - N - Negative Flag     - Set if bit 7 of result is set
+pub fn lax_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let value = match cpu.addressing_mode {
+        AddressingMode::IMM => (cpu.a_register | UNSTABLE_MAGIC) & cpu.fetch(),
+        _ => cpu.fetch(),
+    };
+    cpu.a_register = value;
+    cpu.x_register = value;
+    cpu.set_or_clear_flag(&CpuFlag::Zero, value == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, value & 0x80 != 0);
+    1
+}
+
+/// # Rotate Left then AND (RLA)
+/// Rotate the value in memory left, then AND it into the accumulator.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 7 before the rotate
+/// - Z - Zero Flag         - Set if A is zero
+/// - N - Negative Flag     - Set if bit 7 of A is set
+pub fn rla_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let result = rotate_left(cpu, fetched);
+    cpu.write(cpu.absolute_addr, result);
+    cpu.a_register &= result;
+    cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
+    0
+}
+
+/// # Rotate Right then Add (RRA)
+/// Rotate the value in memory right, then add it to the accumulator with
+/// the carry it rotated out.
+/// ## Processor Status after use:
+/// As ADC
+pub fn rra_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let result = rotate_right(cpu, fetched);
+    cpu.write(cpu.absolute_addr, result);
+    add(cpu, result);
+    0
+}
+
+/// # Store A AND X (SAX)
+/// Store the accumulator ANDed with X in memory.
+pub fn sax_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.write(cpu.absolute_addr, cpu.a_register & cpu.x_register);
+    0
+}
+
+/// # Store A AND X AND High Byte (SHA)
+/// Unstable, see [`unstable_store`].
+pub fn sha_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    unstable_store(cpu, cpu.a_register & cpu.x_register, cpu.y_register);
+    0
+}
+
+/// # Store X AND High Byte (SHX)
+/// Unstable, see [`unstable_store`].
+pub fn shx_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    unstable_store(cpu, cpu.x_register, cpu.y_register);
+    0
+}
+
+/// # Store Y AND High Byte (SHY)
+/// Unstable, see [`unstable_store`].
+pub fn shy_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    unstable_store(cpu, cpu.y_register, cpu.x_register);
+    0
+}
+
+/// # Shift Left then OR (SLO)
+/// Shift the value in memory left, then OR it into the accumulator.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 7 before the shift
+/// - Z - Zero Flag         - Set if A is zero
+/// - N - Negative Flag     - Set if bit 7 of A is set
+pub fn slo_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let result = shift_left(cpu, fetched);
+    cpu.write(cpu.absolute_addr, result);
+    cpu.a_register |= result;
+    cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
+    0
+}
+
+/// # Shift Right then Exclusive Or (SRE)
+/// Shift the value in memory right, then XOR it into the accumulator.
+/// ## Processor Status after use:
+/// - C - Carry Flag        - Set to the value of bit 0 before the shift
+/// - Z - Zero Flag         - Set if A is zero
+/// - N - Negative Flag     - Set if bit 7 of A is set
+pub fn sre_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    let fetched = cpu.fetch();
+    let result = shift_right(cpu, fetched);
+    cpu.write(cpu.absolute_addr, result);
+    cpu.a_register ^= result;
+    cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
+    0
+}
+
+/// # Transfer A AND X to Stack Pointer (TAS)
+/// Set the stack pointer to the accumulator ANDed with X, then store it
+/// like SHA. Unstable, see [`unstable_store`].
+pub fn tas_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.stack_pointer = cpu.a_register & cpu.x_register;
+    unstable_store(cpu, cpu.stack_pointer, cpu.y_register);
+    0
+}
+
+/// # Transfer X to A then AND (XAA)
+/// Unstable: `A = (A | magic) & X & M`, see [`UNSTABLE_MAGIC`].
+/// ## Processor Status after use:
+/// - Z - Zero Flag         - Set if A is zero
+/// - N - Negative Flag     - Set if bit 7 of A is set
+pub fn xaa_fn<M: Memory>(cpu: &mut Cpu<M>) -> u8 {
+    cpu.a_register = (cpu.a_register | UNSTABLE_MAGIC) & cpu.x_register & cpu.fetch();
+    cpu.set_or_clear_flag(&CpuFlag::Zero, cpu.a_register == 0);
+    cpu.set_or_clear_flag(&CpuFlag::Negative, cpu.a_register & 0x80 != 0);
+    0
+}
+
+/// Helper for SHA, SHX, SHY and TAS, which store `value` ANDed with the high
+/// byte of the address before indexing, plus one. If indexing crossed a
+/// page, the high byte of the address written to is replaced by the value
+/// being stored.
+fn unstable_store<M: Memory>(cpu: &mut Cpu<M>, value: u8, index: u8) {
+    let base = cpu.absolute_addr.wrapping_sub(index as u16);
+    let value = value & ((base >> 8) as u8).wrapping_add(1);
+    let address = match (base ^ cpu.absolute_addr) & 0xFF00 {
+        0 => cpu.absolute_addr,
+        _ => u16::from_le_bytes([cpu.absolute_addr as u8, value]),
+    };
+    cpu.write(address, value);
+}
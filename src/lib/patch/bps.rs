@@ -0,0 +1,88 @@
+use super::patch::{check_footer, check_target, check_target_size, PatchError, PatchReader};
+use super::PatchFormat;
+
+const SIGNATURE: &[u8] = b"BPS1";
+
+/// # BPS
+/// `BPS1`, the source, target and metadata sizes as variable length
+/// numbers, the metadata, then commands until the 12 byte footer of
+/// CRC32s. Each command is a number holding the action in its low 2 bits
+/// and the length, less one, above them:
+///
+/// | Action | Name        | Copies from                                        |
+/// |--------|-------------|----------------------------------------------------|
+/// | 0      | Source Read | The source, at the same offset as the target       |
+/// | 1      | Target Read | The patch                                          |
+/// | 2      | Source Copy | The source, at a signed offset from the last copy  |
+/// | 3      | Target Copy | The target, at a signed offset from the last copy  |
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = PatchReader::new(patch);
+    if reader.bytes(SIGNATURE.len()) != Ok(SIGNATURE) {
+        return Err(PatchError::WrongSignature(PatchFormat::Bps));
+    }
+    let target_checksum = check_footer(patch, rom)?;
+    let commands_end = patch.len() - 12;
+
+    // The source size is checked by the source checksum
+    let _source_size = reader.number()?;
+    let target_size = check_target_size(reader.number()?)?;
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while reader.position < commands_end {
+        let command = reader.number()?;
+        let length = (command >> 2) + 1;
+        // Checked before copying, as a target copy can repeat its bytes
+        // for as long as it likes
+        if length > target_size - target.len() {
+            return Err(PatchError::OutOfBounds(target_size));
+        }
+        match command & 0b11 {
+            0 => {
+                let start = target.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or(PatchError::OutOfBounds(start))?;
+                target.extend_from_slice(bytes);
+            }
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            2 => {
+                source_offset = relative_offset(source_offset, reader.number()?)?;
+                let bytes = source_offset
+                    .checked_add(length)
+                    .and_then(|end| rom.get(source_offset..end))
+                    .ok_or(PatchError::OutOfBounds(source_offset))?;
+                target.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            _ => {
+                target_offset = relative_offset(target_offset, reader.number()?)?;
+                // May overlap the bytes being written, to repeat a pattern
+                for _ in 0..length {
+                    let byte = *target
+                        .get(target_offset)
+                        .ok_or(PatchError::OutOfBounds(target_offset))?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    check_target(&target, target_checksum)?;
+    Ok(target)
+}
+
+/// Move `offset` by a number holding the sign in its low bit and the
+/// distance above it
+fn relative_offset(offset: usize, number: usize) -> Result<usize, PatchError> {
+    let distance = number >> 1;
+    match number & 1 {
+        0 => offset.checked_add(distance),
+        _ => offset.checked_sub(distance),
+    }
+    .ok_or(PatchError::OutOfBounds(offset))
+}
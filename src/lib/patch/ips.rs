@@ -0,0 +1,53 @@
+use super::patch::{PatchError, PatchReader};
+use super::PatchFormat;
+
+const SIGNATURE: &[u8] = b"PATCH";
+/// Offset that ends the records, `EOF` in ASCII
+const END_OF_FILE: usize = 0x454F46;
+
+/// # IPS
+/// `PATCH`, then records until the `EOF` marker. Each record is a 3 byte
+/// big endian offset and a 2 byte big endian size, followed by that many
+/// bytes to write. A size of zero is a run instead: a 2 byte count and the
+/// byte to repeat. A 3 byte size may follow the marker to truncate the ROM.
+///
+/// IPS has no checksums, so any ROM at least as long as the patch expects
+/// is patched. Writes past the end of the ROM make it longer.
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = PatchReader::new(patch);
+    if reader.bytes(SIGNATURE.len()) != Ok(SIGNATURE) {
+        return Err(PatchError::WrongSignature(PatchFormat::Ips));
+    }
+
+    let mut target = rom.to_vec();
+    loop {
+        let offset = be(reader.bytes(3)?);
+        if offset == END_OF_FILE {
+            break;
+        }
+        let (length, run) = match be(reader.bytes(2)?) {
+            0 => (be(reader.bytes(2)?), Some(reader.byte()?)),
+            length => (length, None),
+        };
+        let end = offset + length;
+        if target.len() < end {
+            target.resize(end, 0);
+        }
+        match run {
+            Some(byte) => target[offset..end].fill(byte),
+            None => target[offset..end].copy_from_slice(reader.bytes(length)?),
+        }
+    }
+
+    if let Ok(size) = reader.bytes(3) {
+        target.truncate(be(size));
+    }
+    Ok(target)
+}
+
+/// Big endian number
+fn be(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |number, &byte| (number << 8) | byte as usize)
+}
@@ -0,0 +1,7 @@
+#![allow(clippy::module_inception)]
+mod bps;
+mod ips;
+mod patch;
+mod ups;
+
+pub use patch::{Patch, PatchError, PatchFormat};
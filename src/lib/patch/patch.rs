@@ -0,0 +1,233 @@
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use super::{bps, ips, ups};
+
+/// Kind of soft patch, by file extension
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Ups,
+}
+
+/// # Soft Patch
+/// A patch kept next to a ROM and applied each time it is loaded, so
+/// translations and hacks can be played without modifying the ROM.
+///
+/// The patch has the same name as the ROM, e.g. `zelda.nes` is patched by
+/// `zelda.ips`, `zelda.bps` or `zelda.ups`, in that order of preference.
+/// Patches apply to the whole file, header included.
+pub struct Patch {
+    pub format: PatchFormat,
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The file doesn't start with the signature of its format
+    WrongSignature(PatchFormat),
+    /// The patch ends partway through a record
+    CutOff,
+    /// A record reads or writes outside of the ROM
+    OutOfBounds(usize),
+    /// The patch file is corrupt
+    PatchChecksum { expected: u32, actual: u32 },
+    /// The patch was made for a different ROM
+    SourceChecksum { expected: u32, actual: u32 },
+    /// Patching didn't give the ROM the patch was made to produce
+    TargetChecksum { expected: u32, actual: u32 },
+}
+
+impl PatchFormat {
+    const ALL: [Self; 3] = [Self::Ips, Self::Bps, Self::Ups];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "IPS",
+            PatchFormat::Bps => "BPS",
+            PatchFormat::Ups => "UPS",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Bps => "bps",
+            PatchFormat::Ups => "ups",
+        }
+    }
+}
+
+impl Patch {
+    pub fn new(format: PatchFormat, path: PathBuf, data: Vec<u8>) -> Self {
+        Self { format, path, data }
+    }
+
+    /// The patch next to the ROM at `rom_path`, if there is one. Not having
+    /// a patch is not an error.
+    pub fn for_rom(rom_path: &Path) -> io::Result<Option<Self>> {
+        for format in PatchFormat::ALL {
+            let path = rom_path.with_extension(format.extension());
+            match std::fs::read(&path) {
+                Ok(data) => return Ok(Some(Self::new(format, path, data))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The patched ROM. BPS and UPS patches check that `rom` is the one
+    /// they were made for, and that the result is what they were made to
+    /// produce.
+    pub fn apply(&self, rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+        let patched = match self.format {
+            PatchFormat::Ips => ips::apply(&self.data, rom),
+            PatchFormat::Bps => bps::apply(&self.data, rom),
+            PatchFormat::Ups => ups::apply(&self.data, rom),
+        }?;
+        info!(
+            "Applied {} patch {:?}, {} bytes to {} bytes",
+            self.format.name(),
+            self.path,
+            rom.len(),
+            patched.len()
+        );
+        Ok(patched)
+    }
+}
+
+/// Reads the fields of a patch, failing with [`PatchError::CutOff`] at the
+/// end of the data
+pub(super) struct PatchReader<'a> {
+    data: &'a [u8],
+    pub position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Result<&'a [u8], PatchError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .ok_or(PatchError::CutOff)?;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or(PatchError::CutOff)?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Variable length number used by BPS and UPS. Each byte holds 7 bits,
+    /// least significant first, with the top bit set on the last byte.
+    pub fn number(&mut self) -> Result<usize, PatchError> {
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.byte()?;
+            let bits = ((byte & 0x7F) as usize).checked_mul(shift);
+            number = bits
+                .and_then(|bits| number.checked_add(bits))
+                .ok_or(PatchError::CutOff)?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift.checked_mul(128).ok_or(PatchError::CutOff)?;
+            number = number.checked_add(shift).ok_or(PatchError::CutOff)?;
+        }
+    }
+}
+
+/// Largest ROM a BPS or UPS patch may produce. Far larger than any NES ROM,
+/// so a corrupt size fails before it is allocated.
+const MAX_TARGET_SIZE: usize = 16 * 1024 * 1024;
+
+/// Check the target size read from a BPS or UPS patch before allocating it
+pub(super) fn check_target_size(target_size: usize) -> Result<usize, PatchError> {
+    match target_size <= MAX_TARGET_SIZE {
+        true => Ok(target_size),
+        false => Err(PatchError::OutOfBounds(target_size)),
+    }
+}
+
+/// The source, target and patch CRC32s at the end of a BPS or UPS patch.
+/// Checks the patch's own checksum and the source's, returning the
+/// target's to check once it has been built.
+pub(super) fn check_footer(patch: &[u8], source: &[u8]) -> Result<u32, PatchError> {
+    let footer = patch.len().checked_sub(12).ok_or(PatchError::CutOff)?;
+    let crc = |offset: usize| {
+        let bytes = &patch[footer + offset..footer + offset + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+
+    let actual = crate::checksum::crc32(&patch[..footer + 8]);
+    if crc(8) != actual {
+        return Err(PatchError::PatchChecksum {
+            expected: crc(8),
+            actual,
+        });
+    }
+    let actual = crate::checksum::crc32(source);
+    if crc(0) != actual {
+        return Err(PatchError::SourceChecksum {
+            expected: crc(0),
+            actual,
+        });
+    }
+    Ok(crc(4))
+}
+
+/// Check a patched ROM against the checksum from [`check_footer`]
+pub(super) fn check_target(target: &[u8], expected: u32) -> Result<(), PatchError> {
+    let actual = crate::checksum::crc32(target);
+    match actual == expected {
+        true => Ok(()),
+        false => Err(PatchError::TargetChecksum { expected, actual }),
+    }
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::WrongSignature(format) => write!(f, "Not an {} patch", format.name()),
+            PatchError::CutOff => write!(f, "Patch is cut off"),
+            PatchError::OutOfBounds(offset) => {
+                write!(f, "Patch goes outside the ROM at offset {:#X}", offset)
+            }
+            PatchError::PatchChecksum { expected, actual } => write!(
+                f,
+                "Patch is corrupt, its CRC32 is {:08X} but should be {:08X}",
+                actual, expected
+            ),
+            PatchError::SourceChecksum { expected, actual } => write!(
+                f,
+                "Patch is for a different ROM, with CRC32 {:08X} rather than {:08X}",
+                expected, actual
+            ),
+            PatchError::TargetChecksum { expected, actual } => write!(
+                f,
+                "Patched ROM has CRC32 {:08X} but should have {:08X}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
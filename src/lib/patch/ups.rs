@@ -0,0 +1,48 @@
+use super::patch::{check_footer, check_target, check_target_size, PatchError, PatchReader};
+use super::PatchFormat;
+
+const SIGNATURE: &[u8] = b"UPS1";
+
+/// # UPS
+/// `UPS1`, the source and target sizes as variable length numbers, then
+/// records until the 12 byte footer of CRC32s. Each record is the number
+/// of bytes to skip, then bytes to XOR with the source up to and including
+/// a zero byte.
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = PatchReader::new(patch);
+    if reader.bytes(SIGNATURE.len()) != Ok(SIGNATURE) {
+        return Err(PatchError::WrongSignature(PatchFormat::Ups));
+    }
+    let target_checksum = check_footer(patch, rom)?;
+    let records_end = patch.len() - 12;
+
+    // Sizes are checked by the source checksum
+    let _source_size = reader.number()?;
+    let target_size = check_target_size(reader.number()?)?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size.max(rom.len()), 0);
+    let mut offset: usize = 0;
+    while reader.position < records_end {
+        offset = offset
+            .checked_add(reader.number()?)
+            .filter(|&offset| offset <= target.len())
+            .ok_or(PatchError::OutOfBounds(offset))?;
+        loop {
+            let byte = reader.byte()?;
+            offset += 1;
+            // XOR with the zero is a no-op, and may be past the end
+            if byte == 0 {
+                break;
+            }
+            let target_byte = target
+                .get_mut(offset - 1)
+                .ok_or(PatchError::OutOfBounds(offset - 1))?;
+            *target_byte ^= byte;
+        }
+    }
+    target.truncate(target_size);
+
+    check_target(&target, target_checksum)?;
+    Ok(target)
+}
@@ -1,6 +1,7 @@
-use pixels::Pixels;
-
-use crate::{Cartridge, RcCell, Reset};
+use crate::{
+    savestate::{SaveState, SaveStateError, StateReader, StateWriter},
+    Cartridge, RcCell, Reset,
+};
 
 /// # Emulation Structure for the Picture Processing Unit (PPU)
 ///
@@ -15,7 +16,7 @@ use crate::{Cartridge, RcCell, Reset};
 /// ## Pallete Memory Map
 /// | Address         | Description                     |
 /// |-----------------|---------------------------------|
-/// | 0x3F00	      | Universal background color      |
+/// | 0x3F00          | Universal background color      |
 /// | 0x3F01 - 0x3F03 | Background palette 0            |
 /// | 0x3F05 - 0x3F07 | Background palette 1            |
 /// | 0x3F09 - 0x3F0B | Background palette 2            |
@@ -44,11 +45,52 @@ pub struct Ppu {
     pub pattern: [u8; 8 * 1024],
     // name_table: [u8; 2 * 1024],
     // palette: [u8; 32],
+    /// Output of the PPU. One palette index per pixel, row by row. Nothing
+    /// draws into it yet, so it stays at the backdrop colour.
+    frame: Vec<u8>,
 }
 
 impl Ppu {
     pub const SCREEN_WIDTH: usize = 340;
     pub const SCREEN_HEIGHT: usize = 240;
+    /// Visible pixels per scanline
+    pub const FRAME_WIDTH: usize = 256;
+    /// Visible scanlines per frame
+    pub const FRAME_HEIGHT: usize = 240;
+    /// Vblank starts on the second dot of this scanline
+    pub const VBLANK_SCANLINE: usize = 241;
+    /// Last scanline of the frame, where vblank ends on the second dot
+    pub const PRE_RENDER_SCANLINE: usize = 261;
+
+    /// Register at 0x2000
+    const PPUCTRL: usize = 0;
+    /// Register at 0x2002
+    const PPUSTATUS: usize = 2;
+    /// PPUCTRL: Generate an NMI at the start of vblank
+    const CTRL_NMI_ENABLE: u8 = 1 << 7;
+    /// PPUSTATUS: Vblank has started, cleared by reading PPUSTATUS
+    const STATUS_VBLANK: u8 = 1 << 7;
+
+    /// RGB values for each of the 64 colours the PPU can output
+    #[rustfmt::skip]
+    pub const PALETTE: [[u8; 3]; 64] = [
+        [84, 84, 84], [0, 30, 116], [8, 16, 144], [48, 0, 136],
+        [68, 0, 100], [92, 0, 48], [84, 4, 0], [60, 24, 0],
+        [32, 42, 0], [8, 58, 0], [0, 64, 0], [0, 60, 0],
+        [0, 50, 60], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+        [152, 150, 152], [8, 76, 196], [48, 50, 236], [92, 30, 228],
+        [136, 20, 176], [160, 20, 100], [152, 34, 32], [120, 60, 0],
+        [84, 90, 0], [40, 114, 0], [8, 124, 0], [0, 118, 40],
+        [0, 102, 120], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+        [236, 238, 236], [76, 154, 236], [120, 124, 236], [176, 98, 236],
+        [228, 84, 236], [236, 88, 180], [236, 106, 100], [212, 136, 32],
+        [160, 170, 0], [116, 196, 0], [76, 208, 32], [56, 204, 108],
+        [56, 180, 204], [60, 60, 60], [0, 0, 0], [0, 0, 0],
+        [236, 238, 236], [168, 204, 236], [188, 188, 236], [212, 178, 236],
+        [236, 174, 236], [236, 174, 212], [236, 180, 176], [228, 196, 144],
+        [204, 210, 120], [180, 222, 120], [168, 226, 144], [152, 226, 180],
+        [160, 214, 228], [160, 162, 160], [0, 0, 0], [0, 0, 0],
+    ];
 
     pub fn new() -> Self {
         Self {
@@ -59,24 +101,81 @@ impl Ppu {
             scanline: 0,
             pattern: [0; 8 * 1024],
             cycle: 0,
+            frame: vec![0; Self::FRAME_WIDTH * Self::FRAME_HEIGHT],
         }
     }
 
+    /// The last frame output by the PPU, as palette indices
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
     pub fn write_cpu(&mut self, address: u16, data: u8) {
         // WRONG!
         // TODO: Fix with the 8 cases
         self.memory[address as usize] = data;
     }
 
-    pub fn read_cpu(&self, address: u16) -> u8 {
+    pub fn read_cpu(&mut self, address: u16) -> u8 {
         // TODO: Fix with the 8 cases
+        let data = self.memory[address as usize];
+        if address as usize == Self::PPUSTATUS {
+            self.memory[Self::PPUSTATUS] &= !Self::STATUS_VBLANK;
+        }
+        data
+    }
+
+    /// Read a register without the side effects of reading it, such as
+    /// clearing the vblank flag or advancing the VRAM address
+    pub fn peek_cpu(&self, address: u16) -> u8 {
         self.memory[address as usize]
     }
 
+    /// Read from the PPU's own address space.
+    /// The pattern tables at 0x0000 - 0x1FFF are on the cartridge, as
+    /// CHR-ROM or CHR-RAM, and are reached through its mapper.
+    pub fn ppu_read(&self, address: u16) -> u8 {
+        let address = address & 0x3FFF;
+        match (address, &self.cartridge) {
+            (0x0000..=0x1FFF, Some(cartridge)) => cartridge.borrow_mut().ppu_read(address),
+            (0x0000..=0x1FFF, None) => self.pattern[address as usize],
+            // TODO: Name tables and palettes
+            _ => 0,
+        }
+    }
+
+    /// Write to the PPU's own address space. See [`Ppu::ppu_read`].
+    pub fn ppu_write(&mut self, address: u16, data: u8) {
+        let address = address & 0x3FFF;
+        match (address, &self.cartridge) {
+            (0x0000..=0x1FFF, Some(cartridge)) => cartridge.borrow_mut().ppu_write(address, data),
+            (0x0000..=0x1FFF, None) => self.pattern[address as usize] = data,
+            // TODO: Name tables and palettes
+            _ => {}
+        }
+    }
+
     pub fn insert_cartidge(&mut self, cartridge: Option<RcCell<Cartridge>>) {
         self.cartridge = cartridge;
     }
 
+    /// Scanline being drawn
+    pub fn scanline(&self) -> usize {
+        self.scanline
+    }
+
+    /// Dot within the scanline being drawn
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    /// Level of the PPU's NMI output: active while in vblank with NMIs
+    /// enabled in PPUCTRL
+    pub fn nmi_line(&self) -> bool {
+        self.memory[Self::PPUSTATUS] & Self::STATUS_VBLANK != 0
+            && self.memory[Self::PPUCTRL] & Self::CTRL_NMI_ENABLE != 0
+    }
+
     pub fn tick(&mut self) {
         // TODO: Render to some screen
 
@@ -85,10 +184,16 @@ impl Ppu {
         if self.cycle > Self::SCREEN_WIDTH {
             self.cycle = 0;
             self.scanline += 1;
-            if self.scanline > Self::SCREEN_HEIGHT {
+            if self.scanline > Self::PRE_RENDER_SCANLINE {
                 self.scanline = 0;
             }
         }
+
+        match (self.scanline, self.cycle) {
+            (Self::VBLANK_SCANLINE, 1) => self.memory[Self::PPUSTATUS] |= Self::STATUS_VBLANK,
+            (Self::PRE_RENDER_SCANLINE, 1) => self.memory[Self::PPUSTATUS] &= !Self::STATUS_VBLANK,
+            _ => {}
+        }
     }
 }
 
@@ -104,3 +209,24 @@ impl Reset for Ppu {
         // self.memory = [0; 10 * 1024];
     }
 }
+
+impl SaveState for Ppu {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self._name_table);
+        state.write_bytes(&self.memory);
+        state.write_bytes(&self._palette);
+        state.write_bytes(&self.pattern);
+        state.write_u64(self.scanline as u64);
+        state.write_u64(self.cycle as u64);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        state.read_bytes(&mut self._name_table)?;
+        state.read_bytes(&mut self.memory)?;
+        state.read_bytes(&mut self._palette)?;
+        state.read_bytes(&mut self.pattern)?;
+        self.scanline = state.read_u64()? as usize;
+        self.cycle = state.read_u64()? as usize;
+        Ok(())
+    }
+}
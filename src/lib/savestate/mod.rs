@@ -0,0 +1,7 @@
+mod rewind;
+mod run_ahead;
+mod state;
+
+pub use rewind::{Rewind, RewindConfig};
+pub use run_ahead::RunAhead;
+pub use state::{SaveState, SaveStateError, StateReader, StateWriter};
@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+
+use crate::{controllers::ControllerInput, Nes};
+
+/// # Rewind
+/// Bounded history of save states that lets the game be played backwards.
+///
+/// A full snapshot of the machine is captured every
+/// [`RewindConfig::interval`] frames. The newest snapshot is kept as-is.
+/// Every older snapshot is stored as a compressed delta against the snapshot
+/// that came after it, so consecutive snapshots, which are mostly identical,
+/// take up very little memory. The oldest snapshots are dropped once the
+/// history grows past [`RewindConfig::memory_budget`].
+///
+/// The input and the number of ticks of every frame since each snapshot is
+/// also recorded. Stepping back a single frame restores the nearest snapshot
+/// and re-simulates up to the frame before the current one.
+pub struct Rewind {
+    config: RewindConfig,
+    /// Newest snapshot, uncompressed
+    latest: Vec<u8>,
+    /// Oldest to newest. The last entry is for `latest` and has no delta
+    snapshots: VecDeque<Snapshot>,
+    /// Bytes used by every delta plus `latest`
+    memory_used: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RewindConfig {
+    /// Frames between snapshots. Higher values use less memory but make
+    /// each step back slower, as more frames need to be re-simulated.
+    pub interval: usize,
+    /// Upper bound, in bytes, on the memory used by the history
+    pub memory_budget: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self {
+            interval: 10,
+            memory_budget: 64 * 1024 * 1024,
+        }
+    }
+}
+
+struct Snapshot {
+    /// Changes needed to turn the next newer snapshot back into this one.
+    /// Empty for the newest snapshot.
+    delta: Vec<u8>,
+    /// Frames run since this snapshot was taken
+    frames: Vec<Frame>,
+}
+
+#[derive(Copy, Clone)]
+struct Frame {
+    input: ControllerInput,
+    ticks: u64,
+}
+
+impl Rewind {
+    pub fn new(config: RewindConfig) -> Self {
+        Self {
+            config,
+            latest: Vec::new(),
+            snapshots: VecDeque::new(),
+            memory_used: 0,
+        }
+    }
+
+    pub fn config(&self) -> RewindConfig {
+        self.config
+    }
+
+    /// Change the configuration. Clears the history.
+    pub fn set_config(&mut self, config: RewindConfig) {
+        *self = Self::new(config);
+    }
+
+    /// Forget all history, for when the machine is reset or the cartridge
+    /// changes.
+    pub fn clear(&mut self) {
+        self.set_config(self.config);
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    /// Number of frames that can currently be stepped back
+    pub fn frames_available(&self) -> usize {
+        self.snapshots
+            .iter()
+            .map(|snapshot| snapshot.frames.len())
+            .sum()
+    }
+
+    /// Run a frame of `ticks` with `input`, recording it in the history.
+    pub fn run_frame(&mut self, nes: &mut Nes, input: ControllerInput, ticks: u64) {
+        if ticks == 0 {
+            return;
+        }
+
+        let needs_snapshot = match self.snapshots.back() {
+            Some(newest) => newest.frames.len() >= self.config.interval.max(1),
+            None => true,
+        };
+        if needs_snapshot {
+            self.push_snapshot(nes.save_state());
+        }
+
+        self.snapshots
+            .back_mut()
+            .expect("Snapshot pushed above")
+            .frames
+            .push(Frame { input, ticks });
+        Self::simulate(nes, &Frame { input, ticks });
+    }
+
+    /// Step the machine back by a single frame.
+    /// Returns `false` if there is no more history to rewind through.
+    pub fn step_back(&mut self, nes: &mut Nes) -> bool {
+        // Exactly on a snapshot: move to the one before it
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|newest| newest.frames.is_empty())
+        {
+            if self.snapshots.len() < 2 {
+                return false;
+            }
+            self.pop_snapshot();
+        }
+
+        let newest = match self.snapshots.back_mut() {
+            Some(newest) => newest,
+            None => return false,
+        };
+        newest.frames.pop();
+
+        if nes.load_state(&self.latest).is_err() {
+            // Cartridge changed since the history was recorded
+            self.clear();
+            return false;
+        }
+        for frame in newest.frames.iter() {
+            Self::simulate(nes, frame);
+        }
+        true
+    }
+
+    fn simulate(nes: &mut Nes, frame: &Frame) {
+        nes.update_port_input(&frame.input);
+        for _ in 0..frame.ticks {
+            nes.tick();
+        }
+    }
+
+    fn push_snapshot(&mut self, state: Vec<u8>) {
+        if let Some(previous) = self.snapshots.back_mut() {
+            previous.delta = compress_delta(&state, &self.latest);
+            self.memory_used += previous.delta.len();
+        }
+        self.memory_used = self.memory_used - self.latest.len() + state.len();
+        self.latest = state;
+        self.snapshots.push_back(Snapshot {
+            delta: Vec::new(),
+            frames: Vec::with_capacity(self.config.interval),
+        });
+
+        // Never drop the newest snapshot, even if it alone is over budget
+        while self.memory_used > self.config.memory_budget && self.snapshots.len() > 1 {
+            let oldest = self.snapshots.pop_front().expect("More than one snapshot");
+            self.memory_used -= oldest.delta.len();
+        }
+    }
+
+    /// Drop the newest snapshot, making the one before it the newest
+    fn pop_snapshot(&mut self) {
+        self.snapshots.pop_back();
+        let previous = self
+            .snapshots
+            .back_mut()
+            .expect("Only called with at least two snapshots");
+        let state = apply_delta(&self.latest, &previous.delta);
+        self.memory_used =
+            self.memory_used - self.latest.len() - previous.delta.len() + state.len();
+        previous.delta = Vec::new();
+        self.latest = state;
+    }
+}
+
+/// Encode the difference between `base` and `target`, which can be applied
+/// to `base` with [`apply_delta`] to get `target` back.
+///
+/// The bytes of the two states are XORed, so unchanged bytes become zero,
+/// and then run-length encoded as a sequence of
+/// `(zeroes: varint, literal count: varint, literals)`.
+fn compress_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    write_varint(&mut delta, target.len());
+
+    let xored: Vec<u8> = target
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| byte ^ base.get(i).copied().unwrap_or(0))
+        .collect();
+
+    let mut position = 0;
+    while position < xored.len() {
+        let zeroes = xored[position..]
+            .iter()
+            .take_while(|&&byte| byte == 0)
+            .count();
+        position += zeroes;
+        let literals = xored[position..]
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .count();
+        write_varint(&mut delta, zeroes);
+        write_varint(&mut delta, literals);
+        delta.extend_from_slice(&xored[position..position + literals]);
+        position += literals;
+    }
+
+    delta
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut position = 0;
+    let length = read_varint(delta, &mut position);
+    let mut target: Vec<u8> = (0..length)
+        .map(|i| base.get(i).copied().unwrap_or(0))
+        .collect();
+
+    let mut output = 0;
+    while position < delta.len() {
+        output += read_varint(delta, &mut position);
+        let literals = read_varint(delta, &mut position);
+        for byte in &delta[position..position + literals] {
+            target[output] ^= byte;
+            output += 1;
+        }
+        position += literals;
+    }
+
+    target
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(buffer: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = buffer[*position];
+        *position += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{controllers::ControllerInput, Nes};
+
+/// # Run-Ahead
+/// Hides the input lag built into a game by showing frames from slightly in
+/// the future.
+///
+/// Many games only react to input a frame or more after reading it. After
+/// each real frame has been emulated, the machine is saved, emulated
+/// [`RunAhead::frames`] further frames with the same input, and then restored.
+/// The PPU's frame buffer is not part of a save state, so once the PPU
+/// renders it will still hold the output of the last speculative frame after
+/// the restore. Until then run-ahead only costs emulation time: the
+/// speculative frames are rolled back and nothing they drew is presented.
+///
+/// Audio is muted while running ahead, as those frames are rolled back and
+/// their sound will be generated again by the real frames.
+///
+/// <https://docs.libretro.com/guides/runahead/>
+pub struct RunAhead {
+    /// Frames to emulate ahead of the real frame. `0` disables run-ahead.
+    pub frames: usize,
+    /// Reused between frames so saving does not allocate
+    state: Vec<u8>,
+}
+
+impl RunAhead {
+    pub fn new(frames: usize) -> Self {
+        Self {
+            frames,
+            state: Vec::new(),
+        }
+    }
+
+    /// Run the speculative frames of `ticks` each with `input`, and roll the
+    /// machine back to where it was. Call after running the real frame.
+    pub fn run_ahead(&mut self, nes: &mut Nes, input: &ControllerInput, ticks: u64) {
+        if self.frames == 0 || ticks == 0 {
+            return;
+        }
+
+        nes.save_state_into(&mut self.state);
+        let audio_muted = nes.audio_muted();
+        nes.set_audio_muted(true);
+
+        nes.update_port_input(input);
+        for _ in 0..self.frames as u64 * ticks {
+            nes.tick();
+        }
+
+        nes.load_state(&self.state)
+            .expect("State saved this frame should load");
+        nes.set_audio_muted(audio_muted);
+    }
+}
+
+impl Default for RunAhead {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt::Display;
+
+/// # Save State
+/// Implemented by every part of the machine that holds state which needs to
+/// survive a save and restore.
+///
+/// Each component writes its own fields, in a fixed order, into a
+/// [`StateWriter`] and reads them back, in the same order, from a
+/// [`StateReader`].
+pub trait SaveState {
+    fn save_state(&self, state: &mut StateWriter);
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError>;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SaveStateError {
+    /// Data does not start with [`StateWriter::MAGIC`]
+    NotASaveState,
+    /// Saved by an incompatible version of the emulator
+    UnsupportedVersion(u16),
+    /// Saved while playing a different ROM. Holds the CRC-32 of the
+    /// expected and the loaded ROM
+    RomMismatch { expected: u32, found: u32 },
+    /// Ran out of data part-way through reading a value
+    UnexpectedEnd,
+    /// Sections were found out of order. Holds the expected and found tags
+    UnexpectedSection { expected: [u8; 4], found: [u8; 4] },
+    /// A value was read that is not valid for the field
+    InvalidValue(&'static str),
+    /// A component read a different number of bytes than its section holds
+    SectionLengthMismatch {
+        tag: [u8; 4],
+        expected: usize,
+        read: usize,
+    },
+}
+
+impl Display for SaveStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use SaveStateError::*;
+        match self {
+            NotASaveState => write!(f, "Not a save state"),
+            UnsupportedVersion(version) => write!(
+                f,
+                "Save state is from version {}, but only version {} is supported",
+                version,
+                StateWriter::VERSION
+            ),
+            RomMismatch { expected, found } => write!(
+                f,
+                "Save state is for a different ROM (CRC32 {:08X}, loaded ROM is {:08X})",
+                expected, found
+            ),
+            UnexpectedEnd => write!(f, "Save state is truncated"),
+            UnexpectedSection { expected, found } => write!(
+                f,
+                "Save state is corrupt: expected section {:?} but found {:?}",
+                String::from_utf8_lossy(expected),
+                String::from_utf8_lossy(found)
+            ),
+            InvalidValue(field) => write!(f, "Save state is corrupt: invalid {}", field),
+            SectionLengthMismatch {
+                tag,
+                expected,
+                read,
+            } => write!(
+                f,
+                "Save state is corrupt: section {:?} holds {} bytes but {} were read",
+                String::from_utf8_lossy(tag),
+                expected,
+                read
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveStateError {}
+
+/// Serializes state into a versioned binary blob.
+///
+/// ## Format
+/// All values are little-endian.
+///
+/// | Bytes   | Description                                      |
+/// |---------|--------------------------------------------------|
+/// | 0-3     | Constant `NESS`                                  |
+/// | 4-5     | Format version                                   |
+/// | 6-9     | CRC-32 of the loaded ROM (0 if none)             |
+/// | 10-     | Sections                                         |
+///
+/// Each section is a 4 byte tag, followed by the length of its data as a
+/// `u32`, followed by the data itself.
+pub struct StateWriter {
+    buffer: Vec<u8>,
+    open_section: Option<usize>,
+}
+
+impl StateWriter {
+    pub const MAGIC: [u8; 4] = *b"NESS";
+    /// Bump whenever the layout of any component's state changes
+    pub const VERSION: u16 = 9;
+    pub const HEADER_SIZE: usize = 10;
+
+    pub fn new(rom_crc: u32) -> Self {
+        Self::with_buffer(Vec::with_capacity(32 * 1024), rom_crc)
+    }
+
+    /// Write into an existing buffer, replacing its contents. Reusing the
+    /// buffer of a previous state avoids an allocation for every save.
+    pub fn with_buffer(mut buffer: Vec<u8>, rom_crc: u32) -> Self {
+        buffer.clear();
+        let mut writer = Self {
+            buffer,
+            open_section: None,
+        };
+        writer.write_bytes(&Self::MAGIC);
+        writer.write_u16(Self::VERSION);
+        writer.write_u32(rom_crc);
+        writer
+    }
+
+    /// Start a new section. Any section already open is closed.
+    pub fn begin_section(&mut self, tag: &[u8; 4]) {
+        self.end_section();
+        self.write_bytes(tag);
+        self.open_section = Some(self.buffer.len());
+        // Length is patched in once the section is closed
+        self.write_u32(0);
+    }
+
+    fn end_section(&mut self) {
+        if let Some(length_position) = self.open_section.take() {
+            let length = (self.buffer.len() - length_position - 4) as u32;
+            self.buffer[length_position..length_position + 4]
+                .copy_from_slice(&length.to_le_bytes());
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.end_section();
+        self.buffer
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Write a fixed size block of bytes. Read back with
+    /// [`StateReader::read_bytes`] into a buffer of the same size.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Write a variable sized block of bytes, prefixed with its length.
+    /// Read back with [`StateReader::read_vec`].
+    pub fn write_vec(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.write_bytes(bytes);
+    }
+}
+
+/// Reads back state written by a [`StateWriter`]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// Tag, start and length of the section being read
+    open_section: Option<([u8; 4], usize, usize)>,
+    pub version: u16,
+    pub rom_crc: u32,
+}
+
+impl<'a> StateReader<'a> {
+    /// Validate the header of a save state and start reading its sections
+    pub fn new(data: &'a [u8]) -> Result<Self, SaveStateError> {
+        let mut reader = Self {
+            data,
+            position: 0,
+            open_section: None,
+            version: 0,
+            rom_crc: 0,
+        };
+
+        let mut magic = [0; 4];
+        reader
+            .read_bytes(&mut magic)
+            .map_err(|_| SaveStateError::NotASaveState)?;
+        if magic != StateWriter::MAGIC {
+            return Err(SaveStateError::NotASaveState);
+        }
+
+        reader.version = reader.read_u16()?;
+        if reader.version != StateWriter::VERSION {
+            return Err(SaveStateError::UnsupportedVersion(reader.version));
+        }
+        reader.rom_crc = reader.read_u32()?;
+
+        Ok(reader)
+    }
+
+    /// Move to the start of the next section, checking it has the given tag.
+    /// Any section already open must have been read to its end.
+    pub fn section(&mut self, tag: &[u8; 4]) -> Result<(), SaveStateError> {
+        self.end_section()?;
+        let mut found = [0; 4];
+        self.read_bytes(&mut found)?;
+        if &found != tag {
+            return Err(SaveStateError::UnexpectedSection {
+                expected: *tag,
+                found,
+            });
+        }
+        let length = self.read_u32()? as usize;
+        if self.remaining() < length {
+            return Err(SaveStateError::UnexpectedEnd);
+        }
+        self.open_section = Some((*tag, self.position, length));
+        Ok(())
+    }
+
+    fn end_section(&mut self) -> Result<(), SaveStateError> {
+        match self.open_section.take() {
+            Some((tag, start, length)) if self.position - start != length => {
+                Err(SaveStateError::SectionLengthMismatch {
+                    tag,
+                    expected: length,
+                    read: self.position - start,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the last section was read to its end
+    pub fn finish(mut self) -> Result<(), SaveStateError> {
+        self.end_section()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveStateError> {
+        let mut bytes = [0; 1];
+        self.read_bytes(&mut bytes)?;
+        Ok(bytes[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SaveStateError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveStateError::InvalidValue("bool")),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveStateError> {
+        let mut bytes = [0; 2];
+        self.read_bytes(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveStateError> {
+        let mut bytes = [0; 4];
+        self.read_bytes(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveStateError> {
+        let mut bytes = [0; 8];
+        self.read_bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Fill `buffer` with the next `buffer.len()` bytes
+    pub fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<(), SaveStateError> {
+        let end = self.position + buffer.len();
+        if end > self.data.len() {
+            return Err(SaveStateError::UnexpectedEnd);
+        }
+        buffer.copy_from_slice(&self.data[self.position..end]);
+        self.position = end;
+        Ok(())
+    }
+
+    /// Read a length-prefixed block of bytes written by [`StateWriter::write_vec`]
+    pub fn read_vec(&mut self) -> Result<Vec<u8>, SaveStateError> {
+        let length = self.read_u32()? as usize;
+        if length > self.remaining() {
+            return Err(SaveStateError::UnexpectedEnd);
+        }
+        let mut buffer = vec![0; length];
+        self.read_bytes(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Read a length-prefixed block of bytes into an existing buffer, which
+    /// must already be the right size.
+    pub fn read_vec_into(
+        &mut self,
+        buffer: &mut [u8],
+        field: &'static str,
+    ) -> Result<(), SaveStateError> {
+        let length = self.read_u32()? as usize;
+        if length != buffer.len() {
+            return Err(SaveStateError::InvalidValue(field));
+        }
+        self.read_bytes(buffer)
+    }
+}
@@ -1,24 +1,31 @@
 // TODO: hmmmm - mayve declare elsewhere
 pub mod tests;
 
-use std::{cell::RefCell, rc::Rc};
+use std::path::PathBuf;
 
 use env_logger::Env;
-use lib::{egui::Gui, Cartridge, Nes};
-use log::{debug, error, info, trace, warn};
+use lib::{cartridge::convert_to_nes_two, egui::Gui, Nes};
+use log::{error, info};
 
 pub fn main() {
-    let mut app = Gui::new(Nes::default());
     startup_logger();
-    if let Some(cartridge_location) = std::env::args().nth(1) {
-        let nes: &mut Nes = &mut app.nes;
+    // Usage: rust_nes convert INPUT OUTPUT [--no-database]
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("convert") {
+        if let Err(message) = convert(&args[2..]) {
+            error!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-        let cartridge_file_contents =
-            std::fs::read(cartridge_location).expect("Failed to read cartridge file");
-        let cartridge = Cartridge::try_from(cartridge_file_contents)
-            .map(|c| Rc::new(RefCell::new(c)))
-            .expect("Failed to load cartridge");
-        nes.insert_cartidge(Some(cartridge));
+    let mut app = Gui::new(Nes::default());
+    // Usage: rust_nes [ROM] [DISK SYSTEM BIOS]
+    if let Some(bios_location) = std::env::args().nth(2) {
+        app.set_disk_bios(PathBuf::from(bios_location));
+    }
+    if let Some(cartridge_location) = std::env::args().nth(1) {
+        app.open_rom(PathBuf::from(cartridge_location));
     }
 
     info!("Starting Emulator");
@@ -30,6 +37,24 @@ pub fn main() {
     info!("Exiting now");
 }
 
+/// Rewrite an iNES file with a NES 2.0 header, corrected from the ROM
+/// database unless `--no-database` is given
+fn convert(args: &[String]) -> Result<(), String> {
+    let use_database = !args.iter().any(|arg| arg == "--no-database");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let (input, output) = match paths.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err("Usage: rust_nes convert INPUT OUTPUT [--no-database]".to_string()),
+    };
+
+    let file = std::fs::read(input).map_err(|e| format!("Error reading {input:?}: {e}"))?;
+    let converted = convert_to_nes_two(&file, use_database)
+        .map_err(|e| format!("Could not convert {input:?}: {e}"))?;
+    std::fs::write(output, converted).map_err(|e| format!("Error writing {output:?}: {e}"))?;
+    info!("Wrote {:?}", output);
+    Ok(())
+}
+
 fn startup_logger() {
     let (default_filter, default_write) = match in_release_build() {
         true => ("info", "always"),
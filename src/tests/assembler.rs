@@ -0,0 +1,142 @@
+use lib::assembler::{assemble, AssemblyErrorKind};
+use lib::disassembler::disassemble;
+use lib::opcodes::OpCode;
+
+fn bytes(source: &str) -> Vec<u8> {
+    assemble(source).expect("valid source").bytes
+}
+
+fn error(source: &str) -> AssemblyErrorKind {
+    assemble(source).expect_err("invalid source").kind
+}
+
+#[test]
+fn every_opcode_reassembles_from_its_disassembly() {
+    for raw in 0..=0xFF {
+        let memory = [raw, 0x34, 0x12];
+        let instruction = disassemble(0x8000, |address| memory[(address - 0x8000) as usize]);
+        let assembled = bytes(&format!(".org $8000\n{instruction}"));
+        let reassembled = disassemble(0x8000, |address| assembled[(address - 0x8000) as usize]);
+        assert_eq!(
+            reassembled.to_string(),
+            instruction.to_string(),
+            "{raw:02X}"
+        );
+        // Opcodes with duplicates assemble to the official one
+        if OpCode::is_official(raw) {
+            assert_eq!(assembled, instruction.bytes(), "{raw:02X}");
+        }
+    }
+}
+
+#[test]
+fn labels_and_zero_page_selection() {
+    let assembly = assemble(
+        "
+        .org $C000
+        start:  ldx #0          ; comment
+        loop:   lda table,X
+                sta $10,x
+                inx
+                cpx #3
+                bne loop
+                jmp start
+        table:  .byte 1, $02, %11, <table, >table
+                .word start, table+1
+        ",
+    )
+    .expect("valid source");
+
+    assert_eq!(assembly.origin, 0xC000);
+    assert_eq!(assembly.labels["start"], 0xC000);
+    assert_eq!(assembly.labels["loop"], 0xC002);
+    assert_eq!(assembly.labels["table"], 0xC00F);
+    assert_eq!(
+        assembly.bytes,
+        [
+            0xA2, 0x00, // LDX #$00
+            0xBD, 0x0F, 0xC0, // LDA table,X: forward, so absolute
+            0x95, 0x10, // STA $10,X: zero page
+            0xE8, // INX
+            0xE0, 0x03, // CPX #$03
+            0xD0, 0xF6, // BNE loop
+            0x4C, 0x00, 0xC0, // JMP start
+            0x01, 0x02, 0x03, 0x0F, 0xC0, // .byte
+            0x00, 0xC0, 0x10, 0xC0, // .word
+        ]
+    );
+}
+
+#[test]
+fn accumulator_indirect_and_unofficial_forms() {
+    assert_eq!(
+        bytes("asl\nasl a\nrol A\njmp ($02FF)\nlda ( $10 , x )\nsta ($10),Y"),
+        [0x0A, 0x0A, 0x2A, 0x6C, 0xFF, 0x02, 0xA1, 0x10, 0x91, 0x10]
+    );
+    assert_eq!(
+        bytes("lax $10\nisb $1234,y\nsbc #1\nnop\njam"),
+        [0xA7, 0x10, 0xFB, 0x34, 0x12, 0xE9, 0x01, 0xEA, 0x02]
+    );
+    // No zero page form, so absolute
+    assert_eq!(bytes("lda $10,y"), [0xB9, 0x10, 0x00]);
+    // The byte BRK skips is padding, and is left to the program
+    assert_eq!(bytes("brk\n.byte $12\nrti"), [0x00, 0x12, 0x40]);
+}
+
+#[test]
+fn org_fills_gaps_and_writes_to_memory() {
+    let assembly = assemble(".org $0400\nnop\n.org $0403\n.word *").expect("valid source");
+    assert_eq!(assembly.origin, 0x0400);
+    assert_eq!(assembly.bytes, [0xEA, 0x00, 0x00, 0x03, 0x04]);
+
+    let mut memory = [0; 0x800];
+    assembly.write_to(|address, data| memory[address as usize] = data);
+    assert_eq!(memory[0x0400..0x0405], [0xEA, 0x00, 0x00, 0x03, 0x04]);
+}
+
+#[test]
+fn errors_name_their_line() {
+    let first = assemble("nop\n\nfoo #1").expect_err("unknown mnemonic");
+    assert_eq!(first.line, 3);
+    assert_eq!(
+        first.kind,
+        AssemblyErrorKind::UnknownMnemonic("foo".to_string())
+    );
+
+    assert_eq!(
+        error(".fill 3"),
+        AssemblyErrorKind::UnknownDirective(".fill".to_string())
+    );
+    assert_eq!(
+        error("jmp nowhere"),
+        AssemblyErrorKind::UndefinedLabel("nowhere".to_string())
+    );
+    assert_eq!(
+        error("a: nop\na: nop"),
+        AssemblyErrorKind::DuplicateLabel("a".to_string())
+    );
+    assert_eq!(
+        error("jmp $10,x"),
+        AssemblyErrorKind::UnsupportedMode("JMP".to_string())
+    );
+    assert_eq!(
+        error("lda #$100"),
+        AssemblyErrorKind::ValueOutOfRange(0x100)
+    );
+    assert_eq!(
+        error("lda $1x"),
+        AssemblyErrorKind::BadOperand("$1x".to_string())
+    );
+    assert_eq!(
+        error("bne $0100"),
+        AssemblyErrorKind::BranchOutOfRange(0xFE)
+    );
+    assert_eq!(
+        error(".org $10\nnop\n.org 0"),
+        AssemblyErrorKind::OrgBackwards(0)
+    );
+    assert_eq!(
+        error(".org $FFFF\n.word 0"),
+        AssemblyErrorKind::PastEndOfMemory
+    );
+}
@@ -0,0 +1,512 @@
+use lib::cartridge::{
+    convert_to_nes_two, CartridgeParseError, ConsoleType, FileFormat, Header, HeaderFormat,
+    HeaderParseError, Mirroring, SectionCutOff, Timing, VsSystemType,
+};
+use lib::checksum::{crc32, sha1};
+use lib::Cartridge;
+
+use super::{header, nrom_image};
+
+#[test]
+fn program_ram_is_mapped_at_6000() {
+    let mut image = nrom_image(&[0xA9, 0x42]);
+    // Battery flag
+    image[6] |= 0b0000_0010;
+    let mut cartridge = Cartridge::try_from(image).expect("valid test rom");
+
+    assert!(cartridge.has_battery());
+    assert_eq!(cartridge.program_ram.len(), 8 * 1024);
+
+    cartridge.cpu_write(0x6000, 0x12);
+    cartridge.cpu_write(0x7FFF, 0x34);
+    assert_eq!(cartridge.cpu_read(0x6000), 0x12);
+    assert_eq!(cartridge.cpu_read(0x7FFF), 0x34);
+    assert_eq!(cartridge.program_ram[0x1FFF], 0x34);
+
+    // PRG-ROM is unaffected by writes and mirrored for a 16K ROM
+    cartridge.cpu_write(0x8000, 0xFF);
+    assert_eq!(cartridge.cpu_read(0x8000), 0xA9);
+    assert_eq!(cartridge.cpu_read(0xC001), 0x42);
+}
+
+#[test]
+fn nrom_256_is_not_mirrored() {
+    let mut image = nrom_image(&[0x11]);
+    // A second 16K PRG bank, before CHR
+    image[4] = 2;
+    let mut second_bank = vec![0; 16 * 1024];
+    second_bank[0] = 0x22;
+    image.splice(16 + 16 * 1024..16 + 16 * 1024, second_bank);
+    let mut cartridge = Cartridge::try_from(image).expect("valid test rom");
+
+    assert_eq!(cartridge.cpu_read(0x8000), 0x11);
+    assert_eq!(cartridge.cpu_read(0xC000), 0x22);
+    assert_eq!(cartridge.cpu_peek(0xC000), 0x22);
+    // CHR is still found after both banks
+    assert_eq!(cartridge.ppu_read(0x0001), 0x01);
+}
+
+#[test]
+fn load_program_ram_pads_short_saves() {
+    let mut cartridge = Cartridge::try_from(nrom_image(&[])).expect("valid test rom");
+    cartridge.program_ram.fill(0xFF);
+
+    cartridge.load_program_ram(&[1, 2, 3]);
+    assert_eq!(&cartridge.program_ram[..4], &[1, 2, 3, 0]);
+    assert_eq!(cartridge.program_ram.len(), 8 * 1024);
+}
+
+#[test]
+fn zero_chr_banks_gives_writable_chr_ram() {
+    let mut image = nrom_image(&[]);
+    // No CHR-ROM banks, and drop the CHR-ROM from the image
+    image[5] = 0;
+    image.truncate(16 + 16 * 1024);
+    let mut cartridge = Cartridge::try_from(image).expect("valid test rom");
+
+    assert!(cartridge.has_character_ram);
+    assert_eq!(cartridge.virtual_character_memory.len(), 8 * 1024);
+    cartridge.ppu_write(0x0000, 0xAB);
+    cartridge.ppu_write(0x1FFF, 0xCD);
+    assert_eq!(cartridge.ppu_read(0x0000), 0xAB);
+    assert_eq!(cartridge.ppu_read(0x1FFF), 0xCD);
+}
+
+#[test]
+fn chr_rom_smaller_than_a_bank_is_read_only() {
+    let mut image = nrom_image(&[]);
+    // NES 2.0, with 4K of CHR-ROM in exponent form: 2^12 * 1
+    image[7] = 0x08;
+    image[9] = 0xF0;
+    image[5] = 12 << 2;
+    image.truncate(16 + 16 * 1024 + 4 * 1024);
+    let mut cartridge = Cartridge::try_from(image).expect("valid test rom");
+
+    assert!(!cartridge.has_character_ram);
+    cartridge.ppu_write(0x0001, 0xAB);
+    assert_eq!(cartridge.ppu_read(0x0001), 0x01);
+}
+
+#[test]
+fn chr_rom_is_read_only() {
+    let mut cartridge = Cartridge::try_from(nrom_image(&[])).expect("valid test rom");
+
+    assert!(!cartridge.has_character_ram);
+    assert_eq!(cartridge.ppu_read(0x0005), 0x05);
+    cartridge.ppu_write(0x0005, 0xFF);
+    assert_eq!(cartridge.ppu_read(0x0005), 0x05);
+}
+
+#[test]
+fn header_format_detection() {
+    let mut image = nrom_image(&[]);
+    assert_eq!(header(&image).format, HeaderFormat::INes);
+
+    // Ripper's name over the end of the header
+    image[7..16].copy_from_slice(b"DiskDude!");
+    assert_eq!(header(&image).format, HeaderFormat::ArchaicINes);
+
+    image[7..16].fill(0);
+    image[12] = b'X';
+    assert_eq!(header(&image).format, HeaderFormat::INes07);
+
+    image[12] = 0;
+    image[7] = 0b0000_1000;
+    assert_eq!(header(&image).format, HeaderFormat::NesTwo);
+}
+
+#[test]
+fn nes_two_header_fields() {
+    let mut image = nrom_image(&[]);
+    image[6] = 0x12; // Mapper low nybble 1, battery
+    image[7] = 0x38; // Mapper middle nybble 3, NES 2.0, NES console
+    image[8] = 0x52; // Submapper 5, mapper high nybble 2
+    image[10] = 0x70; // No PRG-RAM, 8K PRG-NVRAM
+    image[11] = 0x07; // 8K CHR-RAM
+    image[12] = 0x03; // Dendy
+    image[14] = 0x01; // One misc ROM
+    image[15] = 0x23; // Expansion device 0x23
+    let header = header(&image);
+
+    assert_eq!(header.mapper_id(), 0x231);
+    assert_eq!(header.submapper_id(), 5);
+    assert_eq!(header.prg_rom_bytes(), 16 * 1024);
+    assert_eq!(header.chr_rom_bytes(), 8 * 1024);
+    assert_eq!(header.prg_ram_bytes(), 0);
+    assert_eq!(header.prg_nvram_bytes(), 8 * 1024);
+    assert_eq!(header.chr_ram_bytes(), 8 * 1024);
+    assert_eq!(header.chr_nvram_bytes(), 0);
+    assert_eq!(header.timing(), Timing::Dendy);
+    assert_eq!(header.console_type(), ConsoleType::Nes);
+    assert_eq!(header.misc_rom_count(), 1);
+    assert_eq!(header.default_expansion_device(), 0x23);
+
+    // Back to NROM so the cartridge can be built
+    image[6] = 0x02;
+    image[7] = 0x08;
+    image[8] = 0x00;
+    let cartridge = Cartridge::try_from(image).expect("valid test rom");
+    assert_eq!(cartridge.program_ram.len(), 8 * 1024);
+    assert!(cartridge.has_battery());
+}
+
+#[test]
+fn nes_two_exponent_rom_size_and_vs_system() {
+    let mut image = nrom_image(&[]);
+    image[7] = 0x09; // NES 2.0, Vs. System
+    image[9] = 0x0F; // PRG-ROM size uses exponent-multiplier notation
+    image[4] = 14 << 2; // 2^14 * (0 * 2 + 1) = 16K
+    image[13] = 0x52; // Vs. Dual System, RP2C04-0001
+    let header = header(&image);
+
+    assert_eq!(header.prg_rom_bytes(), 16 * 1024);
+    assert_eq!(header.console_type(), ConsoleType::VsSystem);
+    assert_eq!(
+        header.vs_system_type(),
+        Some(VsSystemType {
+            ppu: 0x2,
+            hardware: 0x5
+        })
+    );
+}
+
+#[test]
+fn oversized_nes_two_header_falls_back() {
+    let mut image = nrom_image(&[]);
+    image[7] = 0x08;
+    // 0x100 extra PRG banks, far more than the file holds
+    image[9] = 0x01;
+    let cartridge = Cartridge::try_from(image).expect("valid test rom");
+    assert_eq!(cartridge.header.format, HeaderFormat::INes07);
+    assert_eq!(cartridge.header.prg_rom_bytes(), 16 * 1024);
+}
+
+#[test]
+fn mirroring_from_flag_6() {
+    let mut image = nrom_image(&[]);
+    assert_eq!(header(&image).mirroring(), Mirroring::Horizontal);
+    image[6] = 0b0000_0001;
+    assert_eq!(header(&image).mirroring(), Mirroring::Vertical);
+    image[6] = 0b0000_1001;
+    assert_eq!(header(&image).mirroring(), Mirroring::FourScreen);
+    assert!(header(&image).has_four_screen());
+}
+
+#[test]
+fn metadata_hashes_rom_without_header() {
+    let image = nrom_image(&[0xEA]);
+    let cartridge = Cartridge::try_from(image.clone()).expect("valid test rom");
+
+    assert_eq!(cartridge.metadata.crc32, crc32(&image[16..]));
+    assert_eq!(
+        cartridge.metadata.prg_crc32,
+        crc32(&image[16..16 + 16 * 1024])
+    );
+    assert_eq!(
+        cartridge.metadata.chr_crc32,
+        crc32(&image[16 + 16 * 1024..])
+    );
+    assert_eq!(cartridge.metadata.sha1, sha1(&[&image[16..]]));
+    assert_eq!(cartridge.crc32(), cartridge.metadata.crc32);
+}
+
+#[test]
+fn truncated_files_are_errors() {
+    let mut image = nrom_image(&[]);
+    // Trainer present
+    image[6] |= 0b0000_0100;
+    image.splice(16..16, [0; 512]);
+
+    for length in 0..image.len() {
+        let result = Cartridge::try_from(&image[..length]);
+        let error = result.err().expect("truncated file should not parse");
+        let expected = match length {
+            0..=15 => matches!(error, CartridgeParseError::TooShort(l) if l == length),
+            16..=527 => matches!(
+                error,
+                CartridgeParseError::TrainerCutsOff(SectionCutOff {
+                    offset: 16,
+                    expected: 512,
+                    ..
+                })
+            ),
+            528..=16_911 => matches!(
+                error,
+                CartridgeParseError::ProgramRomCutsOff(SectionCutOff { offset: 528, .. })
+            ),
+            _ => matches!(
+                error,
+                CartridgeParseError::CharacterRomCutsOff(SectionCutOff { offset: 16_912, .. })
+            ),
+        };
+        assert!(expected, "length {}: unexpected {:?}", length, error);
+        assert!(!error.to_string().is_empty());
+    }
+    assert!(Cartridge::try_from(image).is_ok());
+}
+
+#[test]
+fn header_errors() {
+    let mut image = nrom_image(&[]);
+    image[4] = 0;
+    assert!(matches!(
+        Cartridge::try_from(&image),
+        Err(CartridgeParseError::NoProgramRom)
+    ));
+
+    image[4] = 1;
+    image[6] = 0x50;
+    assert!(matches!(
+        Cartridge::try_from(&image),
+        Err(CartridgeParseError::UnsupportedMapper(5))
+    ));
+
+    image[0] = b'X';
+    assert!(matches!(
+        Cartridge::try_from(&image),
+        Err(CartridgeParseError::InvalidHeader(
+            HeaderParseError::NoNesConstant([b'X', b'E', b'S', 0x1A])
+        ))
+    ));
+
+    assert!(matches!(
+        Header::try_from(&image[..10]),
+        Err(HeaderParseError::InvalidStreamLength(10))
+    ));
+}
+
+/// Stand-in for the fuzz target that runs with the regular tests: random
+/// headers over random, mostly short, bodies must never panic.
+#[test]
+fn random_input_never_panics() {
+    let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut next = || {
+        // xorshift64
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    for _ in 0..2_000 {
+        let length = (next() % (40 * 1024)) as usize;
+        let mut image: Vec<u8> = (0..length).map(|_| next() as u8).collect();
+        if image.len() >= 4 && next() % 4 != 0 {
+            image[..4].copy_from_slice(b"NES\x1A");
+        }
+        if let Ok(mut cartridge) = Cartridge::try_from(image) {
+            for address in [0x4020, 0x6000, 0x7FFF, 0x8000, 0xFFFF] {
+                cartridge.cpu_read(address);
+            }
+            cartridge.ppu_read(0x1FFF);
+        }
+    }
+}
+
+#[test]
+fn trainer_is_copied_to_7000() {
+    let mut image = nrom_image(&[]);
+    image[6] |= 0b0000_0100;
+    let trainer: Vec<u8> = (0..512).map(|i| (i * 7) as u8).collect();
+    image.splice(16..16, trainer.iter().copied());
+    let mut cartridge = Cartridge::try_from(image).expect("valid test rom");
+
+    assert_eq!(cartridge.trainer.as_deref(), Some(trainer.as_slice()));
+    assert_eq!(cartridge.cpu_read(0x6FFF), 0);
+    assert_eq!(cartridge.cpu_read(0x7000), trainer[0]);
+    assert_eq!(cartridge.cpu_read(0x71FF), trainer[511]);
+    assert_eq!(cartridge.cpu_read(0x7200), 0);
+
+    cartridge.cpu_write(0x7000, !trainer[0]);
+    cartridge.power_on();
+    assert_eq!(cartridge.cpu_read(0x7000), trainer[0]);
+}
+
+fn unif_chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = id.to_vec();
+    chunk.extend((data.len() as u32).to_le_bytes());
+    chunk.extend(data);
+    chunk
+}
+
+/// UNIF image of an NROM-256 board, with its PRG-ROM split over two chunks
+/// given out of order
+fn unif_image(board: &[u8]) -> Vec<u8> {
+    let mut image = b"UNIF".to_vec();
+    image.extend(7u32.to_le_bytes());
+    image.extend([0; 24]);
+    image.extend(unif_chunk(b"MAPR", board));
+    image.extend(unif_chunk(b"NAME", b"Test Game\0"));
+    image.extend(unif_chunk(b"PRG1", &[0x22; 16 * 1024]));
+    image.extend(unif_chunk(b"PRG0", &[0x11; 16 * 1024]));
+    image.extend(unif_chunk(b"CHR0", &[0x33; 8 * 1024]));
+    image.extend(unif_chunk(b"MIRR", &[1]));
+    image.extend(unif_chunk(b"BATR", &[1]));
+    image.extend(unif_chunk(b"TVCI", &[1]));
+    image
+}
+
+#[test]
+fn unif_builds_cartridge_from_chunks() {
+    let mut cartridge = Cartridge::try_from(unif_image(b"NES-NROM-256\0")).expect("valid UNIF");
+
+    assert_eq!(cartridge.cpu_read(0x8000), 0x11);
+    assert_eq!(cartridge.cpu_read(0xC000), 0x22);
+    assert_eq!(cartridge.ppu_read(0x0000), 0x33);
+
+    let header = cartridge.header;
+    assert_eq!(header.format, HeaderFormat::NesTwo);
+    assert_eq!(header.mapper_id(), 0);
+    assert_eq!(header.prg_rom_bytes(), 32 * 1024);
+    assert_eq!(header.chr_rom_bytes(), 8 * 1024);
+    assert_eq!(header.mirroring(), Mirroring::Vertical);
+    assert_eq!(header.timing(), Timing::Pal);
+    assert!(header.has_battery());
+    assert_eq!(header.prg_nvram_bytes(), 8 * 1024);
+    assert_eq!(cartridge.program_ram.len(), 8 * 1024);
+
+    let metadata = &cartridge.metadata;
+    assert_eq!(metadata.file_format, FileFormat::Unif);
+    assert_eq!(metadata.name.as_deref(), Some("Test Game"));
+    assert_eq!(metadata.board.as_deref(), Some("NES-NROM-256"));
+    let mut rom = vec![0x11; 16 * 1024];
+    rom.extend([0x22; 16 * 1024]);
+    rom.extend([0x33; 8 * 1024]);
+    assert_eq!(metadata.crc32, crc32(&rom));
+}
+
+#[test]
+fn unif_errors() {
+    assert!(matches!(
+        Cartridge::try_from(unif_image(b"UNL-MADE-UP\0")),
+        Err(CartridgeParseError::UnifUnknownBoard(board)) if board == "UNL-MADE-UP"
+    ));
+    // Known board that isn't emulated yet
+    assert!(matches!(
+        Cartridge::try_from(unif_image(b"HVC-SNROM\0")),
+        Err(CartridgeParseError::UnsupportedMapper(1))
+    ));
+
+    let image = unif_image(b"NROM\0");
+    let mapr_length = 8 + 5;
+    let mut no_board = image[..32].to_vec();
+    no_board.extend(&image[32 + mapr_length..]);
+    assert!(matches!(
+        Cartridge::try_from(no_board),
+        Err(CartridgeParseError::UnifNoBoard)
+    ));
+
+    assert!(matches!(
+        Cartridge::try_from(&image[..image.len() - 1]),
+        Err(CartridgeParseError::UnifChunkCutsOff(SectionCutOff {
+            expected: 1,
+            found: 0,
+            ..
+        }))
+    ));
+    assert!(matches!(
+        Cartridge::try_from(&image[..20]),
+        Err(CartridgeParseError::TooShort(20))
+    ));
+}
+
+#[test]
+fn nes_two_header_setters() {
+    let mut header = Header::nes_two();
+    header.set_mapper(0x123, 4);
+    header.set_prg_rom_bytes(24 * 1024);
+    header.set_chr_rom_bytes(0x1000 * 8 * 1024);
+    header.set_prg_ram_bytes(2 * 1024, 3 * 1024);
+    header.set_mirroring(Mirroring::FourScreen);
+    header.set_timing(Timing::Dendy);
+
+    assert_eq!(header.mapper_id(), 0x123);
+    assert_eq!(header.submapper_id(), 4);
+    assert_eq!(header.prg_rom_bytes(), 24 * 1024);
+    assert_eq!(header.chr_rom_bytes(), 0x1000 * 8 * 1024);
+    assert_eq!(header.prg_ram_bytes(), 2 * 1024);
+    assert_eq!(header.prg_nvram_bytes(), 4 * 1024);
+    assert_eq!(header.mirroring(), Mirroring::FourScreen);
+    assert_eq!(header.timing(), Timing::Dendy);
+}
+
+#[test]
+fn header_encodes_back_to_bytes() {
+    let mut nes_two = Header::nes_two();
+    nes_two.set_mapper(0x123, 4);
+    nes_two.set_prg_rom_bytes(3 * 16 * 1024);
+    nes_two.set_timing(Timing::Dendy);
+    let headers = [
+        nrom_image(&[])[..16].to_vec(),
+        b"NES\x1A\x02\x01\x01\x00\x00\x00\x00DiskDude!".to_vec()[..16].to_vec(),
+        nes_two.to_bytes().to_vec(),
+    ];
+    for bytes in headers {
+        let header = Header::try_from(bytes.as_slice()).expect("valid header");
+        assert_eq!(header.to_bytes().as_slice(), bytes.as_slice());
+    }
+}
+
+#[test]
+fn cartridge_writes_nes_file() {
+    let image = nrom_image(&[0xEA]);
+    let cartridge = Cartridge::try_from(image.as_slice()).expect("valid test rom");
+    assert_eq!(cartridge.to_nes_file(), image);
+
+    // Trainer, and CHR-RAM which is left out
+    let mut image = nrom_image(&[0xEA]);
+    image[5] = 0;
+    image[6] |= 0b0000_0100;
+    image.truncate(16 + 16 * 1024);
+    image.splice(16..16, [0x55; 512]);
+    let cartridge = Cartridge::try_from(image.as_slice()).expect("valid test rom");
+    assert_eq!(cartridge.to_nes_file(), image);
+}
+
+#[test]
+fn converts_ines_to_nes_two() {
+    let mut image = nrom_image(&[]);
+    // Four-screen, battery, mapper 0x42, PAL
+    image[6] |= 0b0010_1010;
+    image[7] |= 0b0100_0000;
+    image[9] = 1;
+    let ines = header(&image);
+    let nes_two = ines.to_nes_two();
+
+    assert_eq!(nes_two.format, HeaderFormat::NesTwo);
+    assert_eq!(nes_two.mapper_id(), ines.mapper_id());
+    assert_eq!(nes_two.mirroring(), Mirroring::FourScreen);
+    assert!(nes_two.has_battery());
+    assert_eq!(nes_two.timing(), Timing::Pal);
+    assert_eq!(nes_two.prg_rom_bytes(), 16 * 1024);
+    assert_eq!(nes_two.chr_rom_bytes(), 8 * 1024);
+    assert_eq!(nes_two.prg_nvram_bytes(), ines.prg_ram_bytes());
+    assert_eq!(nes_two.to_nes_two(), nes_two);
+}
+
+#[test]
+fn converts_ines_files_to_nes_two() {
+    let mut image = nrom_image(&[0xEA]);
+    // Vertical mirroring, battery, mapper 0x41 which isn't supported
+    image[6] |= 0b0001_0011;
+    image[7] |= 0b0100_0000;
+    image.extend(b"title");
+
+    let converted = convert_to_nes_two(&image, false).expect("valid ines file");
+    assert_eq!(converted.len(), image.len());
+    assert_eq!(converted[16..], image[16..]);
+    let header = Header::try_from(&converted[..16]).expect("valid header");
+    assert_eq!(header.format, HeaderFormat::NesTwo);
+    assert_eq!(header.mapper_id(), 0x41);
+    assert_eq!(header.mirroring(), Mirroring::Vertical);
+    assert!(header.has_battery());
+    assert_eq!(header.prg_nvram_bytes(), 8 * 1024);
+
+    // Already NES 2.0
+    let reconverted = convert_to_nes_two(&converted, false).expect("valid nes 2.0 file");
+    assert_eq!(reconverted, converted);
+    assert!(matches!(
+        convert_to_nes_two(&image[..100], false),
+        Err(CartridgeParseError::ProgramRomCutsOff(_))
+    ));
+}
@@ -0,0 +1,32 @@
+use lib::checksum::{crc32, sha1};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn sha1_known_answers() {
+    assert_eq!(hex(&sha1(&[])), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(
+        hex(&sha1(&[b"abc"])),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    // Padding spills into a second block
+    assert_eq!(
+        hex(&sha1(&[
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+        ])),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+    // Split input hashes the same as joined input
+    assert_eq!(sha1(&[b"ab", b"", b"c"]), sha1(&[b"abc"]));
+    assert_eq!(
+        hex(&sha1(&[&[b'a'; 1_000]])),
+        "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+    );
+}
@@ -0,0 +1,67 @@
+use lib::controllers::{Arkanoid, PortDevice, PowerPad};
+
+/// Strobe `device`, then read `count` values from it
+fn read_out(device: &mut dyn PortDevice, count: usize) -> Vec<u8> {
+    device.write_strobe(1);
+    device.write_strobe(0);
+    (0..count).map(|_| device.read()).collect()
+}
+
+#[test]
+fn arkanoid_sends_the_inverted_position_msb_first() {
+    let mut arkanoid = Arkanoid::new();
+    arkanoid.position = 0xA5;
+    arkanoid.fire = true;
+
+    let reads = read_out(&mut arkanoid, 10);
+    // !0xA5 = 0x5A, sent MSB first on D4
+    let d4: Vec<u8> = reads.iter().map(|data| (data >> 4) & 0x01).collect();
+    assert_eq!(d4, [0, 1, 0, 1, 1, 0, 1, 0, 1, 1]);
+    // Fire is on D3 for every read
+    assert!(reads.iter().all(|data| data & 0x08 != 0));
+    // Nothing else is driven
+    assert!(reads.iter().all(|data| data & !0x18 == 0));
+
+    arkanoid.fire = false;
+    assert_eq!(read_out(&mut arkanoid, 1), [0x00]);
+}
+
+#[test]
+fn arkanoid_reads_the_live_position_while_strobed() {
+    let mut arkanoid = Arkanoid::new();
+    arkanoid.position = 0x00;
+    arkanoid.write_strobe(1);
+    assert_eq!(arkanoid.read(), 0x10);
+    assert_eq!(arkanoid.read(), 0x10);
+    arkanoid.position = 0xFF;
+    assert_eq!(arkanoid.read(), 0x00);
+}
+
+#[test]
+fn power_pad_sends_every_button_in_stream_order() {
+    const D3: [usize; 8] = [2, 1, 5, 9, 6, 10, 11, 7];
+    const D4: [usize; 4] = [4, 3, 12, 8];
+
+    for button in 1..=12 {
+        let mut pad = PowerPad::new();
+        pad.buttons[button - 1] = true;
+
+        let reads = read_out(&mut pad, 10);
+        let d3: Vec<u8> = reads.iter().map(|data| (data >> 3) & 0x01).collect();
+        let d4: Vec<u8> = reads.iter().map(|data| (data >> 4) & 0x01).collect();
+
+        let mut expected_d3 = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1];
+        if let Some(bit) = D3.iter().position(|&b| b == button) {
+            expected_d3[bit] = 1;
+        }
+        // Four buttons, then 1s
+        let mut expected_d4 = [0, 0, 0, 0, 1, 1, 1, 1, 1, 1];
+        if let Some(bit) = D4.iter().position(|&b| b == button) {
+            expected_d4[bit] = 1;
+        }
+
+        assert_eq!(d3, expected_d3, "button {button} on D3");
+        assert_eq!(d4, expected_d4, "button {button} on D4");
+        assert!(reads.iter().all(|data| data & !0x18 == 0));
+    }
+}
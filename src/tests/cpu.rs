@@ -0,0 +1,625 @@
+use lib::{
+    assembler::assemble,
+    cpu::{AddressingMode, CpuFlag, FlatMemory, IrqSource},
+    opcodes::{OpCode, OpCodeType},
+    savestate::{SaveState, StateReader, StateWriter},
+    Cpu, Nes, Ppu, Reset,
+};
+
+use super::{nes_with_rom, nrom_image};
+
+/// Where test programs are loaded, clear of the zero page and stack
+const PROGRAM_START: u16 = 0x0400;
+/// Interrupt vectors of the test cartridge and flat memory
+const NMI_HANDLER: u16 = 0x0300;
+const IRQ_HANDLER: u16 = 0x0200;
+
+/// Load `program` into flat memory, and point the CPU at it
+fn cpu_with_program(program: &[u8]) -> Cpu<FlatMemory> {
+    let mut memory = FlatMemory::with_program(PROGRAM_START, program);
+    memory.ram[0xFFFA..0xFFFC].copy_from_slice(&NMI_HANDLER.to_le_bytes());
+    memory.ram[0xFFFE..].copy_from_slice(&IRQ_HANDLER.to_le_bytes());
+    let mut cpu = Cpu::with_memory(memory);
+    cpu.reset();
+    cpu.program_counter = PROGRAM_START;
+    // Let the reset sequence finish
+    cpu.step();
+    cpu
+}
+
+/// Assemble `source` at the program start, and point the CPU at it
+fn cpu_with_source(source: &str) -> Cpu<FlatMemory> {
+    let source = format!(".org ${PROGRAM_START:04X}\n{source}");
+    cpu_with_program(&assemble(&source).expect("valid test program").bytes)
+}
+
+/// Execute `count` instructions
+fn run(cpu: &mut Cpu<FlatMemory>, count: usize) {
+    for _ in 0..count {
+        cpu.step();
+    }
+}
+
+/// Load `program` into RAM on a full machine, for tests of interrupts, and
+/// point the CPU at it
+fn nes_with_program(program: &[u8]) -> Nes {
+    let mut prg = vec![0; 16 * 1024];
+    prg[0x3FFA..0x3FFC].copy_from_slice(&NMI_HANDLER.to_le_bytes());
+    prg[0x3FFE..].copy_from_slice(&IRQ_HANDLER.to_le_bytes());
+    let nes = nes_with_rom(&nrom_image(&prg));
+    for (offset, &byte) in program.iter().enumerate() {
+        nes.bus
+            .borrow_mut()
+            .write_cpu(PROGRAM_START + offset as u16, byte);
+    }
+    nes.cpu_mut().reset();
+    nes.cpu_mut().program_counter = PROGRAM_START;
+    // Let the reset sequence finish
+    nes.cpu_mut().step();
+    nes
+}
+
+/// Execute `count` instructions
+fn step(nes: &Nes, count: usize) {
+    for _ in 0..count {
+        nes.cpu_mut().step();
+    }
+}
+
+fn peek(nes: &Nes, address: u16) -> u8 {
+    nes.bus.borrow_mut().read_cpu(address)
+}
+
+fn poke(nes: &Nes, address: u16, data: u8) {
+    nes.bus.borrow_mut().write_cpu(address, data);
+}
+
+#[test]
+fn lax_loads_both_registers_and_sax_stores_their_and() {
+    let mut cpu = cpu_with_source(
+        "
+        lax $10
+        lda #$0F
+        sax $11
+        ",
+    );
+    cpu.bus.ram[0x10] = 0x37;
+
+    run(&mut cpu, 1);
+    assert_eq!(cpu.a_register, 0x37);
+    assert_eq!(cpu.x_register, 0x37);
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.peek(0x11), 0x07);
+}
+
+#[test]
+fn dcp_and_isc_modify_memory_then_compare_or_subtract() {
+    let mut cpu = cpu_with_program(&[
+        0xA9, 0x04, // LDA #$04
+        0xC7, 0x10, // DCP $10
+        0x38, // SEC
+        0xE7, 0x10, // ISC $10
+    ]);
+    cpu.bus.ram[0x10] = 0x05;
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.peek(0x10), 0x04);
+    assert!(cpu.get_flag(&CpuFlag::Zero));
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.peek(0x10), 0x05);
+    assert_eq!(cpu.a_register, 0xFF);
+    assert!(!cpu.get_flag(&CpuFlag::Carry));
+    assert!(cpu.get_flag(&CpuFlag::Negative));
+}
+
+#[test]
+fn shift_and_rotate_combinations_write_back_then_combine_with_a() {
+    let mut cpu = cpu_with_program(&[
+        0xA9, 0x01, // LDA #$01
+        0x07, 0x10, // SLO $10
+        0x27, 0x11, // RLA $11
+        0x47, 0x12, // SRE $12
+        0x67, 0x13, // RRA $13
+    ]);
+    cpu.bus.ram[0x10] = 0x81;
+    cpu.bus.ram[0x11] = 0x7F;
+    cpu.bus.ram[0x12] = 0x03;
+    cpu.bus.ram[0x13] = 0x02;
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.peek(0x10), 0x02);
+    assert_eq!(cpu.a_register, 0x03);
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+
+    // Carry rotates into bit 0
+    run(&mut cpu, 1);
+    assert_eq!(cpu.peek(0x11), 0xFF);
+    assert_eq!(cpu.a_register, 0x03);
+    assert!(!cpu.get_flag(&CpuFlag::Carry));
+
+    run(&mut cpu, 1);
+    assert_eq!(cpu.peek(0x12), 0x01);
+    assert_eq!(cpu.a_register, 0x02);
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+
+    // Carry rotates into bit 7, then bit 0 is carried into the addition
+    run(&mut cpu, 1);
+    assert_eq!(cpu.peek(0x13), 0x81);
+    assert_eq!(cpu.a_register, 0x83);
+}
+
+#[test]
+fn immediate_combinations() {
+    let mut cpu = cpu_with_program(&[
+        0xA9, 0xFF, // LDA #$FF
+        0x0B, 0x80, // ANC #$80
+        0xA9, 0xFF, // LDA #$FF
+        0x4B, 0x03, // ALR #$03
+        0xA9, 0xFF, // LDA #$FF
+        0x38, // SEC
+        0x6B, 0xFF, // ARR #$FF
+        0xA9, 0x0F, // LDA #$0F
+        0xA2, 0xFF, // LDX #$FF
+        0xCB, 0x01, // AXS #$01
+        0xA9, 0x05, // LDA #$05
+        0x38, // SEC
+        0xEB, 0x03, // SBC #$03
+    ]);
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.a_register, 0x80);
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.a_register, 0x01);
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+
+    run(&mut cpu, 3);
+    assert_eq!(cpu.a_register, 0xFF);
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+    assert!(!cpu.get_flag(&CpuFlag::Overflow));
+
+    run(&mut cpu, 3);
+    assert_eq!(cpu.x_register, 0x0E);
+    assert_eq!(cpu.a_register, 0x0F);
+    assert!(cpu.get_flag(&CpuFlag::Carry));
+
+    run(&mut cpu, 3);
+    assert_eq!(cpu.a_register, 0x02);
+}
+
+#[test]
+fn unofficial_nops_skip_their_operands() {
+    let mut cpu = cpu_with_program(&[
+        0x1A, // NOP
+        0x80, 0x12, // NOP #$12
+        0x04, 0x12, // NOP $12
+        0x14, 0x12, // NOP $12,X
+        0x0C, 0x34, 0x12, // NOP $1234
+        0xA2, 0x01, // LDX #$01
+        0x1C, 0xFF, 0x00, // NOP $00FF,X
+    ]);
+    let lengths = [1, 2, 2, 2, 3, 2, 3];
+
+    let mut address = PROGRAM_START;
+    let mut cycles = 0;
+    for length in lengths {
+        cycles = cpu.step();
+        address += length;
+        assert_eq!(cpu.program_counter, address);
+    }
+    // Indexing crossed a page
+    assert_eq!(cycles, 5);
+}
+
+#[test]
+fn unstable_stores_and_with_the_high_byte() {
+    let mut cpu = cpu_with_program(&[
+        0xA2, 0xFF, // LDX #$FF
+        0xA0, 0x00, // LDY #$00
+        0x9E, 0x00, 0x03, // SHX $0300,Y
+        0xA2, 0x05, // LDX #$05
+        0xA0, 0x01, // LDY #$01
+        0x9E, 0xFF, 0x02, // SHX $02FF,Y
+    ]);
+
+    run(&mut cpu, 3);
+    assert_eq!(cpu.peek(0x0300), 0x04);
+
+    // Crossing a page replaces the high byte of the address with the value
+    run(&mut cpu, 3);
+    assert_eq!(cpu.peek(0x0100), 0x01);
+}
+
+#[test]
+fn jam_halts_until_reset() {
+    let mut cpu = cpu_with_program(&[
+        0x02, // JAM
+        0xE8, // INX
+    ]);
+
+    run(&mut cpu, 3);
+    assert!(cpu.jammed);
+    assert_eq!(cpu.program_counter, PROGRAM_START);
+    assert_eq!(cpu.x_register, 0);
+
+    cpu.reset();
+    assert!(!cpu.jammed);
+}
+
+#[test]
+fn instructions_take_the_cycles_in_the_opcode_table() {
+    for raw in 0..=u8::MAX {
+        let opcode = OpCode::from(raw);
+        if matches!(opcode.code_type, OpCodeType::JAM) {
+            continue;
+        }
+        // Operands point into the zero page, and never cross a page
+        let mut cpu = cpu_with_program(&[raw, 0x10, 0x00]);
+        cpu.bus.ram[0x10] = 0x00;
+        cpu.bus.ram[0x11] = 0x02;
+
+        let cycles = cpu.step();
+        let expected = opcode.cycles as u64;
+        match opcode.addressing_mode {
+            // Whether the branch is taken depends on the flags after reset
+            AddressingMode::REL => assert!(
+                cycles == expected || cycles == expected + 1,
+                "{raw:02X} took {cycles} cycles"
+            ),
+            _ => assert_eq!(cycles, expected, "{raw:02X} took {cycles} cycles"),
+        }
+    }
+}
+
+#[test]
+fn page_crossings_add_cycles() {
+    let mut cpu = cpu_with_program(&[
+        0xA2, 0x01, // LDX #$01
+        0xBD, 0xFF, 0x02, // LDA $02FF,X
+        0x9D, 0x00, 0x03, // STA $0300,X
+        0x1E, 0xFF, 0x02, // ASL $02FF,X
+        0x18, // CLC
+        0x90, 0xF0, // BCC -$10
+    ]);
+    cpu.bus.ram[0x0300] = 0x21;
+
+    let cycles: Vec<u64> = (0..6).map(|_| cpu.step()).collect();
+    assert_eq!(cycles, [2, 5, 5, 7, 2, 4]);
+    assert_eq!(cpu.a_register, 0x21);
+    assert_eq!(cpu.peek(0x0300), 0x42);
+    assert_eq!(cpu.program_counter, PROGRAM_START + 14 - 0x10);
+}
+
+#[test]
+fn subroutines_and_break_return_to_the_caller() {
+    let mut cpu = cpu_with_program(&[
+        0x20, 0x10, 0x04, // JSR $0410
+        0xE8, // INX
+        0x00, // BRK
+    ]);
+    // Subroutine at $0410
+    cpu.bus.ram[0x0410] = 0xC8; // INY
+    cpu.bus.ram[0x0411] = 0x60; // RTS
+
+    run(&mut cpu, 4);
+    assert_eq!(cpu.y_register, 1);
+    assert_eq!(cpu.x_register, 1);
+    assert_eq!(cpu.program_counter, PROGRAM_START + 4);
+
+    // The return address skips the byte after BRK
+    run(&mut cpu, 1);
+    assert_eq!(cpu.program_counter, IRQ_HANDLER);
+    assert!(cpu.get_flag(&CpuFlag::Interrupt));
+    assert_eq!(cpu.peek(0x01FD), 0x04);
+    assert_eq!(cpu.peek(0x01FC), 0x06);
+    // Break is only set on the pushed copy
+    assert_ne!(cpu.peek(0x01FB) & CpuFlag::Break as u8, 0);
+    assert!(!cpu.get_flag(&CpuFlag::Break));
+}
+
+#[test]
+fn irq_waits_for_the_instruction_after_cli() {
+    let nes = nes_with_program(&[
+        0x58, // CLI
+        0xEA, // NOP
+        0xEA, // NOP
+    ]);
+    nes.cpu_mut().set_irq_line(IrqSource::Mapper, true);
+
+    // Still masked when CLI checked for interrupts
+    step(&nes, 2);
+    assert_eq!(nes.cpu_ref().program_counter, PROGRAM_START + 2);
+
+    assert_eq!(nes.cpu_mut().step(), 7);
+    assert_eq!(nes.cpu_ref().program_counter, IRQ_HANDLER);
+    // Pushed without Break, and with the address of the next instruction
+    assert_eq!(peek(&nes, 0x01FC), 0x02);
+    assert_eq!(peek(&nes, 0x01FB) & CpuFlag::Break as u8, 0);
+    assert!(nes.cpu_ref().get_flag(&CpuFlag::Interrupt));
+
+    // Masked while the handler runs, and not taken again once released
+    step(&nes, 1);
+    nes.cpu_mut().set_irq_line(IrqSource::Mapper, false);
+    assert!(!nes.cpu_ref().irq_line());
+}
+
+#[test]
+fn nmi_triggers_on_the_rising_edge_only() {
+    let nes = nes_with_program(&[
+        0xEA, // NOP
+        0xEA, // NOP
+        0xEA, // NOP
+    ]);
+    // Handler: NOP, RTI
+    poke(&nes, NMI_HANDLER, 0xEA);
+    poke(&nes, NMI_HANDLER + 1, 0x40);
+
+    // Noticed during the first NOP
+    nes.cpu_mut().set_nmi_line(true);
+    step(&nes, 2);
+    assert_eq!(nes.cpu_ref().program_counter, NMI_HANDLER);
+
+    // Holding the line doesn't trigger it again
+    step(&nes, 3);
+    assert_eq!(nes.cpu_ref().program_counter, PROGRAM_START + 2);
+
+    nes.cpu_mut().set_nmi_line(false);
+    nes.cpu_mut().set_nmi_line(true);
+    step(&nes, 2);
+    assert_eq!(nes.cpu_ref().program_counter, NMI_HANDLER);
+}
+
+#[test]
+fn nmi_during_brk_hijacks_it() {
+    let nes = nes_with_program(&[
+        0x00, 0x00, // BRK
+    ]);
+
+    for _ in 0..3 {
+        nes.cpu_mut().tick();
+    }
+    nes.cpu_mut().set_nmi_line(true);
+    nes.cpu_mut().step();
+
+    assert_eq!(nes.cpu_ref().program_counter, NMI_HANDLER);
+    // Still pushed as a BRK
+    assert_ne!(peek(&nes, 0x01FB) & CpuFlag::Break as u8, 0);
+    // The first instruction of the handler runs before the NMI is noticed again
+    assert!(!nes.cpu_ref().nmi_pending);
+}
+
+/// Run a cartridge that counts NMIs at $10, with PPUCTRL set to `ppuctrl`,
+/// for `frames` frames
+fn nmis_taken(ppuctrl: u8, frames: usize) -> u8 {
+    let source = format!(
+        "
+        .org $8000
+        reset:
+            lda #${ppuctrl:02X}
+            sta $2000
+        loop:
+            jmp loop
+        nmi:
+            inc $10
+            lda $2002       ; acknowledge vblank
+            rti
+        "
+    );
+    let program = assemble(&source).expect("valid test program");
+    let mut prg = vec![0; 16 * 1024];
+    prg[..program.bytes.len()].copy_from_slice(&program.bytes);
+    prg[0x3FFA..0x3FFC].copy_from_slice(&program.labels["nmi"].to_le_bytes());
+    prg[0x3FFC..0x3FFE].copy_from_slice(&program.labels["reset"].to_le_bytes());
+
+    let mut nes = nes_with_rom(&nrom_image(&prg));
+    nes.reset();
+    let dots_per_frame = (Ppu::SCREEN_WIDTH + 1) * (Ppu::PRE_RENDER_SCANLINE + 1);
+    for _ in 0..frames * dots_per_frame {
+        nes.tick();
+    }
+    peek(&nes, 0x10)
+}
+
+#[test]
+fn vblank_nmi_runs_the_handler_once_a_frame() {
+    assert_eq!(nmis_taken(0x80, 3), 3);
+    // Disabled in PPUCTRL
+    assert_eq!(nmis_taken(0x00, 3), 0);
+}
+
+#[test]
+fn taken_branch_on_the_same_page_delays_interrupts() {
+    let nes = nes_with_program(&[
+        0x58, // CLI
+        0x18, // CLC
+        0x90, 0x00, // BCC +0
+        0xEA, // NOP
+    ]);
+    step(&nes, 2);
+
+    // Raised after the branch's first cycle
+    nes.cpu_mut().tick();
+    nes.cpu_mut().set_irq_line(IrqSource::Mapper, true);
+    assert_eq!(nes.cpu_mut().step(), 2);
+    assert_eq!(nes.cpu_ref().program_counter, PROGRAM_START + 4);
+
+    // The NOP runs first
+    assert_eq!(nes.cpu_mut().step(), 2);
+    assert_eq!(nes.cpu_mut().step(), 7);
+    assert_eq!(nes.cpu_ref().program_counter, IRQ_HANDLER);
+}
+
+#[test]
+fn runs_on_flat_memory() {
+    let program = assemble(
+        "
+        .org $8000
+        start:  ldx #3
+        loop:   inc $F000,x     ; RAM everywhere, no mirrors or registers
+                dex
+                bpl loop
+                jam
+        .org $FFFC
+                .word start
+        ",
+    )
+    .expect("valid test program");
+    let mut cpu = Cpu::with_memory(FlatMemory::with_program(program.origin, &program.bytes));
+    cpu.reset();
+    assert_eq!(cpu.program_counter, 0x8000);
+
+    while !cpu.jammed {
+        cpu.step();
+    }
+    assert_eq!(cpu.bus.ram[0xF000..0xF004], [1, 1, 1, 1]);
+    assert_eq!(cpu.peek(0xF004), 0);
+}
+
+#[test]
+fn decimal_mode_is_only_bcd_when_enabled() {
+    let source = "
+        .org $8000
+        sed
+        clc
+        lda #$58
+        adc #$46        ; 58 + 46 = 104
+        sta $00
+        php
+        sec
+        lda #$40
+        sbc #$13        ; 40 - 13 = 27
+        sta $01
+        jam
+    ";
+    let program = assemble(source).expect("valid test program");
+    let run = |decimal_mode| {
+        let mut cpu = Cpu::with_memory(FlatMemory::with_program(0x8000, &program.bytes));
+        cpu.decimal_mode = decimal_mode;
+        cpu.reset();
+        cpu.program_counter = 0x8000;
+        while !cpu.jammed {
+            cpu.step();
+        }
+        cpu
+    };
+
+    let cpu = run(true);
+    assert_eq!(cpu.peek(0x00), 0x04);
+    assert_ne!(cpu.peek(0x01FD) & CpuFlag::Carry as u8, 0);
+    assert_eq!(cpu.peek(0x01), 0x27);
+
+    // Binary on the NES
+    let cpu = run(false);
+    assert_eq!(cpu.peek(0x00), 0x9E);
+    assert_eq!(cpu.peek(0x01), 0x2D);
+}
+
+#[test]
+fn decimal_mode_survives_a_save_state() {
+    let mut cpu = cpu_with_program(&[0xEA]);
+    cpu.decimal_mode = true;
+    let mut state = StateWriter::new(0);
+    cpu.save_state(&mut state);
+    let state = state.finish();
+
+    let mut restored = cpu_with_program(&[0xEA]);
+    let mut reader = StateReader::new(&state).expect("valid state");
+    restored.load_state(&mut reader).expect("state should load");
+    assert!(restored.decimal_mode);
+}
+
+/// ADC in decimal mode on an NMOS 6502, written the way VICE does, as an
+/// independent check of the emulator's BCD. Returns A and the flags.
+fn nmos_decimal_adc(a: u8, value: u8, carry: bool) -> (u8, u8) {
+    let (a, value, carry) = (a as u32, value as u32, carry as u32);
+    let mut sum = (a & 0x0F) + (value & 0x0F) + carry;
+    if sum > 0x09 {
+        sum += 0x06;
+    }
+    sum = match sum <= 0x0F {
+        true => (sum & 0x0F) + (a & 0xF0) + (value & 0xF0),
+        false => (sum & 0x0F) + (a & 0xF0) + (value & 0xF0) + 0x10,
+    };
+    let zero = (a + value + carry) & 0xFF == 0;
+    let negative = sum & 0x80 != 0;
+    let overflow = (a ^ sum) & 0x80 != 0 && (a ^ value) & 0x80 == 0;
+    if sum & 0x1F0 > 0x90 {
+        sum += 0x60;
+    }
+    let carry = sum & 0xFF0 > 0xF0;
+    (sum as u8, flags(carry, zero, negative, overflow))
+}
+
+/// SBC in decimal mode on an NMOS 6502, see [`nmos_decimal_adc`]
+fn nmos_decimal_sbc(a: u8, value: u8, carry: bool) -> (u8, u8) {
+    let (a, value, borrow) = (a as u32, value as u32, !carry as u32);
+    let binary = a.wrapping_sub(value).wrapping_sub(borrow);
+    let mut difference = (a & 0x0F).wrapping_sub(value & 0x0F).wrapping_sub(borrow);
+    difference = match difference & 0x10 != 0 {
+        true => {
+            (difference.wrapping_sub(6) & 0x0F)
+                | (a & 0xF0).wrapping_sub(value & 0xF0).wrapping_sub(0x10)
+        }
+        false => (difference & 0x0F) | (a & 0xF0).wrapping_sub(value & 0xF0),
+    };
+    if difference & 0x100 != 0 {
+        difference = difference.wrapping_sub(0x60);
+    }
+    let carry = binary < 0x100;
+    let zero = binary & 0xFF == 0;
+    let negative = binary & 0x80 != 0;
+    let overflow = (a ^ binary) & 0x80 != 0 && (a ^ value) & 0x80 != 0;
+    (difference as u8, flags(carry, zero, negative, overflow))
+}
+
+fn flags(carry: bool, zero: bool, negative: bool, overflow: bool) -> u8 {
+    (carry as u8 * CpuFlag::Carry as u8)
+        | (zero as u8 * CpuFlag::Zero as u8)
+        | (negative as u8 * CpuFlag::Negative as u8)
+        | (overflow as u8 * CpuFlag::Overflow as u8)
+}
+
+#[test]
+fn decimal_mode_matches_the_nmos_6502_for_every_input() {
+    let mut cpu = cpu_with_source("adc #0\nsbc #0");
+    cpu.decimal_mode = true;
+    let checked = CpuFlag::Carry as u8
+        | CpuFlag::Zero as u8
+        | CpuFlag::Negative as u8
+        | CpuFlag::Overflow as u8;
+
+    let cases = [
+        (
+            PROGRAM_START,
+            nmos_decimal_adc as fn(u8, u8, bool) -> (u8, u8),
+        ),
+        (PROGRAM_START + 2, nmos_decimal_sbc),
+    ];
+    for (address, reference) in cases {
+        for a in 0..=u8::MAX {
+            for value in 0..=u8::MAX {
+                for carry in [false, true] {
+                    cpu.bus.ram[address as usize + 1] = value;
+                    cpu.program_counter = address;
+                    cpu.a_register = a;
+                    cpu.status_register = CpuFlag::Decimal as u8 | carry as u8;
+                    cpu.step();
+
+                    let expected = reference(a, value, carry);
+                    let actual = (cpu.a_register, cpu.status_register & checked);
+                    assert_eq!(
+                        actual,
+                        expected,
+                        "{:02X} with A={a:02X} #${value:02X} C={}",
+                        cpu.peek(address),
+                        carry as u8
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use lib::cartridge::{HeaderFormat, Mirroring, Timing};
+use lib::checksum::crc32;
+use lib::database::{DatabaseError, RomDatabase};
+use lib::Cartridge;
+
+use super::{header, nrom_image};
+
+#[test]
+fn finds_games_by_crc() {
+    let image = nrom_image(&[0xEA]);
+    let crc = crc32(&image[16..]);
+    let xml = format!(
+        r#"<?xml version="1.0"?>
+        <!-- Test database -->
+        <database>
+          <game name="Test &amp; Game" publisher="Tester">
+            <cartridge system="NES-PAL" crc="{crc:08X}">
+              <board type="NES-NROM-128" mapper="0" mirroring="V" battery="1"/>
+              <prg size="16k"/>
+            </cartridge>
+          </game>
+          <game name="Split">
+            <cartridge>
+              <prg crc="0000ABCD"/>
+              <chr crc="00001234"/>
+            </cartridge>
+          </game>
+        </database>"#
+    );
+    let database = RomDatabase::from_xml(&xml).expect("valid database");
+    assert_eq!(database.len(), 2);
+
+    let cartridge = Cartridge::try_from(image).expect("valid test rom");
+    let game = database
+        .find(&cartridge.metadata)
+        .expect("game in database");
+    assert_eq!(game.name, "Test & Game");
+    assert_eq!(game.publisher.as_deref(), Some("Tester"));
+    assert_eq!(game.board.as_deref(), Some("NES-NROM-128"));
+    assert_eq!(game.mirroring, Some(Mirroring::Vertical));
+    assert_eq!(game.timing, Some(Timing::Pal));
+
+    let mut metadata = cartridge.metadata.clone();
+    metadata.crc32 = 0;
+    assert_eq!(database.find(&metadata), None);
+    metadata.prg_crc32 = 0xABCD;
+    metadata.chr_crc32 = 0x1234;
+    assert_eq!(
+        database.find(&metadata).map(|game| game.name.as_str()),
+        Some("Split")
+    );
+}
+
+#[test]
+fn corrects_headers() {
+    let image = nrom_image(&[]);
+    let ines = header(&image);
+    let xml = format!(
+        r#"<database><game name="Test"><cartridge system="Dendy" crc="{:08X}">
+            <board mapper="0" submapper="1" mirroring="V" battery="1"/>
+        </cartridge></game></database>"#,
+        crc32(&image[16..])
+    );
+    let database = RomDatabase::from_xml(&xml).expect("valid database");
+    let cartridge = Cartridge::try_from(image).expect("valid test rom");
+    let game = database
+        .find(&cartridge.metadata)
+        .expect("game in database");
+
+    let corrected = game.correct_header(&ines);
+    assert_eq!(corrected.format, HeaderFormat::NesTwo);
+    assert_eq!(corrected.mapper_id(), 0);
+    assert_eq!(corrected.submapper_id(), 1);
+    assert_eq!(corrected.mirroring(), Mirroring::Vertical);
+    assert!(corrected.has_battery());
+    assert_eq!(corrected.prg_ram_bytes(), 0);
+    assert_eq!(corrected.prg_nvram_bytes(), ines.prg_ram_bytes());
+    assert_eq!(corrected.timing(), Timing::Dendy);
+    assert_eq!(corrected.prg_rom_bytes(), ines.prg_rom_bytes());
+    assert_eq!(corrected.chr_rom_bytes(), ines.chr_rom_bytes());
+
+    // Nothing to correct leaves the header alone
+    assert_eq!(game.correct_header(&corrected), corrected);
+}
+
+#[test]
+fn database_errors() {
+    assert!(matches!(
+        RomDatabase::from_xml("<database><game name=\"a\">"),
+        Err(DatabaseError::InvalidXml(_))
+    ));
+    assert!(matches!(
+        RomDatabase::from_xml("<game/>"),
+        Err(DatabaseError::MissingName)
+    ));
+    assert!(matches!(
+        RomDatabase::from_xml(r#"<game name="a"><cartridge/></game>"#),
+        Err(DatabaseError::MissingCrc(name)) if name == "a"
+    ));
+    assert!(matches!(
+        RomDatabase::from_xml(r#"<game name="a"><cartridge crc="XYZ"/></game>"#),
+        Err(DatabaseError::InvalidValue(attribute, _)) if attribute == "crc"
+    ));
+}
+
+#[test]
+fn bundled_database_loads() {
+    assert!(RomDatabase::bundled().len() > 2000);
+}
+
+#[test]
+fn bundled_database_corrects_bad_dumps() {
+    let image = nrom_image(&[]);
+    let ines = header(&image);
+    assert_eq!(ines.mapper_id(), 0);
+    assert_eq!(ines.mirroring(), Mirroring::Horizontal);
+    let mut metadata = Cartridge::try_from(image).expect("valid test rom").metadata;
+
+    // Super Mario Bros. + Duck Hunt, dumped as NROM instead of GxROM
+    metadata.crc32 = 0xD26EFD78;
+    let game = RomDatabase::bundled()
+        .find(&metadata)
+        .expect("game in database");
+    assert_eq!(game.name, "Super Mario Bros. + Duck Hunt (USA)");
+    let corrected = game.correct_header(&ines);
+    assert_eq!(corrected.format, HeaderFormat::NesTwo);
+    assert_eq!(corrected.mapper_id(), 66);
+    assert_eq!(corrected.mirroring(), Mirroring::Vertical);
+    assert_eq!(corrected.timing(), Timing::Ntsc);
+
+    // Super Mario Bros., dumped with horizontal mirroring
+    metadata.crc32 = 0xD445F698;
+    let game = RomDatabase::bundled()
+        .find(&metadata)
+        .expect("game in database");
+    assert_eq!(game.name, "Super Mario Bros. (World)");
+    let corrected = game.correct_header(&ines);
+    assert_eq!(corrected.mapper_id(), 0);
+    assert_eq!(corrected.mirroring(), Mirroring::Vertical);
+    assert!(!corrected.has_battery());
+}
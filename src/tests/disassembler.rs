@@ -0,0 +1,86 @@
+use lib::disassembler::{disassemble, disassemble_range};
+use lib::opcodes::OpCodeType;
+
+/// Peek into `memory`, placed at `base`
+fn peek_at(base: u16, memory: &[u8]) -> impl Fn(u16) -> u8 + '_ {
+    move |address| {
+        memory
+            .get(address.wrapping_sub(base) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[test]
+fn operands_are_formatted_per_addressing_mode() {
+    let cases: [(&[u8], &str); 15] = [
+        (&[0xEA], "NOP"),
+        // The byte after BRK is skipped when it runs, but isn't its operand
+        (&[0x00], "BRK"),
+        (&[0x0A], "ASL A"),
+        (&[0xA9, 0x10], "LDA #$10"),
+        (&[0xA5, 0x10], "LDA $10"),
+        (&[0xB5, 0x10], "LDA $10,X"),
+        (&[0xB6, 0x10], "LDX $10,Y"),
+        (&[0xAD, 0x34, 0x12], "LDA $1234"),
+        (&[0xBD, 0x34, 0x12], "LDA $1234,X"),
+        (&[0xB9, 0x34, 0x12], "LDA $1234,Y"),
+        (&[0x6C, 0xFF, 0x02], "JMP ($02FF)"),
+        (&[0xA1, 0x10], "LDA ($10,X)"),
+        (&[0xB1, 0x10], "LDA ($10),Y"),
+        // Branches show their target, relative to the next instruction
+        (&[0xD0, 0xFE], "BNE $8000"),
+        (&[0xD0, 0x10], "BNE $8012"),
+    ];
+
+    for (bytes, text) in cases {
+        let instruction = disassemble(0x8000, peek_at(0x8000, bytes));
+        assert_eq!(instruction.to_string(), text);
+        assert_eq!(instruction.bytes(), bytes);
+        assert_eq!(instruction.length() as usize, bytes.len());
+    }
+}
+
+#[test]
+fn unofficial_opcodes_are_marked() {
+    let instruction = disassemble(0, peek_at(0, &[0xA7, 0x02]));
+    assert_eq!(instruction.opcode.code_type, OpCodeType::LAX);
+    assert_eq!(instruction.to_string(), "LAX $02");
+    assert!(!instruction.is_official());
+    assert!(disassemble(0, peek_at(0, &[0xA5, 0x02])).is_official());
+}
+
+#[test]
+fn ranges_follow_instruction_lengths() {
+    let program = [
+        0xA2, 0x00, // LDX #$00
+        0xE8, // INX
+        0x8E, 0x00, 0x02, // STX $0200
+        0x4C, 0x00, 0xC0, // JMP $C000
+    ];
+    let listing: Vec<String> = disassemble_range(0xC000, 4, peek_at(0xC000, &program))
+        .iter()
+        .map(|instruction| format!("{:04X} {instruction}", instruction.address))
+        .collect();
+    assert_eq!(
+        listing,
+        [
+            "C000 LDX #$00",
+            "C002 INX",
+            "C003 STX $0200",
+            "C006 JMP $C000"
+        ]
+    );
+}
+
+#[test]
+fn operands_wrap_around_the_address_space() {
+    let instruction = disassemble(0xFFFF, |address| match address {
+        0xFFFF => 0x4C,
+        0x0000 => 0x34,
+        0x0001 => 0x12,
+        _ => 0,
+    });
+    assert_eq!(instruction.to_string(), "JMP $1234");
+    assert_eq!(instruction.next_address(), 0x0002);
+}
@@ -0,0 +1,209 @@
+use lib::cartridge::{CartridgeParseError, Mirroring};
+use lib::fds::{DiskDiff, DiskImage, DiskSystem, SIDE_SIZE};
+use lib::Cartridge;
+
+/// A disk side holding one file of `file` bytes
+fn disk_side(file: &[u8]) -> Vec<u8> {
+    let mut side = b"\x01*NINTENDO-HVC*".to_vec();
+    side.resize(56, 0);
+    side.extend([2, 1]);
+    let mut header = vec![3, 0, 0];
+    header.extend(b"TESTFILE");
+    header.extend([0x00, 0x60]);
+    header.extend((file.len() as u16).to_le_bytes());
+    header.push(0);
+    side.extend(header);
+    side.push(4);
+    side.extend(file);
+    side.resize(SIDE_SIZE, 0);
+    side
+}
+
+fn disk_cartridge() -> Cartridge {
+    let image = [disk_side(&[0xAA; 100]), disk_side(&[0xBB; 10])].concat();
+    let mut bios = vec![0; DiskSystem::BIOS_SIZE];
+    bios[0x1FFC] = 0x34;
+    Cartridge::from_disk_image(&image, &bios).expect("valid disk image")
+}
+
+#[test]
+fn parses_images_with_and_without_header() {
+    let sides = [disk_side(&[1, 2, 3]), disk_side(&[4, 5])];
+    let headerless = sides.concat();
+    let mut headered = b"FDS\x1A\x02".to_vec();
+    headered.resize(16, 0);
+    headered.extend(&headerless);
+
+    for (bytes, has_header) in [(&headerless, false), (&headered, true)] {
+        let image = DiskImage::try_from(bytes.as_slice()).expect("valid disk image");
+        assert_eq!(image.has_header, has_header);
+        assert_eq!(image.sides, sides);
+        assert_eq!(&image.to_bytes(), bytes);
+    }
+
+    assert!(matches!(
+        Cartridge::try_from(headered.as_slice()),
+        Err(CartridgeParseError::DiskSystemNeedsBios)
+    ));
+    assert!(matches!(
+        Cartridge::from_disk_image(&headered, &[0; 100]),
+        Err(CartridgeParseError::InvalidBios(100))
+    ));
+    assert!(matches!(
+        DiskImage::try_from(&headered[..headered.len() - 1]),
+        Err(CartridgeParseError::DiskSideCutsOff(_))
+    ));
+    headered[16 + SIDE_SIZE] = 0;
+    assert!(matches!(
+        DiskImage::try_from(headered.as_slice()),
+        Err(CartridgeParseError::NotADiskSide(1))
+    ));
+}
+
+#[test]
+fn gaps_are_added_and_removed() {
+    let side = disk_side(&[0x80; 300]);
+    let drive_side = DiskImage::drive_side(&side);
+    assert!(drive_side.len() > SIDE_SIZE);
+    assert_eq!(DiskImage::side_from_drive(&drive_side), side);
+}
+
+#[test]
+fn disk_system_memory_map() {
+    let mut cartridge = disk_cartridge();
+
+    assert_eq!(cartridge.cpu_read(0xFFFC), 0x34);
+    for address in [0x6000, 0x8000, 0xDFFF] {
+        cartridge.cpu_write(address, 0x5A);
+        assert_eq!(cartridge.cpu_read(address), 0x5A);
+    }
+    cartridge.cpu_write(0xE000, 0x5A);
+    assert_eq!(cartridge.cpu_read(0xE000), 0);
+
+    cartridge.ppu_write(0x1000, 0x77);
+    assert_eq!(cartridge.ppu_read(0x1000), 0x77);
+
+    assert_eq!(cartridge.mirroring(), Mirroring::Vertical);
+    cartridge.cpu_write(0x4023, 0b01);
+    cartridge.cpu_write(0x4025, 0b0010_1000);
+    assert_eq!(cartridge.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn timer_irq() {
+    let mut cartridge = disk_cartridge();
+    cartridge.cpu_write(0x4023, 0b01);
+    cartridge.cpu_write(0x4020, 9);
+    cartridge.cpu_write(0x4021, 0);
+    cartridge.cpu_write(0x4022, 0b11);
+
+    for _ in 0..10 {
+        assert!(!cartridge.irq_pending());
+        cartridge.cpu_tick();
+    }
+    assert!(cartridge.irq_pending());
+    // Peeking doesn't acknowledge it
+    assert_eq!(cartridge.cpu_peek(0x4030) & 1, 1);
+    assert!(cartridge.irq_pending());
+    assert_eq!(cartridge.cpu_read(0x4030) & 1, 1);
+    assert!(!cartridge.irq_pending());
+
+    // Repeats
+    for _ in 0..10 {
+        cartridge.cpu_tick();
+    }
+    assert!(cartridge.irq_pending());
+
+    // Disabling disk I/O stops the timer
+    cartridge.cpu_write(0x4023, 0);
+    assert!(!cartridge.irq_pending());
+}
+
+#[test]
+fn drive_reads_blocks_after_start_mark() {
+    let mut cartridge = disk_cartridge();
+    cartridge.cpu_write(0x4023, 0b01);
+    assert_eq!(cartridge.cpu_read(0x4032) & 0b011, 0b010);
+
+    // Motor on, read mode, transfer enabled, IRQ per byte
+    cartridge.cpu_write(0x4025, 0b1100_0101);
+    let mut bytes = Vec::new();
+    let mut cycles = 0;
+    while bytes.len() < 15 {
+        cartridge.cpu_tick();
+        cycles += 1;
+        if cartridge.irq_pending() {
+            bytes.push(cartridge.cpu_read(0x4031));
+        }
+        assert!(cycles < 2_000_000, "drive never transferred data");
+    }
+    assert_eq!(bytes, b"\x01*NINTENDO-HVC*"[..15]);
+    assert_eq!(cartridge.cpu_read(0x4032) & 0b011, 0);
+}
+
+#[test]
+fn swapping_sides_ejects_first() {
+    let mut cartridge = disk_cartridge();
+    cartridge.cpu_write(0x4023, 0b01);
+    let disk_system = cartridge.disk_system.as_mut().expect("disk system");
+    assert_eq!(disk_system.side_count(), 2);
+    assert_eq!(disk_system.inserted_side(), Some(0));
+
+    disk_system.swap_side(1);
+    assert_eq!(disk_system.inserted_side(), None);
+    assert_eq!(cartridge.cpu_read(0x4032) & 1, 1);
+    for _ in 0..=DiskSystem::SWAP_CYCLES {
+        cartridge.cpu_tick();
+    }
+    let disk_system = cartridge.disk_system.as_ref().expect("disk system");
+    assert_eq!(disk_system.inserted_side(), Some(1));
+    assert_eq!(cartridge.cpu_read(0x4032) & 1, 0);
+}
+
+#[test]
+fn disk_diff_round_trip() {
+    let original: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mut modified = original.clone();
+    modified[10] = 0;
+    modified[12] = 0;
+    modified[900..950].fill(0xFF);
+
+    let diff = DiskDiff::diff(&original, &modified);
+    assert!(diff.len() < 100);
+    let mut patched = original.clone();
+    DiskDiff::apply(&diff, &mut patched).expect("valid diff");
+    assert_eq!(patched, modified);
+
+    assert!(DiskDiff::apply(&diff[..diff.len() - 1], &mut patched).is_err());
+    assert!(DiskDiff::apply(b"NOPE", &mut patched).is_err());
+}
+
+#[test]
+fn disk_writes_survive_save_state() {
+    let mut nes = lib::Nes::default();
+    let cartridge = std::rc::Rc::new(std::cell::RefCell::new(disk_cartridge()));
+    nes.insert_cartidge(Some(cartridge.clone()));
+    let state = nes.save_state();
+
+    let mut sides = cartridge.borrow().disk_system.as_ref().unwrap().sides();
+    // First byte of the file on side B
+    sides[1][56 + 2 + 16 + 1] = 0x42;
+    cartridge
+        .borrow_mut()
+        .disk_system
+        .as_mut()
+        .unwrap()
+        .load_sides(&sides);
+    let modified = nes.save_state();
+
+    nes.load_state(&state).expect("state should load");
+    assert_ne!(
+        cartridge.borrow().disk_system.as_ref().unwrap().sides(),
+        sides
+    );
+    nes.load_state(&modified).expect("state should load");
+    assert_eq!(
+        cartridge.borrow().disk_system.as_ref().unwrap().sides(),
+        sides
+    );
+}
@@ -0,0 +1,97 @@
+//! Klaus Dormann's 6502 test suite, run on flat memory so only the CPU is
+//! tested.
+//!
+//! `roms/6502_functional_test.bin` is the suite's own build of the
+//! functional test, in its default configuration. `roms/6502_decimal_test.bin`
+//! is assembled from `roms/6502_decimal_test.s`, which states its
+//! configuration at the top.
+//!
+//! <https://github.com/Klaus2m5/6502_65C02_functional_tests>
+
+use std::{fs, path::Path};
+
+use lib::{
+    cpu::{FlatMemory, Memory},
+    Cpu, Reset,
+};
+
+/// Where the functional test starts, in its default configuration
+const FUNCTIONAL_START: u16 = 0x0400;
+/// The functional test traps here once every test has passed
+const FUNCTIONAL_SUCCESS: u16 = 0x3469;
+/// The functional test keeps the number of the test running here
+const FUNCTIONAL_TEST_CASE: u16 = 0x0200;
+
+/// The decimal test is assembled to load and start here
+const DECIMAL_START: u16 = 0x0200;
+/// The decimal test leaves `0` here if it passed, and `1` if it failed
+const DECIMAL_ERROR: u16 = 0x000B;
+
+/// Far more than either test needs, in case one never traps
+const MAX_INSTRUCTIONS: u64 = 200_000_000;
+
+/// Read a test binary from `roms/`
+fn test_binary(name: &str) -> Vec<u8> {
+    fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("roms")
+            .join(name),
+    )
+    .unwrap_or_else(|e| panic!("roms/{name}: {e}"))
+}
+
+/// Run until the CPU jumps or branches to the instruction it is on, which is
+/// how the tests stop. Returns the address of that trap.
+fn run_until_trap<M: Memory>(cpu: &mut Cpu<M>) -> u16 {
+    for _ in 0..MAX_INSTRUCTIONS {
+        let address = cpu.program_counter;
+        cpu.step();
+        if cpu.program_counter == address {
+            return address;
+        }
+    }
+    panic!(
+        "no trap after {MAX_INSTRUCTIONS} instructions, at ${:04X}",
+        cpu.program_counter
+    );
+}
+
+/// A CPU with `binary` loaded at `load_address`, about to run from `start`.
+/// The suite tests a full 6502, so decimal mode is on.
+fn cpu_with_binary(binary: &[u8], load_address: u16, start: u16) -> Cpu<FlatMemory> {
+    let mut cpu = Cpu::with_memory(FlatMemory::with_program(load_address, binary));
+    cpu.decimal_mode = true;
+    cpu.reset();
+    cpu.program_counter = start;
+    // Let the reset sequence finish, so it isn't mistaken for a trap
+    cpu.step();
+    cpu
+}
+
+#[test]
+fn functional_test() {
+    let binary = test_binary("6502_functional_test.bin");
+
+    // A full 64K image
+    let mut cpu = cpu_with_binary(&binary, 0x0000, FUNCTIONAL_START);
+    let trap = run_until_trap(&mut cpu);
+    assert_eq!(
+        trap,
+        FUNCTIONAL_SUCCESS,
+        "trapped at ${trap:04X} in test ${:02X}, see the listing for what failed",
+        cpu.peek(FUNCTIONAL_TEST_CASE)
+    );
+}
+
+#[test]
+fn decimal_test() {
+    let binary = test_binary("6502_decimal_test.bin");
+
+    let mut cpu = cpu_with_binary(&binary, DECIMAL_START, DECIMAL_START);
+    let trap = run_until_trap(&mut cpu);
+    assert_eq!(
+        cpu.peek(DECIMAL_ERROR),
+        0,
+        "trapped at ${trap:04X} with a wrong result, see the listing for what failed"
+    );
+}
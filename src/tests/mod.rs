@@ -1 +1,52 @@
+#[cfg(test)]
+mod assembler;
+#[cfg(test)]
+mod cartridge;
+#[cfg(test)]
+mod checksum;
+#[cfg(test)]
+mod controllers;
+#[cfg(test)]
+mod cpu;
+#[cfg(test)]
+mod database;
+#[cfg(test)]
+mod disassembler;
+#[cfg(test)]
+mod fds;
+#[cfg(test)]
+mod klaus_dormann;
+#[cfg(test)]
+mod nestest;
+#[cfg(test)]
+mod patch;
+#[cfg(test)]
+mod savestate;
 
+/// Build an iNES image for an NROM cartridge with a single 16K PRG bank and
+/// a single 8K CHR bank.
+/// `program` is placed at the start of PRG ROM, the rest is zero filled.
+#[cfg(test)]
+pub fn nrom_image(program: &[u8]) -> Vec<u8> {
+    let mut image = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut prg = vec![0; 16 * 1024];
+    prg[..program.len()].copy_from_slice(program);
+    image.extend(prg);
+    image.extend((0..8 * 1024).map(|i| i as u8));
+    image
+}
+
+/// A machine with the cartridge in `image` inserted, before it is reset
+#[cfg(test)]
+pub fn nes_with_rom(image: &[u8]) -> lib::Nes {
+    let mut nes = lib::Nes::default();
+    let cartridge = lib::Cartridge::try_from(image).expect("valid test rom");
+    nes.insert_cartidge(Some(std::rc::Rc::new(std::cell::RefCell::new(cartridge))));
+    nes
+}
+
+/// Parse the header at the start of an image
+#[cfg(test)]
+pub fn header(image: &[u8]) -> lib::cartridge::Header {
+    lib::cartridge::Header::try_from(&image[..16]).expect("valid header")
+}
@@ -0,0 +1,71 @@
+use std::{fs, path::Path};
+
+use lib::{Nes, Reset};
+
+use super::{nes_with_rom, nrom_image};
+
+/// Reset the machine, then start the CPU at $C000. This is nestest's
+/// automation mode, which runs every test without needing a PPU.
+fn nes_at_c000(image: &[u8]) -> Nes {
+    let mut nes = nes_with_rom(image);
+    nes.reset();
+    nes.cpu_mut().program_counter = 0xC000;
+    nes
+}
+
+/// Run the machine until the CPU is about to fetch its next instruction
+fn next_instruction(nes: &mut Nes) {
+    loop {
+        // One CPU cycle
+        for _ in 0..3 {
+            nes.tick();
+        }
+        if nes.cpu_ref().is_between_instructions() {
+            return;
+        }
+    }
+}
+
+#[test]
+fn trace_lines_follow_the_nestest_format() {
+    let nes = &mut nes_at_c000(&nrom_image(&[
+        0x4C, 0x05, 0xC0, // JMP $C005
+        0x00, 0x00, //
+        0xA2, 0x00, // LDX #$00
+        0x86, 0x02, // STX $02
+        0xA7, 0x02, // LAX $02
+    ]));
+    let expected = [
+        "C000  4C 05 C0  JMP $C005                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+        "C005  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10",
+        "C007  86 02     STX $02 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12",
+        "C009  A7 02    *LAX $02 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 45 CYC:15",
+    ];
+
+    for line in expected {
+        next_instruction(nes);
+        assert_eq!(nes.cpu_mut().nestest_trace(), line);
+    }
+}
+
+/// Compares against the reference log line by line, stopping at the first
+/// line that differs. `roms/nestest.nes` and `roms/nestest.log` are kevtris'
+/// originals, from <https://www.nesdev.org/wiki/Emulator_tests>.
+#[test]
+fn nestest_matches_the_reference_log() {
+    let roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+    let rom = fs::read(roms.join("nestest.nes")).expect("roms/nestest.nes");
+    let log = fs::read_to_string(roms.join("nestest.log")).expect("roms/nestest.log");
+
+    let nes = &mut nes_at_c000(&rom);
+    for (number, expected) in log.lines().enumerate() {
+        next_instruction(nes);
+        let actual = nes.cpu_mut().nestest_trace();
+        assert_eq!(
+            actual,
+            expected.trim_end(),
+            "first difference is on line {} of nestest.log",
+            number + 1
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use lib::checksum::crc32;
+use lib::patch::{Patch, PatchError, PatchFormat};
+
+use super::nrom_image;
+
+/// Variable length number used by BPS and UPS
+fn number(mut value: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let bits = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(0x80 | bits);
+            return bytes;
+        }
+        bytes.push(bits);
+        value -= 1;
+    }
+}
+
+/// Add the source, target and patch CRC32s
+fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+    patch.extend(crc32(source).to_le_bytes());
+    patch.extend(crc32(target).to_le_bytes());
+    patch.extend(crc32(&patch).to_le_bytes());
+    patch
+}
+
+fn patch(format: PatchFormat, data: Vec<u8>) -> Patch {
+    Patch::new(format, "test".into(), data)
+}
+
+#[test]
+fn ips_writes_runs_and_truncates() {
+    let rom = vec![0; 16];
+    let mut ips = b"PATCH".to_vec();
+    // 2 bytes at 0x0002
+    ips.extend([0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);
+    // Run of 3 0xCC at 0x0008
+    ips.extend([0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x03, 0xCC]);
+    // 1 byte past the end
+    ips.extend([0x00, 0x00, 0x13, 0x00, 0x01, 0xDD]);
+    ips.extend(b"EOF");
+
+    let patched = patch(PatchFormat::Ips, ips.clone())
+        .apply(&rom)
+        .expect("valid patch");
+    assert_eq!(patched.len(), 20);
+    assert_eq!(&patched[..4], &[0, 0, 0xAA, 0xBB]);
+    assert_eq!(&patched[8..11], &[0xCC; 3]);
+    assert_eq!(patched[19], 0xDD);
+
+    ips.extend([0x00, 0x00, 0x0A]);
+    let truncated = patch(PatchFormat::Ips, ips.clone()).apply(&rom);
+    assert_eq!(truncated.map(|rom| rom.len()), Ok(10));
+
+    assert_eq!(
+        patch(PatchFormat::Ips, ips[..10].to_vec()).apply(&rom),
+        Err(PatchError::CutOff)
+    );
+    assert_eq!(
+        patch(PatchFormat::Ips, b"NOPE".to_vec()).apply(&rom),
+        Err(PatchError::WrongSignature(PatchFormat::Ips))
+    );
+}
+
+#[test]
+fn ups_xors_and_checks_crcs() {
+    let source = nrom_image(&[0xA9, 0x00]);
+    let mut target = source.clone();
+    target[17] = 0x42;
+    target[20] = 0x01;
+    target.push(0x99);
+
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(number(source.len()));
+    ups.extend(number(target.len()));
+    // Skip to offset 17, XOR one byte, then the terminating zero
+    ups.extend(number(17));
+    ups.extend([0x42, 0x00]);
+    // Offset 19 after the zero, skip one more
+    ups.extend(number(1));
+    ups.extend([0x01, 0x00]);
+    ups.extend(number(target.len() - 1 - 22));
+    ups.extend([0x99, 0x00]);
+    let ups = with_footer(ups, &source, &target);
+
+    let ups_patch = patch(PatchFormat::Ups, ups.clone());
+    assert_eq!(ups_patch.apply(&source), Ok(target.clone()));
+    assert!(matches!(
+        ups_patch.apply(&target),
+        Err(PatchError::SourceChecksum { .. })
+    ));
+
+    let mut corrupt = ups;
+    corrupt[10] ^= 1;
+    assert!(matches!(
+        patch(PatchFormat::Ups, corrupt).apply(&source),
+        Err(PatchError::PatchChecksum { .. })
+    ));
+}
+
+#[test]
+fn bps_runs_every_command() {
+    let source: Vec<u8> = (0..32).collect();
+    let command = |action: usize, length: usize| number(((length - 1) << 2) | action);
+
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(24));
+    bps.extend(number(3));
+    bps.extend(b"xyz");
+    // Source Read 4: 0 1 2 3
+    bps.extend(command(0, 4));
+    // Target Read 2: AA BB
+    bps.extend(command(1, 2));
+    bps.extend([0xAA, 0xBB]);
+    // Source Copy 4 from +20: 20 21 22 23
+    bps.extend(command(2, 4));
+    bps.extend(number(20 << 1));
+    // Source Copy 2 from -10 (now at 14): 14 15
+    bps.extend(command(2, 2));
+    bps.extend(number((10 << 1) | 1));
+    // Target Copy 12 from 4, overlapping to repeat AA BB 20 21 ...
+    bps.extend(command(3, 12));
+    bps.extend(number(4 << 1));
+
+    let mut target = vec![0, 1, 2, 3, 0xAA, 0xBB, 20, 21, 22, 23, 14, 15];
+    for i in 0..12 {
+        target.push(target[4 + i]);
+    }
+    let bps = with_footer(bps, &source, &target);
+
+    assert_eq!(
+        patch(PatchFormat::Bps, bps.clone()).apply(&source),
+        Ok(target)
+    );
+
+    let mut other_source = source.clone();
+    other_source[0] = 0xFF;
+    assert!(matches!(
+        patch(PatchFormat::Bps, bps).apply(&other_source),
+        Err(PatchError::SourceChecksum { .. })
+    ));
+}
+
+#[test]
+fn implausible_sizes_fail_before_allocating() {
+    let source: Vec<u8> = (0..32).collect();
+    let target = vec![0; 4];
+    let huge = usize::MAX >> 2;
+
+    // Target sizes far larger than any ROM
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(huge));
+    bps.extend(number(0));
+    let bps = with_footer(bps, &source, &target);
+    assert_eq!(
+        patch(PatchFormat::Bps, bps).apply(&source),
+        Err(PatchError::OutOfBounds(huge))
+    );
+
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(number(source.len()));
+    ups.extend(number(huge));
+    let ups = with_footer(ups, &source, &target);
+    assert_eq!(
+        patch(PatchFormat::Ups, ups).apply(&source),
+        Err(PatchError::OutOfBounds(huge))
+    );
+
+    // A Target Copy longer than the target
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(target.len()));
+    bps.extend(number(0));
+    bps.extend(number(0b01));
+    bps.push(0xAA);
+    bps.extend(number((huge << 2) | 0b11));
+    bps.extend(number(0));
+    let bps = with_footer(bps, &source, &target);
+    assert_eq!(
+        patch(PatchFormat::Bps, bps).apply(&source),
+        Err(PatchError::OutOfBounds(target.len()))
+    );
+
+    // A Target Read and metadata longer than the patch
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(target.len()));
+    bps.extend(number(usize::MAX));
+    let bps = with_footer(bps, &source, &target);
+    assert_eq!(
+        patch(PatchFormat::Bps, bps).apply(&source),
+        Err(PatchError::CutOff)
+    );
+
+    // A UPS skip past the end
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(number(source.len()));
+    ups.extend(number(source.len()));
+    ups.extend(number(usize::MAX));
+    ups.push(0x00);
+    let ups = with_footer(ups, &source, &source);
+    assert!(matches!(
+        patch(PatchFormat::Ups, ups).apply(&source),
+        Err(PatchError::OutOfBounds(_))
+    ));
+}
+
+#[test]
+fn patch_is_found_next_to_rom() {
+    let directory = std::env::temp_dir().join(format!("rust_nes_patch_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).expect("temp directory");
+    let rom = directory.join("game.nes");
+    assert!(Patch::for_rom(&rom).expect("no patch").is_none());
+
+    std::fs::write(directory.join("game.bps"), b"BPS1").expect("write patch");
+    let found = Patch::for_rom(&rom).expect("readable patch");
+    std::fs::remove_dir_all(&directory).expect("remove temp directory");
+
+    let found = found.expect("patch next to the rom");
+    assert_eq!(found.format, PatchFormat::Bps);
+    assert_eq!(found.path(), directory.join("game.bps"));
+    assert_eq!(found.apply(&[]), Err(PatchError::CutOff));
+}
@@ -0,0 +1,349 @@
+use std::path::PathBuf;
+
+use lib::{
+    controllers::{ControllerInput, PortDeviceKind},
+    egui::{format_timestamp, SaveSlots, SlotError, Thumbnail},
+    savestate::{Rewind, RewindConfig, RunAhead, SaveStateError, StateWriter},
+    Nes, Ppu,
+};
+
+use super::{nes_with_rom, nrom_image};
+
+/// Loops forever, incrementing X and writing it to RAM, the stack and the PPU.
+const PROGRAM: [u8; 13] = [
+    0xE8, // INX
+    0x8E, 0x00, 0x02, // STX $0200
+    0x8A, // TXA
+    0x48, // PHA
+    0x8D, 0x06, 0x20, // STA $2006
+    0x4C, 0x00, 0x00, // JMP $0000
+    0xEA, // NOP
+];
+
+/// Save slots in a fresh directory, removed when dropped
+struct TempSlots(PathBuf);
+
+impl TempSlots {
+    fn new(name: &str) -> Self {
+        let directory =
+            std::env::temp_dir().join(format!("rust-nes-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        Self(directory)
+    }
+
+    fn open(&self) -> SaveSlots {
+        SaveSlots::in_directory(self.0.clone())
+    }
+}
+
+impl Drop for TempSlots {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(nes: &mut Nes, ticks: usize) {
+    for _ in 0..ticks {
+        nes.tick();
+    }
+}
+
+#[test]
+fn save_state_round_trip_continues_identically() {
+    let image = nrom_image(&[]);
+    let mut original = nes_with_rom(&image);
+    for (address, &byte) in PROGRAM.iter().enumerate() {
+        original.bus.borrow_mut().write_cpu(address as u16, byte);
+    }
+
+    run(&mut original, 10_000);
+    let snapshot = original.save_state();
+    run(&mut original, 5_000);
+    let expected = original.save_state();
+
+    let mut restored = nes_with_rom(&image);
+    restored.load_state(&snapshot).expect("state should load");
+    assert_eq!(restored.save_state(), snapshot);
+
+    run(&mut restored, 5_000);
+    assert_eq!(restored.save_state(), expected);
+    assert_ne!(snapshot, expected);
+}
+
+#[test]
+fn save_state_rejects_other_rom() {
+    let nes = nes_with_rom(&nrom_image(&[]));
+    let snapshot = nes.save_state();
+
+    let mut other = nes_with_rom(&nrom_image(&[0xEA]));
+    assert!(matches!(
+        other.load_state(&snapshot),
+        Err(SaveStateError::RomMismatch { .. })
+    ));
+}
+
+#[test]
+fn save_state_rejects_other_version() {
+    let nes = nes_with_rom(&nrom_image(&[]));
+    let mut snapshot = nes.save_state();
+    snapshot[4] = snapshot[4].wrapping_add(1);
+
+    let mut restored = nes_with_rom(&nrom_image(&[]));
+    assert!(matches!(
+        restored.load_state(&snapshot),
+        Err(SaveStateError::UnsupportedVersion(_))
+    ));
+}
+
+#[test]
+fn save_state_rejects_partly_read_sections() {
+    let nes = nes_with_rom(&nrom_image(&[]));
+    let mut snapshot = nes.save_state();
+
+    // Grow the first section by a byte its component doesn't read
+    let length_at = StateWriter::HEADER_SIZE + 4;
+    let length = u32::from_le_bytes(snapshot[length_at..length_at + 4].try_into().unwrap());
+    snapshot[length_at..length_at + 4].copy_from_slice(&(length + 1).to_le_bytes());
+    snapshot.insert(length_at + 4 + length as usize, 0);
+
+    let mut restored = nes_with_rom(&nrom_image(&[]));
+    assert_eq!(
+        restored.load_state(&snapshot),
+        Err(SaveStateError::SectionLengthMismatch {
+            tag: *b"NES ",
+            expected: length as usize + 1,
+            read: length as usize,
+        })
+    );
+}
+
+#[test]
+fn save_state_holds_only_internal_ram() {
+    let nes = nes_with_rom(&nrom_image(&[]));
+    // 2K of CPU RAM plus the PPU, rather than the whole 64K address space
+    let without_cartridge = Nes::default().save_state().len();
+    assert!(without_cartridge < 32 * 1024, "{without_cartridge}");
+    assert!(nes.save_state().len() > without_cartridge);
+}
+
+#[test]
+fn save_state_leaves_the_nrom_board_alone() {
+    // 16K of PRG-ROM, mirrored into both halves of 0x8000-0xFFFF
+    let mut nes = nes_with_rom(&nrom_image(&[0xA9, 0x42]));
+    let snapshot = nes.save_state();
+    nes.load_state(&snapshot).expect("state should load");
+
+    let bus = nes.bus.borrow();
+    assert_eq!(bus.peek_cpu(0x8001), 0x42);
+    assert_eq!(bus.peek_cpu(0xC001), 0x42);
+}
+
+#[test]
+fn rewind_steps_back_through_every_frame() {
+    const FRAME_TICKS: u64 = 1_000;
+    let mut nes = nes_with_rom(&nrom_image(&[]));
+    for (address, &byte) in PROGRAM.iter().enumerate() {
+        nes.bus.borrow_mut().write_cpu(address as u16, byte);
+    }
+    let mut rewind = Rewind::new(RewindConfig {
+        interval: 4,
+        ..Default::default()
+    });
+
+    let mut states = vec![nes.save_state()];
+    for _ in 0..10 {
+        rewind.run_frame(&mut nes, ControllerInput::default(), FRAME_TICKS);
+        states.push(nes.save_state());
+    }
+    assert_eq!(rewind.frames_available(), 10);
+
+    states.pop();
+    while let Some(expected) = states.pop() {
+        assert!(rewind.step_back(&mut nes));
+        assert_eq!(nes.save_state(), expected);
+    }
+    assert!(!rewind.step_back(&mut nes));
+}
+
+#[test]
+fn run_ahead_leaves_machine_unchanged() {
+    let mut nes = nes_with_rom(&nrom_image(&[]));
+    for (address, &byte) in PROGRAM.iter().enumerate() {
+        nes.bus.borrow_mut().write_cpu(address as u16, byte);
+    }
+    nes.connect_port_device(1, PortDeviceKind::Arkanoid);
+    run(&mut nes, 3_000);
+    let before = nes.save_state();
+
+    // Speculative frames see different input from the real one
+    let input = ControllerInput {
+        pointer_x: 1.0,
+        pointer_down: true,
+        ..Default::default()
+    };
+    let mut run_ahead = RunAhead::new(2);
+    run_ahead.run_ahead(&mut nes, &input, 1_000);
+
+    assert_eq!(nes.save_state(), before);
+    assert!(!nes.audio_muted());
+
+    // A mute set by the frontend is left alone
+    nes.set_audio_muted(true);
+    run_ahead.run_ahead(&mut nes, &input, 1_000);
+    assert_eq!(nes.save_state(), before);
+    assert!(nes.audio_muted());
+}
+
+#[test]
+fn save_state_restores_port_devices() {
+    let mut nes = nes_with_rom(&nrom_image(&[]));
+    nes.connect_port_device(1, PortDeviceKind::Arkanoid);
+    nes.update_port_input(&ControllerInput {
+        pointer_x: 1.0,
+        ..Default::default()
+    });
+    let snapshot = nes.save_state();
+
+    // Same device, different state
+    nes.update_port_input(&ControllerInput::default());
+    nes.load_state(&snapshot).expect("state should load");
+    assert_eq!(nes.save_state(), snapshot);
+
+    // Different devices
+    nes.connect_port_device(0, PortDeviceKind::PowerPad);
+    nes.connect_port_device(1, PortDeviceKind::Disconnected);
+    nes.load_state(&snapshot).expect("state should load");
+    assert_eq!(nes.port_device_kind(0), PortDeviceKind::Disconnected);
+    assert_eq!(nes.port_device_kind(1), PortDeviceKind::Arkanoid);
+    assert_eq!(nes.save_state(), snapshot);
+}
+
+#[test]
+fn save_slot_round_trip() {
+    let directory = TempSlots::new("slot-round-trip");
+    let image = nrom_image(&[]);
+    let mut nes = nes_with_rom(&image);
+    for (address, &byte) in PROGRAM.iter().enumerate() {
+        nes.bus.borrow_mut().write_cpu(address as u16, byte);
+    }
+    run(&mut nes, 10_000);
+
+    let mut slots = directory.open();
+    assert!(slots.info(2).is_none());
+    assert!(matches!(
+        slots.load(2, &mut nes_with_rom(&image)),
+        Err(SlotError::Empty(2))
+    ));
+    slots.save(2, &nes).expect("slot should save");
+    let snapshot = nes.save_state();
+
+    // Read back from disk by a fresh set of slots
+    let slots = directory.open();
+    let info = slots.info(2).expect("slot 3 is filled");
+    assert!(info.timestamp > 0);
+    // The PPU has not drawn anything, so there is no picture
+    assert!(info.thumbnail.is_empty());
+    assert!((0..SaveSlots::SLOT_COUNT)
+        .filter(|&slot| slot != 2)
+        .all(|slot| slots.info(slot).is_none()));
+
+    let mut restored = nes_with_rom(&image);
+    slots.load(2, &mut restored).expect("slot should load");
+    assert_eq!(restored.save_state(), snapshot);
+}
+
+#[test]
+fn save_slot_errors_explain_mismatches() {
+    let directory = TempSlots::new("slot-mismatch");
+    let nes = nes_with_rom(&nrom_image(&[]));
+    let mut slots = directory.open();
+    slots.save(0, &nes).expect("slot should save");
+
+    let mut other = nes_with_rom(&nrom_image(&[0xEA]));
+    let error = slots.load(0, &mut other).expect_err("different rom");
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Save state is for a different ROM (CRC32 {:08X}, loaded ROM is {:08X})",
+            nes.rom_crc32(),
+            other.rom_crc32()
+        )
+    );
+
+    // Bump the version of the save state, which follows the slot's header
+    // and a thumbnail of no pixels
+    let path = directory.0.join("slot-1.state");
+    let mut contents = std::fs::read(&path).expect("slot file");
+    contents[17 + 4] = contents[17 + 4].wrapping_add(1);
+    std::fs::write(&path, contents.clone()).expect("slot file");
+    let error = slots
+        .load(0, &mut nes_with_rom(&nrom_image(&[])))
+        .expect_err("newer version");
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Save state is from version {}, but only version {} is supported",
+            StateWriter::VERSION + 1,
+            StateWriter::VERSION
+        )
+    );
+
+    // Bump the version of the slot file itself
+    contents[4] = contents[4].wrapping_add(1);
+    std::fs::write(&path, contents).expect("slot file");
+    let error = slots
+        .load(0, &mut nes_with_rom(&nrom_image(&[])))
+        .expect_err("newer slot version");
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Save slot is from version {}, but only version {} is supported",
+            SaveSlots::VERSION + 1,
+            SaveSlots::VERSION
+        )
+    );
+    assert!(directory.open().info(0).is_none());
+}
+
+#[test]
+fn thumbnails_shrink_the_frame() {
+    let mut frame = vec![0x0F; Ppu::FRAME_WIDTH * Ppu::FRAME_HEIGHT];
+    assert!(Thumbnail::from_frame(&frame).is_empty());
+
+    // Every 4th pixel of every 4th row is kept
+    frame[4 * Ppu::FRAME_WIDTH + 8] = 0x30;
+    frame[4 * Ppu::FRAME_WIDTH + 9] = 0x16;
+    let thumbnail = Thumbnail::from_frame(&frame);
+    assert_eq!([thumbnail.width, thumbnail.height], Thumbnail::SIZE);
+    assert_eq!(thumbnail.rgb.len(), 64 * 60 * 3);
+    let pixel = |x: usize, y: usize| &thumbnail.rgb[(y * 64 + x) * 3..][..3];
+    assert_eq!(pixel(2, 1), Ppu::PALETTE[0x30]);
+    assert_eq!(pixel(3, 1), Ppu::PALETTE[0x0F]);
+    assert_eq!(pixel(0, 0), Ppu::PALETTE[0x0F]);
+}
+
+#[test]
+fn save_slot_out_of_range_touches_no_files() {
+    let directory = TempSlots::new("slot-out-of-range");
+    let nes = nes_with_rom(&nrom_image(&[]));
+    let mut slots = directory.open();
+
+    let slot = SaveSlots::SLOT_COUNT;
+    assert!(matches!(
+        slots.save(slot, &nes),
+        Err(SlotError::OutOfRange(10))
+    ));
+    assert!(matches!(
+        slots.load(slot, &mut nes_with_rom(&nrom_image(&[]))),
+        Err(SlotError::OutOfRange(10))
+    ));
+    assert!(!directory.0.exists());
+}
+
+#[test]
+fn slot_timestamps_are_utc_dates() {
+    assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+    assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+    assert_eq!(format_timestamp(1_709_251_199), "2024-02-29 23:59:59 UTC");
+    assert_eq!(format_timestamp(4_102_444_800), "2100-01-01 00:00:00 UTC");
+}